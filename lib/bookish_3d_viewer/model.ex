@@ -10,7 +10,8 @@ defmodule Bookish3dViewer.Model do
     :bounds_max,
     :center,
     :file_path,
-    :file_size
+    :file_size,
+    :validation
   ]
 
   @type t :: %__MODULE__{
@@ -20,7 +21,8 @@ defmodule Bookish3dViewer.Model do
     bounds_max: {float(), float(), float()},
     center: {float(), float(), float()},
     file_path: String.t() | nil,
-    file_size: integer() | nil
+    file_size: integer() | nil,
+    validation: map() | nil
   }
 
   def new(info_string) when is_binary(info_string) do
@@ -43,10 +45,29 @@ defmodule Bookish3dViewer.Model do
       bounds_max: bounds_max,
       center: center,
       file_path: extract_file_path(file_info),
-      file_size: nil
+      file_size: nil,
+      validation: extract_validation(lines)
     }
   end
 
+  defp extract_validation(lines) do
+    case Enum.find(lines, fn line -> String.starts_with?(line, "Watertight:") end) do
+      nil -> nil
+      line ->
+        %{
+          watertight: String.trim(String.replace(line, "Watertight:", "")) == "yes",
+          boundary_edges: extract_integer(lines, "Boundary edges:"),
+          boundary_loops: extract_integer(lines, "Boundary loops:"),
+          non_manifold_edges: extract_integer(lines, "Non-manifold edges:"),
+          non_manifold_vertices: extract_integer(lines, "Non-manifold vertices:"),
+          degenerate_faces: extract_integer(lines, "Degenerate faces:"),
+          duplicate_faces: extract_integer(lines, "Duplicate faces:"),
+          inconsistent_winding_edges: extract_integer(lines, "Inconsistent winding edges:"),
+          components: extract_integer(lines, "Components:")
+        }
+    end
+  end
+
   defp extract_integer(lines, prefix) do
     case Enum.find(lines, fn line -> String.starts_with?(line, prefix) end) do
       nil -> 0
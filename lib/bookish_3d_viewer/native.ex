@@ -11,5 +11,11 @@ defmodule Bookish3dViewer.Native do
   def pan_camera(_resource, _delta_x, _delta_y), do: :erlang.nif_error(:nif_not_loaded)
   def reset_camera(_resource), do: :erlang.nif_error(:nif_not_loaded)
   def get_model_info(_resource), do: :erlang.nif_error(:nif_not_loaded)
+  def get_part_names(_resource), do: :erlang.nif_error(:nif_not_loaded)
+  def toggle_part(_resource, _index), do: :erlang.nif_error(:nif_not_loaded)
+  def export_model(_resource, _path, _format), do: :erlang.nif_error(:nif_not_loaded)
+  def set_unit(_resource, _unit), do: :erlang.nif_error(:nif_not_loaded)
+  def set_normal_mode(_resource, _mode), do: :erlang.nif_error(:nif_not_loaded)
+  def clean_up_mesh(_resource), do: :erlang.nif_error(:nif_not_loaded)
 end
 
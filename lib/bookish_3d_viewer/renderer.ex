@@ -5,6 +5,7 @@ defmodule Bookish3dViewer.Renderer do
 
   defstruct [
     :wireframe_mode,
+    :shading_mode,
     :flat_shading,
     :wireframe_color,
     :flat_color,
@@ -13,6 +14,8 @@ defmodule Bookish3dViewer.Renderer do
 
   @type t :: %__MODULE__{
     wireframe_mode: boolean(),
+    shading_mode: :faceted | :smooth | :unlit,
+    # Deprecated: true when shading_mode is :faceted.
     flat_shading: boolean(),
     wireframe_color: integer(),
     flat_color: integer(),
@@ -22,7 +25,8 @@ defmodule Bookish3dViewer.Renderer do
   def new do
     %__MODULE__{
       wireframe_mode: false,
-      flat_shading: true,
+      shading_mode: :smooth,
+      flat_shading: false,
       wireframe_color: 0x00FF00,
       flat_color: 0xFFBF00,
       background_color: 0x000000
@@ -33,8 +37,20 @@ defmodule Bookish3dViewer.Renderer do
     %{renderer | wireframe_mode: !renderer.wireframe_mode}
   end
 
+  def cycle_shading_mode(renderer) do
+    next =
+      case renderer.shading_mode do
+        :faceted -> :smooth
+        :smooth -> :unlit
+        :unlit -> :faceted
+      end
+
+    %{renderer | shading_mode: next, flat_shading: next == :faceted}
+  end
+
+  @deprecated "Use cycle_shading_mode/1 instead"
   def toggle_flat_shading(renderer) do
-    %{renderer | flat_shading: !renderer.flat_shading}
+    cycle_shading_mode(renderer)
   end
 
   def set_wireframe_color(renderer, color) do
@@ -49,4 +65,3 @@ defmodule Bookish3dViewer.Renderer do
     %{renderer | background_color: color}
   end
 end
-
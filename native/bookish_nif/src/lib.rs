@@ -9,6 +9,12 @@ rustler::init!("Elixir.Bookish3dViewer.Native", [
     pan_camera,
     reset_camera,
     get_model_info,
+    get_part_names,
+    toggle_part,
+    export_model,
+    set_unit,
+    set_normal_mode,
+    clean_up_mesh,
 ]);
 
 struct ViewerResource {
@@ -18,25 +24,15 @@ struct ViewerResource {
 #[rustler::nif]
 fn load_model(path: String) -> Result<Term, Error> {
     let mut app = bookish_3d_viewer::App::new();
-    
-    match std::fs::read(&path) {
-        Ok(data) => {
-            let ext = std::path::Path::new(&path)
-                .extension()
-                .and_then(|e| e.to_str())
-                .unwrap_or("");
-            
-            match app.load_file_from_bytes(&data, ext) {
-                Ok(_) => {
-                    let resource = ResourceArc::new(ViewerResource {
-                        app: Mutex::new(app),
-                    });
-                    Ok(rustler::types::atom::ok().encode(Env::current_env()))
-                }
-                Err(e) => Err(Error::Term(Box::new(format!("Failed to load model: {}", e))))
-            }
+
+    match app.load_model_from_path(&path) {
+        Ok(_) => {
+            let resource = ResourceArc::new(ViewerResource {
+                app: Mutex::new(app),
+            });
+            Ok(rustler::types::atom::ok().encode(Env::current_env()))
         }
-        Err(e) => Err(Error::Term(Box::new(format!("Failed to read file: {}", e))))
+        Err(e) => Err(Error::Term(Box::new(format!("Failed to load model: {}", e))))
     }
 }
 
@@ -70,10 +66,60 @@ fn reset_camera(resource: ResourceArc<ViewerResource>) -> Result<Term, Error> {
 
 #[rustler::nif]
 fn get_model_info(resource: ResourceArc<ViewerResource>) -> Result<Term, Error> {
-    let app = resource.app.lock().unwrap();
-    match &app.model_info {
-        Some(info) => Ok(info.format_info().encode(Env::current_env())),
+    let mut app = resource.app.lock().unwrap();
+    match app.info_text() {
+        Some(info) => Ok(info.encode(Env::current_env())),
         None => Ok("No model loaded".encode(Env::current_env()))
     }
 }
 
+
+#[rustler::nif]
+fn get_part_names(resource: ResourceArc<ViewerResource>) -> Result<Term, Error> {
+    let app = resource.app.lock().unwrap();
+    Ok(app.part_names().encode(Env::current_env()))
+}
+
+#[rustler::nif]
+fn toggle_part(resource: ResourceArc<ViewerResource>, index: usize) -> Result<Term, Error> {
+    let mut app = resource.app.lock().unwrap();
+    Ok(app.toggle_part(index).encode(Env::current_env()))
+}
+
+#[rustler::nif]
+fn export_model(resource: ResourceArc<ViewerResource>, path: String, format: String) -> Result<Term, Error> {
+    let app = resource.app.lock().unwrap();
+    let format = bookish_3d_viewer::export::ExportFormat::from_name(&format)
+        .ok_or_else(|| Error::Term(Box::new(format!("Unknown export format: {}", format))))?;
+    match app.export_model(&path, format) {
+        Ok(_) => Ok(rustler::types::atom::ok().encode(Env::current_env())),
+        Err(e) => Err(Error::Term(Box::new(format!("Failed to export model: {}", e))))
+    }
+}
+
+#[rustler::nif]
+fn set_unit(resource: ResourceArc<ViewerResource>, unit: String) -> Result<Term, Error> {
+    let mut app = resource.app.lock().unwrap();
+    let unit = bookish_3d_viewer::units::Unit::from_name(&unit)
+        .ok_or_else(|| Error::Term(Box::new(format!("Unknown unit: {}", unit))))?;
+    app.set_unit(unit);
+    Ok(rustler::types::atom::ok().encode(Env::current_env()))
+}
+
+#[rustler::nif]
+fn set_normal_mode(resource: ResourceArc<ViewerResource>, mode: String) -> Result<Term, Error> {
+    let mut app = resource.app.lock().unwrap();
+    let mode = bookish_3d_viewer::normals::NormalMode::from_name(&mode)
+        .ok_or_else(|| Error::Term(Box::new(format!("Unknown normal mode: {}", mode))))?;
+    app.set_normal_mode(mode);
+    Ok(rustler::types::atom::ok().encode(Env::current_env()))
+}
+
+#[rustler::nif]
+fn clean_up_mesh(resource: ResourceArc<ViewerResource>) -> Result<Term, Error> {
+    let mut app = resource.app.lock().unwrap();
+    match app.clean_up_mesh() {
+        Some(report) => Ok(report.format_report().encode(Env::current_env())),
+        None => Err(Error::Term(Box::new("No model loaded")))
+    }
+}
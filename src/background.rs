@@ -0,0 +1,38 @@
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum BackgroundStyle {
+    #[default]
+    Solid,
+    Gradient,
+    Blueprint,
+    Ground,
+}
+
+impl BackgroundStyle {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "solid" => Some(BackgroundStyle::Solid),
+            "gradient" => Some(BackgroundStyle::Gradient),
+            "blueprint" => Some(BackgroundStyle::Blueprint),
+            "ground" => Some(BackgroundStyle::Ground),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BackgroundStyle::Solid => "solid",
+            BackgroundStyle::Gradient => "gradient",
+            BackgroundStyle::Blueprint => "blueprint",
+            BackgroundStyle::Ground => "ground",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            BackgroundStyle::Solid => BackgroundStyle::Gradient,
+            BackgroundStyle::Gradient => BackgroundStyle::Blueprint,
+            BackgroundStyle::Blueprint => BackgroundStyle::Ground,
+            BackgroundStyle::Ground => BackgroundStyle::Solid,
+        }
+    }
+}
@@ -0,0 +1,274 @@
+use glam::Vec3;
+use crate::model::Model;
+
+const LEAF_FACES: usize = 4;
+const RAY_EPSILON: f32 = 1e-8;
+
+#[derive(Debug, Clone, Copy)]
+pub struct RayHit {
+    pub face: usize,
+    pub barycentric: Vec3,
+    pub point: Vec3,
+    pub distance: f32,
+}
+
+impl RayHit {
+    pub fn nearest_corner(&self) -> usize {
+        let [a, b, c] = self.barycentric.to_array();
+        if a >= b && a >= c {
+            0
+        } else if b >= c {
+            1
+        } else {
+            2
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct BvhNode {
+    min: Vec3,
+    max: Vec3,
+    /// First face for leaves, left child for interior nodes (right is `first + 1`).
+    first: u32,
+    count: u32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Bvh {
+    nodes: Vec<BvhNode>,
+    faces: Vec<u32>,
+    pub revision: u64,
+}
+
+fn triangle(model: &Model, face: usize) -> [Vec3; 3] {
+    let corner = |i: usize| model.vertices[model.indices[face * 3 + i] as usize].position_vec3();
+    [corner(0), corner(1), corner(2)]
+}
+
+/// Möller–Trumbore intersection. Returns the distance and the barycentric
+/// weights of the hit, from both sides of the triangle.
+pub fn ray_triangle(origin: Vec3, direction: Vec3, [a, b, c]: [Vec3; 3]) -> Option<(f32, Vec3)> {
+    let ab = b - a;
+    let ac = c - a;
+    let p = direction.cross(ac);
+    let det = ab.dot(p);
+    if det.abs() < RAY_EPSILON {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+    let s = origin - a;
+    let u = s.dot(p) * inv_det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = s.cross(ab);
+    let v = direction.dot(q) * inv_det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let t = ac.dot(q) * inv_det;
+    (t > 0.0).then_some((t, Vec3::new(1.0 - u - v, u, v)))
+}
+
+fn ray_box(origin: Vec3, inv_direction: Vec3, min: Vec3, max: Vec3, max_distance: f32) -> bool {
+    let t0 = (min - origin) * inv_direction;
+    let t1 = (max - origin) * inv_direction;
+    let near = t0.min(t1).max_element();
+    let far = t0.max(t1).min_element();
+    near <= far && far >= 0.0 && near <= max_distance
+}
+
+impl Bvh {
+    pub fn new(model: &Model) -> Self {
+        let face_count = model.indices.len() / 3;
+        let mut bvh = Self {
+            nodes: Vec::with_capacity(face_count.max(1) * 2 / LEAF_FACES + 1),
+            faces: (0..face_count as u32).collect(),
+            revision: model.revision,
+        };
+        if face_count == 0 {
+            return bvh;
+        }
+        let bounds: Vec<(Vec3, Vec3, Vec3)> = (0..face_count)
+            .map(|face| {
+                let [a, b, c] = triangle(model, face);
+                let min = a.min(b).min(c);
+                let max = a.max(b).max(c);
+                (min, max, (min + max) * 0.5)
+            })
+            .collect();
+        bvh.nodes.push(BvhNode {
+            min: Vec3::ZERO,
+            max: Vec3::ZERO,
+            first: 0,
+            count: face_count as u32,
+        });
+        bvh.subdivide(0, &bounds);
+        bvh
+    }
+
+    fn subdivide(&mut self, node: usize, bounds: &[(Vec3, Vec3, Vec3)]) {
+        let BvhNode { first, count, .. } = self.nodes[node];
+        let faces = &mut self.faces[first as usize..(first + count) as usize];
+        let (mut min, mut max) = (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN));
+        let (mut centroid_min, mut centroid_max) = (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN));
+        for &face in faces.iter() {
+            let (face_min, face_max, centroid) = bounds[face as usize];
+            min = min.min(face_min);
+            max = max.max(face_max);
+            centroid_min = centroid_min.min(centroid);
+            centroid_max = centroid_max.max(centroid);
+        }
+        self.nodes[node].min = min;
+        self.nodes[node].max = max;
+        if faces.len() <= LEAF_FACES {
+            return;
+        }
+
+        // Median split along the longest axis of the centroids.
+        let extent = centroid_max - centroid_min;
+        let axis = if extent.x >= extent.y && extent.x >= extent.z {
+            0
+        } else if extent.y >= extent.z {
+            1
+        } else {
+            2
+        };
+        let middle = faces.len() / 2;
+        faces.select_nth_unstable_by(middle, |a, b| {
+            bounds[*a as usize].2[axis].total_cmp(&bounds[*b as usize].2[axis])
+        });
+
+        let left = self.nodes.len();
+        self.nodes.push(BvhNode {
+            min,
+            max,
+            first,
+            count: middle as u32,
+        });
+        self.nodes.push(BvhNode {
+            min,
+            max,
+            first: first + middle as u32,
+            count: count - middle as u32,
+        });
+        self.nodes[node].first = left as u32;
+        self.nodes[node].count = 0;
+        self.subdivide(left, bounds);
+        self.subdivide(left + 1, bounds);
+    }
+
+    pub fn intersect(&self, model: &Model, origin: Vec3, direction: Vec3, include: impl Fn(usize) -> bool) -> Option<RayHit> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+        // Axis-aligned rays would give 0 * inf = NaN on slabs touching the ray.
+        let inv_direction = Vec3::select(direction.cmpeq(Vec3::ZERO), Vec3::splat(RAY_EPSILON), direction).recip();
+        let mut closest: Option<RayHit> = None;
+        let mut stack = vec![0usize];
+        while let Some(index) = stack.pop() {
+            let node = self.nodes[index];
+            let max_distance = closest.map(|hit| hit.distance).unwrap_or(f32::MAX);
+            if !ray_box(origin, inv_direction, node.min, node.max, max_distance) {
+                continue;
+            }
+            if node.count == 0 {
+                stack.push(node.first as usize);
+                stack.push(node.first as usize + 1);
+                continue;
+            }
+            for &face in &self.faces[node.first as usize..(node.first + node.count) as usize] {
+                let face = face as usize;
+                if !include(face) {
+                    continue;
+                }
+                let Some((distance, barycentric)) = ray_triangle(origin, direction, triangle(model, face)) else {
+                    continue;
+                };
+                if distance < closest.map(|hit| hit.distance).unwrap_or(f32::MAX) {
+                    closest = Some(RayHit {
+                        face,
+                        barycentric,
+                        point: origin + direction * distance,
+                        distance,
+                    });
+                }
+            }
+        }
+        closest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_meshes::cube;
+
+    #[test]
+    fn ray_triangle_hits_from_both_sides_only_in_front() {
+        let triangle = [Vec3::ZERO, Vec3::X, Vec3::Y];
+        let (distance, barycentric) = ray_triangle(Vec3::new(0.25, 0.5, 2.0), -Vec3::Z, triangle).unwrap();
+        assert!((distance - 2.0).abs() < 1e-6);
+        assert!((barycentric - Vec3::new(0.25, 0.25, 0.5)).length() < 1e-6);
+        assert!(ray_triangle(Vec3::new(0.25, 0.5, -2.0), Vec3::Z, triangle).is_some());
+        assert!(ray_triangle(Vec3::new(0.25, 0.5, 2.0), Vec3::Z, triangle).is_none());
+        assert!(ray_triangle(Vec3::new(0.75, 0.75, 2.0), -Vec3::Z, triangle).is_none());
+        assert!(ray_triangle(Vec3::new(0.25, 0.25, 0.0), Vec3::X, triangle).is_none());
+    }
+
+    #[test]
+    fn finds_the_nearest_face_and_skips_excluded_ones() {
+        let model = cube();
+        let bvh = Bvh::new(&model);
+        let origin = Vec3::new(-1.0, 0.1, 0.9);
+        let hit = bvh.intersect(&model, origin, Vec3::X, |_| true).unwrap();
+        assert_eq!(hit.face, 8);
+        assert!((hit.distance - 1.0).abs() < 1e-6);
+        assert!((hit.point - Vec3::new(0.0, 0.1, 0.9)).length() < 1e-6);
+        assert_eq!(model.indices[hit.face * 3 + hit.nearest_corner()], 4);
+
+        let hit = bvh.intersect(&model, origin, Vec3::X, |face| face != 8 && face != 9).unwrap();
+        assert_eq!(hit.face, 11);
+        assert!((hit.distance - 2.0).abs() < 1e-6);
+        assert!(bvh.intersect(&model, origin, -Vec3::X, |_| true).is_none());
+    }
+
+    #[test]
+    fn matches_a_brute_force_search() {
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        for k in 0..27 {
+            let offset = Vec3::new((k % 3) as f32, (k / 3 % 3) as f32, (k / 9) as f32) * 1.5;
+            let base = vertices.len() as u32;
+            vertices.extend(cube().vertices.iter().map(|v| {
+                let mut v = *v;
+                v.position = (v.position_vec3() + offset).to_array();
+                v
+            }));
+            indices.extend(cube().indices.iter().map(|i| i + base));
+        }
+        let model = Model::from_mesh(vertices, indices, Vec::new());
+        let bvh = Bvh::new(&model);
+        let origin = Vec3::new(-2.0, -1.5, -3.0);
+        for k in 0..200 {
+            let target = Vec3::new((k % 10) as f32 * 0.4, (k / 10 % 5) as f32 * 0.8, (k / 50) as f32 * 1.1);
+            let direction = (target - origin).normalize();
+            let expected = (0..model.indices.len() / 3)
+                .filter_map(|face| ray_triangle(origin, direction, triangle(&model, face)))
+                .map(|(distance, _)| distance)
+                .min_by(f32::total_cmp);
+            let hit = bvh.intersect(&model, origin, direction, |_| true).map(|hit| hit.distance);
+            match (hit, expected) {
+                (Some(hit), Some(expected)) => assert!((hit - expected).abs() < 1e-4, "ray towards {}", target),
+                _ => assert_eq!(hit, expected, "ray towards {}", target),
+            }
+        }
+    }
+
+    #[test]
+    fn empty_models_have_nothing_to_hit() {
+        let model = Model::from_mesh(Vec::new(), Vec::new(), Vec::new());
+        assert!(Bvh::new(&model).intersect(&model, Vec3::ZERO, Vec3::X, |_| true).is_none());
+    }
+}
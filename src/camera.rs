@@ -1,4 +1,4 @@
-use glam::{Mat4, Vec3};
+use glam::{Mat4, Vec2, Vec3};
 
 pub struct Camera {
     pub position: Vec3,
@@ -79,19 +79,48 @@ impl Camera {
         right.cross(forward).normalize()
     }
 
-    pub fn get_view_matrix(&self) -> Mat4 {
+    pub fn eye_position(&self) -> Vec3 {
         let cos_x = self.rotation_x.cos();
         let sin_x = self.rotation_x.sin();
         let cos_y = self.rotation_y.cos();
         let sin_y = self.rotation_y.sin();
 
-        let eye = Vec3::new(
+        Vec3::new(
             self.distance * cos_x * sin_y + self.pan_x,
             self.distance * sin_x + self.pan_y,
             self.distance * cos_x * cos_y,
+        )
+    }
+
+    pub fn get_view_matrix(&self) -> Mat4 {
+        Mat4::look_at_rh(self.eye_position(), self.target, self.up)
+    }
+
+    /// World-space ray through a cursor position given in pixels from the
+    /// top-left corner of a viewport of `viewport` pixels.
+    pub fn cursor_ray(&self, cursor: Vec2, viewport: Vec2) -> (Vec3, Vec3) {
+        let ndc = Vec2::new(
+            cursor.x / viewport.x.max(1.0) * 2.0 - 1.0,
+            1.0 - cursor.y / viewport.y.max(1.0) * 2.0,
         );
+        let inverse = (self.get_projection_matrix() * self.get_view_matrix()).inverse();
+        let near = inverse.project_point3(ndc.extend(0.0));
+        let far = inverse.project_point3(ndc.extend(1.0));
+        (near, (far - near).normalize())
+    }
+
+    pub fn project(&self, point: Vec3, viewport: Vec2) -> Option<Vec2> {
+        let clip = self.get_projection_matrix() * self.get_view_matrix() * point.extend(1.0);
+        if clip.w <= 1e-6 {
+            return None;
+        }
+        let ndc = clip.truncate().truncate() / clip.w;
+        Some(Vec2::new((ndc.x + 1.0) * 0.5 * viewport.x, (1.0 - ndc.y) * 0.5 * viewport.y))
+    }
 
-        Mat4::look_at_rh(eye, self.target, self.up)
+    pub fn world_per_pixel(&self, point: Vec3, viewport_height: f32) -> f32 {
+        let depth = (point - self.eye_position()).dot((self.target - self.eye_position()).normalize());
+        2.0 * depth.max(self.near) * (self.fov * 0.5).tan() / viewport_height.max(1.0)
     }
 
     pub fn get_projection_matrix(&self) -> Mat4 {
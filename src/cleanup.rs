@@ -0,0 +1,426 @@
+use glam::{IVec3, Vec3};
+use std::collections::{HashMap, HashSet, VecDeque};
+use crate::model::{BaseMesh, ModelPart};
+use crate::normals::{self, face_normal, NormalWeighting};
+use crate::utils::position_key;
+
+const NORMAL_EPSILON: f32 = 1e-4;
+
+type WeldKey = (Option<usize>, u32, [u32; 2], [u32; 4]);
+
+#[derive(Debug, Clone, Default)]
+pub struct CleanupReport {
+    pub welded_vertices: usize,
+    pub unused_vertices: usize,
+    pub degenerate_faces: usize,
+    pub duplicate_faces: usize,
+    pub flipped_faces: usize,
+    pub changed_normals: usize,
+}
+
+impl CleanupReport {
+    pub fn is_empty(&self) -> bool {
+        self.welded_vertices == 0
+            && self.unused_vertices == 0
+            && self.degenerate_faces == 0
+            && self.duplicate_faces == 0
+            && self.flipped_faces == 0
+            && self.changed_normals == 0
+    }
+
+    pub fn format_report(&self) -> String {
+        format!(
+            "Welded vertices: {}\nUnused vertices removed: {}\nDegenerate faces removed: {}\nDuplicate faces removed: {}\nFaces flipped: {}\nNormals changed: {}",
+            self.welded_vertices,
+            self.unused_vertices,
+            self.degenerate_faces,
+            self.duplicate_faces,
+            self.flipped_faces,
+            self.changed_normals,
+        )
+    }
+}
+
+/// Cleans up a mesh part by part: welds vertices closer than `tolerance`
+/// that share texture coordinates and colors, drops zero-area and duplicate
+/// faces and vertices no face uses, makes the winding of connected faces
+/// agree (outwards on closed pieces) and recomputes smooth vertex normals.
+pub fn clean_up(mesh: &BaseMesh, tolerance: f32, weighting: NormalWeighting) -> (BaseMesh, CleanupReport) {
+    let mut report = CleanupReport::default();
+    let positions: Vec<Vec3> = mesh.vertices.iter().map(|v| v.position_vec3()).collect();
+    let groups = position_groups(&positions, tolerance);
+    let vertex_parts = ranges_of(&mesh.parts, mesh.vertices.len(), |p| (p.vertex_offset, p.vertex_count));
+    let face_parts = ranges_of(&mesh.parts, mesh.indices.len() / 3, |p| (p.index_offset / 3, p.index_count / 3));
+
+    // Texture seams and color borders stay split.
+    let mut welds: HashMap<WeldKey, u32> = HashMap::new();
+    let weld: Vec<u32> = mesh
+        .vertices
+        .iter()
+        .enumerate()
+        .map(|(i, v)| {
+            let key = (vertex_parts[i], groups[i], v.uv.map(f32::to_bits), v.color.map(f32::to_bits));
+            *welds.entry(key).or_insert(i as u32)
+        })
+        .collect();
+    report.welded_vertices = weld.iter().enumerate().filter(|&(i, &w)| w as usize != i).count();
+
+    let mut faces: Vec<Option<[u32; 3]>> = Vec::with_capacity(mesh.indices.len() / 3);
+    let mut seen = HashSet::new();
+    for (face, tri) in mesh.indices.chunks_exact(3).enumerate() {
+        let corners = [weld[tri[0] as usize], weld[tri[1] as usize], weld[tri[2] as usize]];
+        let ids = corners.map(|c| groups[c as usize]);
+        let [a, b, c] = corners.map(|c| positions[c as usize]);
+        if ids[0] == ids[1] || ids[1] == ids[2] || ids[0] == ids[2] || face_normal(a, b, c).is_none() {
+            report.degenerate_faces += 1;
+            faces.push(None);
+            continue;
+        }
+        let mut sorted = ids;
+        sorted.sort_unstable();
+        if !seen.insert((face_parts[face], sorted)) {
+            report.duplicate_faces += 1;
+            faces.push(None);
+            continue;
+        }
+        faces.push(Some(corners));
+    }
+
+    let flips = unify_winding(&faces, &face_parts, &groups, &positions);
+    for (face, flip) in faces.iter_mut().zip(flips) {
+        if let (Some(corners), true) = (face, flip) {
+            corners.swap(1, 2);
+            report.flipped_faces += 1;
+        }
+    }
+
+    // Point clouds, and parts without faces, keep all their vertices.
+    let used: HashSet<u32> = faces.iter().flatten().flatten().copied().collect();
+    let points = |i: usize| match vertex_parts[i] {
+        Some(part) => mesh.parts[part].index_count == 0,
+        None => mesh.indices.is_empty(),
+    };
+    let keep_vertex = |i: usize| weld[i] as usize == i && (points(i) || used.contains(&(i as u32)));
+    report.unused_vertices = (0..mesh.vertices.len())
+        .filter(|&i| weld[i] as usize == i && !keep_vertex(i))
+        .count();
+    let kept: Vec<usize> = (0..mesh.vertices.len()).filter(|&i| keep_vertex(i)).collect();
+    let mut new_index = vec![0u32; mesh.vertices.len()];
+    for (new, &old) in kept.iter().enumerate() {
+        new_index[old] = new as u32;
+    }
+
+    let mut vertices: Vec<_> = kept.iter().map(|&i| mesh.vertices[i]).collect();
+    let indices: Vec<u32> = faces.iter().flatten().flatten().map(|&c| new_index[c as usize]).collect();
+    if !indices.is_empty() {
+        let positions: Vec<Vec3> = vertices.iter().map(|v| v.position_vec3()).collect();
+        for (vertex, normal) in vertices.iter_mut().zip(normals::vertex_normals(&positions, &indices, weighting)) {
+            if (vertex.normal_vec3() - normal).length() > NORMAL_EPSILON {
+                report.changed_normals += 1;
+            }
+            vertex.normal = normal.to_array();
+        }
+    }
+
+    let vertices_before = prefix_counts(mesh.vertices.len(), keep_vertex);
+    let faces_before = prefix_counts(faces.len(), |face| faces[face].is_some());
+    let parts = mesh
+        .parts
+        .iter()
+        .map(|part| {
+            let vertex_range = part.vertex_offset as usize..(part.vertex_offset + part.vertex_count) as usize;
+            let face_range = part.index_offset as usize / 3..(part.index_offset + part.index_count) as usize / 3;
+            ModelPart {
+                vertex_offset: vertices_before[vertex_range.start] as u32,
+                vertex_count: (vertices_before[vertex_range.end] - vertices_before[vertex_range.start]) as u32,
+                index_offset: faces_before[face_range.start] as u32 * 3,
+                index_count: (faces_before[face_range.end] - faces_before[face_range.start]) as u32 * 3,
+                ..part.clone()
+            }
+        })
+        .collect();
+
+    let cleaned = BaseMesh {
+        vertices,
+        indices,
+        parts,
+        vertex_colors: mesh
+            .vertex_colors
+            .as_ref()
+            .map(|colors| kept.iter().filter_map(|&i| colors.get(i).copied()).collect()),
+        scalar_values: mesh
+            .scalar_values
+            .iter()
+            .map(|values| kept.iter().filter_map(|&i| values.get(i).copied()).collect())
+            .collect(),
+    };
+    (cleaned, report)
+}
+
+fn position_groups(positions: &[Vec3], tolerance: f32) -> Vec<u32> {
+    if tolerance <= 0.0 {
+        let mut ids: HashMap<[u32; 3], u32> = HashMap::new();
+        return positions
+            .iter()
+            .enumerate()
+            .map(|(i, &p)| *ids.entry(position_key(p)).or_insert(i as u32))
+            .collect();
+    }
+
+    let cell_of = |p: Vec3| (p / tolerance).floor().as_ivec3();
+    let mut cells: HashMap<IVec3, Vec<u32>> = HashMap::new();
+    let mut groups = Vec::with_capacity(positions.len());
+    for (i, &p) in positions.iter().enumerate() {
+        let cell = cell_of(p);
+        let near = (-1..=1)
+            .flat_map(|x| (-1..=1).flat_map(move |y| (-1..=1).map(move |z| cell + IVec3::new(x, y, z))))
+            .filter_map(|neighbor| cells.get(&neighbor))
+            .flatten()
+            .copied()
+            .find(|&first| positions[first as usize].distance(p) <= tolerance);
+        match near {
+            Some(first) => groups.push(first),
+            None => {
+                cells.entry(cell).or_default().push(i as u32);
+                groups.push(i as u32);
+            }
+        }
+    }
+    groups
+}
+
+fn ranges_of(parts: &[ModelPart], count: usize, range: impl Fn(&ModelPart) -> (u32, u32)) -> Vec<Option<usize>> {
+    let mut owners = vec![None; count];
+    for (index, part) in parts.iter().enumerate() {
+        let (offset, length) = range(part);
+        for owner in owners.iter_mut().skip(offset as usize).take(length as usize) {
+            *owner = Some(index);
+        }
+    }
+    owners
+}
+
+fn prefix_counts(count: usize, keep: impl Fn(usize) -> bool) -> Vec<usize> {
+    let mut counts = Vec::with_capacity(count + 1);
+    let mut kept = 0;
+    counts.push(0);
+    for i in 0..count {
+        kept += keep(i) as usize;
+        counts.push(kept);
+    }
+    counts
+}
+
+/// Which faces to flip so that faces sharing an edge run along it in
+/// opposite directions. Each connected piece keeps the winding most of its
+/// faces have, or faces outwards when it is closed.
+fn unify_winding(faces: &[Option<[u32; 3]>], face_parts: &[Option<usize>], groups: &[u32], positions: &[Vec3]) -> Vec<bool> {
+    let ids = |face: usize| faces[face].map(|corners| corners.map(|c| groups[c as usize]));
+    let mut edges: HashMap<(Option<usize>, u32, u32), Vec<usize>> = HashMap::new();
+    for (face, &part) in face_parts.iter().enumerate() {
+        let Some(ids) = ids(face) else {
+            continue;
+        };
+        for k in 0..3 {
+            let (a, b) = (ids[k], ids[(k + 1) % 3]);
+            edges.entry((part, a.min(b), a.max(b))).or_default().push(face);
+        }
+    }
+    let runs_along = |face: usize, a: u32, b: u32| {
+        ids(face).is_some_and(|ids| (0..3).any(|k| ids[k] == a && ids[(k + 1) % 3] == b))
+    };
+
+    let mut flip = vec![false; faces.len()];
+    let mut visited = vec![false; faces.len()];
+    for seed in 0..faces.len() {
+        if visited[seed] || faces[seed].is_none() {
+            continue;
+        }
+        visited[seed] = true;
+        let mut piece = vec![seed];
+        let mut queue = VecDeque::from([seed]);
+        let mut closed = true;
+        while let Some(face) = queue.pop_front() {
+            let Some(ids) = ids(face) else {
+                continue;
+            };
+            for k in 0..3 {
+                let (mut a, mut b) = (ids[k], ids[(k + 1) % 3]);
+                if flip[face] {
+                    std::mem::swap(&mut a, &mut b);
+                }
+                let users = &edges[&(face_parts[face], a.min(b), a.max(b))];
+                if users.len() != 2 {
+                    // Open or non-manifold: nothing to agree with across it.
+                    closed = false;
+                    continue;
+                }
+                let other = if users[0] == face { users[1] } else { users[0] };
+                if !visited[other] {
+                    visited[other] = true;
+                    flip[other] = runs_along(other, a, b);
+                    piece.push(other);
+                    queue.push_back(other);
+                }
+            }
+        }
+
+        let flipped = piece.iter().filter(|&&face| flip[face]).count();
+        let reverse = if closed {
+            let volume: f32 = piece
+                .iter()
+                .filter_map(|&face| {
+                    let [a, b, c] = faces[face]?.map(|corner| positions[corner as usize]);
+                    let volume = a.dot(b.cross(c));
+                    Some(if flip[face] { -volume } else { volume })
+                })
+                .sum();
+            volume < 0.0
+        } else {
+            flipped * 2 > piece.len()
+        };
+        if reverse {
+            for &face in &piece {
+                flip[face] = !flip[face];
+            }
+        }
+    }
+    flip
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Vertex;
+    use crate::test_meshes::{cube_vertices, CUBE_INDICES};
+    use crate::validation;
+
+    fn mesh(vertices: Vec<Vertex>, indices: Vec<u32>, parts: Vec<ModelPart>) -> BaseMesh {
+        BaseMesh {
+            vertices,
+            indices,
+            parts,
+            vertex_colors: None,
+            scalar_values: Vec::new(),
+        }
+    }
+
+    fn split_cube() -> (Vec<Vertex>, Vec<u32>) {
+        let vertices = CUBE_INDICES.iter().map(|&i| cube_vertices()[i as usize]).collect();
+        (vertices, (0..36).collect())
+    }
+
+    fn assert_outward_cube(mesh: &BaseMesh) {
+        let report = validation::validate(&mesh.vertices, &mesh.indices);
+        assert!(report.is_clean(), "{}", report.format_report());
+        for tri in mesh.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|k| mesh.vertices[tri[k] as usize].position_vec3());
+            let outward = (a + b + c) / 3.0 - Vec3::splat(0.5);
+            assert!(face_normal(a, b, c).unwrap().dot(outward) > 0.0);
+        }
+        for vertex in &mesh.vertices {
+            let diagonal = (vertex.position_vec3() - Vec3::splat(0.5)).normalize();
+            assert!(vertex.normal_vec3().dot(diagonal) > 0.9);
+        }
+    }
+
+    #[test]
+    fn repairs_a_cube_with_duplicated_and_flipped_faces() {
+        let (vertices, mut indices) = split_cube();
+        indices.swap(1, 2);
+        indices.swap(31, 32);
+        indices.extend_from_slice(&[3, 5, 4]);
+        indices.extend_from_slice(&[10, 11, 11]);
+
+        let (cleaned, report) = clean_up(&mesh(vertices, indices, Vec::new()), 0.0, NormalWeighting::Angle);
+        assert_eq!(report.welded_vertices, 28);
+        assert_eq!(report.unused_vertices, 0);
+        assert_eq!(report.duplicate_faces, 1);
+        assert_eq!(report.degenerate_faces, 1);
+        assert_eq!(report.flipped_faces, 2);
+        assert_eq!(report.changed_normals, 8);
+        assert_eq!((cleaned.vertices.len(), cleaned.indices.len()), (8, 36));
+        assert_outward_cube(&cleaned);
+    }
+
+    #[test]
+    fn turns_an_inside_out_cube_outwards() {
+        let mut indices = CUBE_INDICES.to_vec();
+        for tri in indices.chunks_exact_mut(3) {
+            tri.swap(1, 2);
+        }
+        let (cleaned, report) = clean_up(&mesh(cube_vertices(), indices, Vec::new()), 0.0, NormalWeighting::Angle);
+        assert_eq!(report.flipped_faces, 12);
+        assert_outward_cube(&cleaned);
+
+        let (_, report) = clean_up(&cleaned, 0.0, NormalWeighting::Angle);
+        assert!(report.is_empty(), "{}", report.format_report());
+    }
+
+    #[test]
+    fn welds_within_the_tolerance_and_keeps_texture_seams() {
+        let (mut vertices, indices) = split_cube();
+        vertices[0].position[0] += 1e-4;
+        let (cleaned, report) = clean_up(&mesh(vertices.clone(), indices.clone(), Vec::new()), 1e-3, NormalWeighting::Area);
+        assert_eq!(report.welded_vertices, 28);
+        assert_eq!(cleaned.vertices.len(), 8);
+
+        let (cleaned, _) = clean_up(&mesh(vertices.clone(), indices.clone(), Vec::new()), 0.0, NormalWeighting::Area);
+        assert_eq!(cleaned.vertices.len(), 9);
+
+        vertices[1].uv = [0.5, 0.5];
+        let (cleaned, _) = clean_up(&mesh(vertices, indices, Vec::new()), 1e-3, NormalWeighting::Area);
+        assert_eq!(cleaned.vertices.len(), 9);
+    }
+
+    #[test]
+    fn keeps_the_vertices_of_point_parts() {
+        let mut vertices = cube_vertices();
+        vertices.extend([Vec3::splat(3.0), Vec3::splat(4.0)].map(|p| Vertex::new(p, Vec3::ZERO)));
+        vertices.push(Vertex::new(Vec3::splat(5.0), Vec3::ZERO));
+        let part = |name: &str, vertex_offset, vertex_count, index_offset, index_count| ModelPart {
+            name: name.to_string(),
+            vertex_offset,
+            vertex_count,
+            index_offset,
+            index_count,
+            material_id: None,
+            visible: true,
+        };
+        let parts = vec![part("mesh", 0, 8, 0, 36), part("points", 8, 2, 36, 0)];
+
+        let (cleaned, report) = clean_up(&mesh(vertices, CUBE_INDICES.to_vec(), parts), 0.0, NormalWeighting::Area);
+        assert_eq!(report.unused_vertices, 1);
+        assert_eq!(cleaned.vertices.len(), 10);
+        assert_eq!((cleaned.parts[1].vertex_offset, cleaned.parts[1].vertex_count), (8, 2));
+        assert_eq!(cleaned.vertices[9].position, [4.0; 3]);
+    }
+
+    #[test]
+    fn keeps_parts_apart_and_updates_their_ranges() {
+        let (mut vertices, indices) = split_cube();
+        vertices.extend(cube_vertices());
+        let mut indices: Vec<u32> = indices.iter().copied().chain(CUBE_INDICES.iter().map(|i| i + 36)).collect();
+        indices.extend_from_slice(&[36, 38, 39]);
+        let part = |name: &str, vertex_offset, vertex_count, index_offset, index_count| ModelPart {
+            name: name.to_string(),
+            vertex_offset,
+            vertex_count,
+            index_offset,
+            index_count,
+            material_id: None,
+            visible: true,
+        };
+        let parts = vec![part("split", 0, 36, 0, 36), part("shared", 36, 8, 36, 39)];
+
+        let (cleaned, report) = clean_up(&mesh(vertices, indices, parts), 0.0, NormalWeighting::Area);
+        assert_eq!(report.welded_vertices, 28);
+        assert_eq!(report.duplicate_faces, 1);
+        let ranges: Vec<[u32; 4]> = cleaned
+            .parts
+            .iter()
+            .map(|p| [p.vertex_offset, p.vertex_count, p.index_offset, p.index_count])
+            .collect();
+        assert_eq!(ranges, [[0, 8, 0, 36], [8, 8, 36, 36]]);
+    }
+}
@@ -66,6 +66,12 @@ pub struct ColorPicker {
     pub background_color: Color,
 }
 
+impl Default for ColorPicker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ColorPicker {
     pub fn new() -> Self {
         Self {
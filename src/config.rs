@@ -1,17 +1,49 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
+use crate::lighting::LightingRig;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
     pub wireframe_color: u32,
     pub flat_color: u32,
     pub background_color: u32,
+    pub background_style: String,
+    pub background_top_color: u32,
+    pub grid_color: u32,
+    pub grid_spacing: f32,
     pub camera_sensitivity: f32,
     pub zoom_sensitivity: f32,
     pub pan_sensitivity: f32,
     pub default_fov: f32,
     pub recent_files: Vec<String>,
+    pub export_format: String,
+    pub export_recalculate_normals: bool,
+    pub unit: String,
+    pub normal_mode: String,
+    pub normal_weighting: String,
+    pub crease_angle: f32,
+    pub density: f32,
+    pub wireframe_thickness: f32,
+    pub wireframe_edges: String,
+    pub feature_angle: f32,
+    pub hidden_line_dashed: bool,
+    pub display_mode: String,
+    pub shading_mode: String,
+    pub selection_mode: String,
+    pub selection_shape: String,
+    pub select_visible_only: bool,
+    pub select_normal_angle: f32,
+    pub snapping: bool,
+    pub snap_translate: f32,
+    pub snap_rotate: f32,
+    pub snap_scale: f32,
+    pub history_memory_mb: f32,
+    pub weld_tolerance: f32,
+    pub lighting_rig: String,
+    pub headlight: bool,
+    pub lighting_rigs: Vec<LightingRig>,
 }
 
 impl Default for Config {
@@ -20,11 +52,41 @@ impl Default for Config {
             wireframe_color: 0x00FF00,
             flat_color: 0xFFBF00,
             background_color: 0x000000,
+            background_style: String::from("solid"),
+            background_top_color: 0x1A2A4A,
+            grid_color: 0x4F86C6,
+            grid_spacing: 0.25,
             camera_sensitivity: 0.01,
             zoom_sensitivity: 0.1,
             pan_sensitivity: 0.001,
             default_fov: 45.0,
             recent_files: Vec::new(),
+            export_format: String::from("obj"),
+            export_recalculate_normals: false,
+            unit: String::from("mm"),
+            normal_mode: String::from("file"),
+            normal_weighting: String::from("area"),
+            crease_angle: 30.0,
+            density: 1.0,
+            wireframe_thickness: 1.0,
+            wireframe_edges: String::from("all"),
+            feature_angle: 30.0,
+            hidden_line_dashed: false,
+            display_mode: String::from("shaded"),
+            shading_mode: String::from("smooth"),
+            selection_mode: String::from("face"),
+            selection_shape: String::from("box"),
+            select_visible_only: true,
+            select_normal_angle: 15.0,
+            snapping: false,
+            snap_translate: 1.0,
+            snap_rotate: 15.0,
+            snap_scale: 0.1,
+            history_memory_mb: 64.0,
+            weld_tolerance: 0.0001,
+            lighting_rig: String::from("default"),
+            headlight: false,
+            lighting_rigs: LightingRig::defaults(),
         }
     }
 }
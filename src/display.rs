@@ -0,0 +1,81 @@
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DisplayMode {
+    #[default]
+    Shaded,
+    Wireframe,
+    HiddenLine,
+    ShadedWireframe,
+}
+
+impl DisplayMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "shaded" => Some(DisplayMode::Shaded),
+            "wireframe" => Some(DisplayMode::Wireframe),
+            "hidden_line" => Some(DisplayMode::HiddenLine),
+            "shaded_wireframe" => Some(DisplayMode::ShadedWireframe),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DisplayMode::Shaded => "shaded",
+            DisplayMode::Wireframe => "wireframe",
+            DisplayMode::HiddenLine => "hidden_line",
+            DisplayMode::ShadedWireframe => "shaded_wireframe",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            DisplayMode::Shaded => DisplayMode::Wireframe,
+            DisplayMode::Wireframe => DisplayMode::HiddenLine,
+            DisplayMode::HiddenLine => DisplayMode::ShadedWireframe,
+            DisplayMode::ShadedWireframe => DisplayMode::Shaded,
+        }
+    }
+
+    pub fn shows_surfaces(&self) -> bool {
+        *self != DisplayMode::Wireframe
+    }
+
+    pub fn shows_edges(&self) -> bool {
+        *self != DisplayMode::Shaded
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ShadingMode {
+    Faceted,
+    #[default]
+    Smooth,
+    Unlit,
+}
+
+impl ShadingMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "faceted" => Some(ShadingMode::Faceted),
+            "smooth" => Some(ShadingMode::Smooth),
+            "unlit" => Some(ShadingMode::Unlit),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ShadingMode::Faceted => "faceted",
+            ShadingMode::Smooth => "smooth",
+            ShadingMode::Unlit => "unlit",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            ShadingMode::Faceted => ShadingMode::Smooth,
+            ShadingMode::Smooth => ShadingMode::Unlit,
+            ShadingMode::Unlit => ShadingMode::Faceted,
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use bytemuck::{Pod, Zeroable};
+use std::collections::HashMap;
+use std::ops::Range;
+use crate::model::Model;
+use crate::normals::face_normal;
+use crate::utils::position_key;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub struct EdgeInstance {
+    pub start: [f32; 3],
+    pub end: [f32; 3],
+    pub color: [f32; 4],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EdgeFilter {
+    All,
+    Feature,
+    Boundary,
+}
+
+impl EdgeFilter {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "all" => Some(EdgeFilter::All),
+            "feature" => Some(EdgeFilter::Feature),
+            "boundary" => Some(EdgeFilter::Boundary),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EdgeFilter::All => "all",
+            EdgeFilter::Feature => "feature",
+            EdgeFilter::Boundary => "boundary",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            EdgeFilter::All => EdgeFilter::Feature,
+            EdgeFilter::Feature => EdgeFilter::Boundary,
+            EdgeFilter::Boundary => EdgeFilter::All,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct EdgeSet {
+    pub edges: Vec<EdgeInstance>,
+    pub ranges: Vec<Range<u32>>,
+}
+
+struct EdgeUse {
+    start: u32,
+    end: u32,
+    faces: u32,
+    normals: [Option<glam::Vec3>; 2],
+}
+
+pub fn extract(model: &Model, filter: EdgeFilter, feature_angle: f32) -> EdgeSet {
+    let mut ids: HashMap<[u32; 3], u32> = HashMap::new();
+    let position_ids: Vec<u32> = model
+        .vertices
+        .iter()
+        .map(|v| {
+            let next = ids.len() as u32;
+            *ids.entry(position_key(v.position_vec3())).or_insert(next)
+        })
+        .collect();
+    let cos_feature = feature_angle.to_radians().cos();
+
+    let mut set = EdgeSet::default();
+    for part in &model.parts {
+        let first = set.edges.len() as u32;
+        let mut lookup: HashMap<(u32, u32), usize> = HashMap::new();
+        let mut uses: Vec<EdgeUse> = Vec::new();
+
+        for tri in model.indices[part.index_range().start as usize..part.index_range().end as usize].chunks_exact(3) {
+            let [a, b, c] = [tri[0], tri[1], tri[2]].map(|i| model.vertices[i as usize].position_vec3());
+            let normal = face_normal(a, b, c);
+            for k in 0..3 {
+                let (start, end) = (tri[k], tri[(k + 1) % 3]);
+                let (ps, pe) = (position_ids[start as usize], position_ids[end as usize]);
+                if ps == pe {
+                    continue;
+                }
+                let index = *lookup.entry((ps.min(pe), ps.max(pe))).or_insert_with(|| {
+                    uses.push(EdgeUse {
+                        start,
+                        end,
+                        faces: 0,
+                        normals: [None, None],
+                    });
+                    uses.len() - 1
+                });
+                let edge = &mut uses[index];
+                if edge.faces < 2 {
+                    edge.normals[edge.faces as usize] = normal;
+                }
+                edge.faces += 1;
+            }
+        }
+
+        for edge in uses {
+            let keep = match filter {
+                EdgeFilter::All => true,
+                EdgeFilter::Boundary => edge.faces == 1,
+                EdgeFilter::Feature => match (edge.faces, edge.normals) {
+                    (2, [Some(n0), Some(n1)]) => n0.dot(n1) < cos_feature,
+                    (2, _) => false,
+                    _ => true,
+                },
+            };
+            if keep {
+                let start = &model.vertices[edge.start as usize];
+                let end = &model.vertices[edge.end as usize];
+                set.edges.push(EdgeInstance {
+                    start: start.position,
+                    end: end.position,
+                    color: start.color,
+                });
+            }
+        }
+
+        set.ranges.push(first..set.edges.len() as u32);
+    }
+    set
+}
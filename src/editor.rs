@@ -1,69 +1,191 @@
-use crate::transform::Transform;
+use glam::Vec3;
+use crate::transform::{Transform, TransformTool};
 use crate::selection::Selection;
 use crate::color_picker::ColorPicker;
+use crate::mesh_edit::MeshCommand;
+use crate::model::Model;
+
+pub const DEFAULT_HISTORY_BUDGET: usize = 64 * 1024 * 1024;
 
 pub struct Editor {
     pub transform: Transform,
+    /// Transform of each object (model part), applied before `transform`.
+    pub object_transforms: Vec<Transform>,
     pub selection: Selection,
     pub color_picker: ColorPicker,
-    pub history: Vec<EditorState>,
+    pub tool: TransformTool,
+    pub history: Vec<Command>,
+    /// Number of commands in `history` that are applied; the rest can be redone.
     pub history_index: usize,
-    pub max_history: usize,
+    pub history_budget: usize,
+    saved: EditorState,
 }
 
 #[derive(Clone)]
 pub struct EditorState {
     transform: Transform,
+    object_transforms: Vec<Transform>,
+}
+
+impl EditorState {
+    fn memory_size(&self) -> usize {
+        std::mem::size_of::<Self>() + self.object_transforms.len() * std::mem::size_of::<Transform>()
+    }
+}
+
+pub enum Command {
+    Transform { before: EditorState, after: EditorState },
+    Mesh(MeshCommand),
+}
+
+impl Command {
+    pub fn name(&self) -> &str {
+        match self {
+            Command::Transform { .. } => "transform",
+            Command::Mesh(command) => &command.name,
+        }
+    }
+
+    pub fn memory_size(&self) -> usize {
+        match self {
+            Command::Transform { before, after } => before.memory_size() + after.memory_size(),
+            Command::Mesh(command) => command.memory_size(),
+        }
+    }
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Editor {
     pub fn new() -> Self {
         Self {
             transform: Transform::new(),
+            object_transforms: Vec::new(),
             selection: Selection::new(),
             color_picker: ColorPicker::new(),
-            history: vec![EditorState {
-                transform: Transform::new(),
-            }],
+            tool: TransformTool::None,
+            history: Vec::new(),
             history_index: 0,
-            max_history: 100,
+            history_budget: DEFAULT_HISTORY_BUDGET,
+            saved: EditorState {
+                transform: Transform::new(),
+                object_transforms: Vec::new(),
+            },
         }
     }
 
-    pub fn save_state(&mut self) {
-        let state = EditorState {
+    fn state(&self) -> EditorState {
+        EditorState {
             transform: self.transform.clone(),
-        };
+            object_transforms: self.object_transforms.clone(),
+        }
+    }
+
+    fn restore(&mut self, state: EditorState) {
+        self.transform = state.transform.clone();
+        self.object_transforms = state.object_transforms.clone();
+        self.saved = state;
+    }
 
-        self.history.truncate(self.history_index + 1);
-        self.history.push(state);
+    pub fn save_state(&mut self) {
+        let after = self.state();
+        let before = std::mem::replace(&mut self.saved, after.clone());
+        self.record(Command::Transform { before, after });
+    }
+
+    pub fn record(&mut self, command: Command) {
+        self.history.truncate(self.history_index);
+        self.history.push(command);
         self.history_index += 1;
 
-        if self.history.len() > self.max_history {
-            self.history.remove(0);
+        let mut used: usize = self.history.iter().map(Command::memory_size).sum();
+        while used > self.history_budget && self.history.len() > 1 {
+            used -= self.history.remove(0).memory_size();
             self.history_index -= 1;
         }
     }
 
-    pub fn undo(&mut self) -> bool {
-        if self.history_index > 0 {
-            self.history_index -= 1;
-            let state = &self.history[self.history_index];
-            self.transform = state.transform.clone();
-            true
-        } else {
-            false
+    pub fn undo(&mut self, model: Option<&mut Model>) -> Option<String> {
+        if self.history_index == 0 {
+            return None;
+        }
+        self.history_index -= 1;
+        match &self.history[self.history_index] {
+            Command::Transform { before, .. } => {
+                let state = before.clone();
+                self.restore(state);
+            }
+            Command::Mesh(command) => {
+                if let Some(model) = model {
+                    command.revert(model);
+                }
+            }
+        }
+        Some(self.history[self.history_index].name().to_string())
+    }
+
+    pub fn redo(&mut self, model: Option<&mut Model>) -> Option<String> {
+        if self.history_index >= self.history.len() {
+            return None;
+        }
+        match &self.history[self.history_index] {
+            Command::Transform { after, .. } => {
+                let state = after.clone();
+                self.restore(state);
+            }
+            Command::Mesh(command) => {
+                if let Some(model) = model {
+                    command.apply(model);
+                }
+            }
+        }
+        self.history_index += 1;
+        Some(self.history[self.history_index - 1].name().to_string())
+    }
+
+    fn saved_states(&mut self) -> impl Iterator<Item = &mut EditorState> {
+        let recorded = self.history.iter_mut().flat_map(|command| match command {
+            Command::Transform { before, after } => vec![before, after],
+            Command::Mesh(_) => Vec::new(),
+        });
+        std::iter::once(&mut self.saved).chain(recorded)
+    }
+
+    pub fn set_pivot(&mut self, pivot: Vec3) {
+        self.transform.pivot = pivot;
+        for state in self.saved_states() {
+            state.transform.pivot = pivot;
         }
     }
 
-    pub fn redo(&mut self) -> bool {
-        if self.history_index < self.history.len() - 1 {
-            self.history_index += 1;
-            let state = &self.history[self.history_index];
-            self.transform = state.transform.clone();
-            true
-        } else {
-            false
+    pub fn add_objects(&mut self, pivots: impl IntoIterator<Item = Vec3>) {
+        let added: Vec<Transform> = pivots
+            .into_iter()
+            .map(|pivot| Transform {
+                pivot,
+                ..Transform::new()
+            })
+            .collect();
+        self.object_transforms.extend(added.iter().cloned());
+        for state in self.saved_states() {
+            state.object_transforms.extend(added.iter().cloned());
+        }
+    }
+
+    pub fn transform_of(&self, object: Option<usize>) -> &Transform {
+        object
+            .and_then(|index| self.object_transforms.get(index))
+            .unwrap_or(&self.transform)
+    }
+
+    pub fn transform_of_mut(&mut self, object: Option<usize>) -> &mut Transform {
+        match object {
+            Some(index) if index < self.object_transforms.len() => &mut self.object_transforms[index],
+            _ => &mut self.transform,
         }
     }
 
@@ -72,13 +194,15 @@ impl Editor {
     }
 
     pub fn can_redo(&self) -> bool {
-        self.history_index < self.history.len() - 1
+        self.history_index < self.history.len()
     }
 
     pub fn reset(&mut self) {
         self.transform.reset();
+        for transform in &mut self.object_transforms {
+            transform.reset();
+        }
         self.selection.clear();
         self.save_state();
     }
 }
-
@@ -0,0 +1,526 @@
+use glam::{Mat3, Mat4, Vec3};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+use std::io::Write;
+use std::path::Path;
+use crate::material::TextureData;
+use crate::model::{Model, ModelPart};
+use crate::normals::{self, NormalMode, NormalSettings};
+use crate::transform::Transform;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Obj,
+    StlBinary,
+    StlAscii,
+    Ply,
+}
+
+impl ExportFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "obj" => Some(ExportFormat::Obj),
+            "stl" | "stl_binary" => Some(ExportFormat::StlBinary),
+            "stl_ascii" => Some(ExportFormat::StlAscii),
+            "ply" => Some(ExportFormat::Ply),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportFormat::Obj => "obj",
+            ExportFormat::StlBinary => "stl_binary",
+            ExportFormat::StlAscii => "stl_ascii",
+            ExportFormat::Ply => "ply",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Obj => "obj",
+            ExportFormat::StlBinary | ExportFormat::StlAscii => "stl",
+            ExportFormat::Ply => "ply",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            ExportFormat::Obj => ExportFormat::StlBinary,
+            ExportFormat::StlBinary => ExportFormat::StlAscii,
+            ExportFormat::StlAscii => ExportFormat::Ply,
+            ExportFormat::Ply => ExportFormat::Obj,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions {
+    pub transform: Option<Transform>,
+    pub recalculate_normals: bool,
+}
+
+struct ExportMesh {
+    positions: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<[f32; 2]>,
+    colors: Option<Vec<[f32; 4]>>,
+    scalar_values: Vec<Vec<f32>>,
+    indices: Vec<u32>,
+    parts: Vec<ModelPart>,
+}
+
+impl ExportMesh {
+    fn triangles(&self) -> impl Iterator<Item = (Vec3, [Vec3; 3])> + '_ {
+        self.indices.chunks_exact(3).map(|tri| {
+            let corners = [
+                self.positions[tri[0] as usize],
+                self.positions[tri[1] as usize],
+                self.positions[tri[2] as usize],
+            ];
+            let normal = (corners[1] - corners[0]).cross(corners[2] - corners[0]).normalize_or_zero();
+            (normal, corners)
+        })
+    }
+}
+
+/// Exports the mesh as loaded rather than the render mesh, which flat and
+/// crease normals split at every sharp corner.
+fn prepare(model: &Model, options: &ExportOptions) -> ExportMesh {
+    let base = model.base_mesh();
+    let settings = NormalSettings {
+        mode: if model.normal_settings.mode == NormalMode::File { NormalMode::File } else { NormalMode::Smooth },
+        ..model.normal_settings
+    };
+    let smoothed = normals::compute(&base.vertices, &base.indices, &base.parts, &settings);
+    let mut positions: Vec<Vec3> = base.vertices.iter().map(|v| v.position_vec3()).collect();
+    let mut normals: Vec<Vec3> = smoothed.vertices.iter().map(|v| v.normal_vec3()).collect();
+    let mut indices = base.indices;
+
+    for (index, part) in base.parts.iter().enumerate() {
+        let matrix = model.part_transform(index);
+        if matrix == Mat4::IDENTITY {
+            continue;
+        }
+        let normal_matrix = Mat3::from_mat4(matrix).inverse().transpose();
+        let vertices = part.vertex_offset as usize..(part.vertex_offset + part.vertex_count) as usize;
+        for p in &mut positions[vertices.clone()] {
+            *p = matrix.transform_point3(*p);
+        }
+        for n in &mut normals[vertices] {
+            *n = (normal_matrix * *n).normalize_or_zero();
+        }
+        if matrix.determinant() < 0.0 {
+            let start = part.index_offset as usize;
+            for tri in indices[start..start + part.index_count as usize].chunks_exact_mut(3) {
+                tri.swap(1, 2);
+            }
+        }
+    }
+
+    if let Some(transform) = &options.transform {
+        let matrix = transform.to_matrix();
+        let normal_matrix = Mat3::from_mat4(matrix).inverse().transpose();
+        for p in &mut positions {
+            *p = matrix.transform_point3(*p);
+        }
+        for n in &mut normals {
+            *n = (normal_matrix * *n).normalize_or_zero();
+        }
+        if matrix.determinant() < 0.0 {
+            for tri in indices.chunks_exact_mut(3) {
+                tri.swap(1, 2);
+            }
+        }
+    }
+
+    if options.recalculate_normals {
+        normals = normals::vertex_normals(&positions, &indices, model.normal_settings.weighting);
+    }
+
+    ExportMesh {
+        positions,
+        normals,
+        uvs: base.vertices.iter().map(|v| v.uv).collect(),
+        colors: base.vertex_colors,
+        scalar_values: base.scalar_values,
+        indices,
+        parts: base.parts,
+    }
+}
+
+fn encode(model: &Model, mesh: &ExportMesh, format: ExportFormat, mtllib: Option<&str>) -> Vec<u8> {
+    match format {
+        ExportFormat::Obj => write_obj(model, mesh, mtllib).into_bytes(),
+        ExportFormat::StlBinary => write_stl_binary(mesh),
+        ExportFormat::StlAscii => write_stl_ascii(model, mesh).into_bytes(),
+        ExportFormat::Ply => write_ply(model, mesh),
+    }
+}
+
+pub fn export_to_bytes(model: &Model, format: ExportFormat, options: &ExportOptions) -> Vec<u8> {
+    encode(model, &prepare(model, options), format, None)
+}
+
+pub fn export_model(
+    model: &Model,
+    path: &str,
+    format: ExportFormat,
+    options: &ExportOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mesh = prepare(model, options);
+    let mtllib = if format == ExportFormat::Obj && !model.materials.is_empty() {
+        let mtl_path = Path::new(path).with_extension("mtl");
+        let texture_names = texture_file_names(model);
+        for (material, name) in model.materials.iter().zip(&texture_names) {
+            if let (Some(texture), Some(name)) = (&material.diffuse_texture, name) {
+                let texture_path = mtl_path.with_file_name(name);
+                image::save_buffer(&texture_path, &texture.rgba, texture.width, texture.height, image::ColorType::Rgba8)?;
+            }
+        }
+        std::fs::write(&mtl_path, write_mtl(model, &texture_names))?;
+        mtl_path.file_name().and_then(|n| n.to_str()).map(str::to_string)
+    } else {
+        None
+    };
+    let bytes = encode(model, &mesh, format, mtllib.as_deref());
+
+    let mut file = std::fs::File::create(path)?;
+    file.write_all(&bytes)?;
+    Ok(())
+}
+
+fn write_obj(model: &Model, mesh: &ExportMesh, mtllib: Option<&str>) -> String {
+    let mut out = String::new();
+    let has_uv = mesh.uvs.iter().any(|uv| *uv != [0.0, 0.0]);
+    let has_normals = mesh.normals.iter().any(|n| *n != Vec3::ZERO);
+
+    let _ = writeln!(out, "# Exported by bookish-3d-viewer");
+    if let Some(mtllib) = mtllib {
+        let _ = writeln!(out, "mtllib {}", mtllib);
+    }
+
+    for (i, p) in mesh.positions.iter().enumerate() {
+        match mesh.colors.as_ref().and_then(|colors| colors.get(i)) {
+            Some(c) => {
+                let _ = writeln!(out, "v {} {} {} {} {} {}", p.x, p.y, p.z, c[0], c[1], c[2]);
+            }
+            None => {
+                let _ = writeln!(out, "v {} {} {}", p.x, p.y, p.z);
+            }
+        }
+    }
+    if has_uv {
+        for uv in &mesh.uvs {
+            let _ = writeln!(out, "vt {} {}", uv[0], 1.0 - uv[1]);
+        }
+    }
+    if has_normals {
+        for n in &mesh.normals {
+            let _ = writeln!(out, "vn {} {} {}", n.x, n.y, n.z);
+        }
+    }
+
+    for part in &mesh.parts {
+        let _ = writeln!(out, "o {}", part.name);
+        if mtllib.is_some() {
+            if let Some(material) = model.part_material(part) {
+                let _ = writeln!(out, "usemtl {}", material.name);
+            }
+        }
+        let start = part.index_offset as usize;
+        let end = start + part.index_count as usize;
+        for tri in mesh.indices[start..end].chunks_exact(3) {
+            let corners: Vec<String> = tri
+                .iter()
+                .map(|&i| {
+                    let i = i + 1;
+                    match (has_uv, has_normals) {
+                        (true, true) => format!("{}/{}/{}", i, i, i),
+                        (true, false) => format!("{}/{}", i, i),
+                        (false, true) => format!("{}//{}", i, i),
+                        (false, false) => i.to_string(),
+                    }
+                })
+                .collect();
+            let _ = writeln!(out, "f {}", corners.join(" "));
+        }
+    }
+
+    out
+}
+
+fn write_mtl(model: &Model, texture_names: &[Option<String>]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "# Exported by bookish-3d-viewer");
+    for (material, texture_name) in model.materials.iter().zip(texture_names) {
+        let _ = writeln!(out, "\nnewmtl {}", material.name);
+        let _ = writeln!(out, "Ka {} {} {}", material.ambient[0], material.ambient[1], material.ambient[2]);
+        let _ = writeln!(out, "Kd {} {} {}", material.diffuse[0], material.diffuse[1], material.diffuse[2]);
+        let _ = writeln!(out, "Ks {} {} {}", material.specular[0], material.specular[1], material.specular[2]);
+        let _ = writeln!(out, "Ns {}", material.shininess);
+        let _ = writeln!(out, "d {}", material.dissolve);
+        if let Some(texture_name) = texture_name {
+            let _ = writeln!(out, "map_Kd {}", texture_name);
+        }
+    }
+    out
+}
+
+/// File name of each material's texture, written as PNG next to the .mtl.
+/// Materials sharing a texture share its file; other textures whose names
+/// would collide get a numeric suffix.
+fn texture_file_names(model: &Model) -> Vec<Option<String>> {
+    let mut by_texture: HashMap<&str, String> = HashMap::new();
+    let mut taken = HashSet::new();
+    model
+        .materials
+        .iter()
+        .map(|material| {
+            let texture = material.diffuse_texture.as_ref()?;
+            if let Some(name) = by_texture.get(texture.name.as_str()) {
+                return Some(name.clone());
+            }
+            let stem = texture_stem(texture);
+            let name = (1..)
+                .map(|n| if n == 1 { format!("{}.png", stem) } else { format!("{}_{}.png", stem, n) })
+                .find(|name| !taken.contains(name))?;
+            taken.insert(name.clone());
+            by_texture.insert(&texture.name, name.clone());
+            Some(name)
+        })
+        .collect()
+}
+
+fn texture_stem(texture: &TextureData) -> String {
+    let stem: String = Path::new(&texture.name)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    if stem.is_empty() {
+        String::from("texture")
+    } else {
+        stem
+    }
+}
+
+fn write_stl_binary(mesh: &ExportMesh) -> Vec<u8> {
+    let count = mesh.indices.len() / 3;
+    let mut out = Vec::with_capacity(84 + count * 50);
+
+    let mut header = [0u8; 80];
+    let title = b"Exported by bookish-3d-viewer";
+    header[..title.len()].copy_from_slice(title);
+    out.extend_from_slice(&header);
+    out.extend_from_slice(&(count as u32).to_le_bytes());
+
+    for (normal, corners) in mesh.triangles() {
+        for v in std::iter::once(normal).chain(corners) {
+            out.extend_from_slice(&v.x.to_le_bytes());
+            out.extend_from_slice(&v.y.to_le_bytes());
+            out.extend_from_slice(&v.z.to_le_bytes());
+        }
+        out.extend_from_slice(&[0, 0]);
+    }
+
+    out
+}
+
+fn write_stl_ascii(model: &Model, mesh: &ExportMesh) -> String {
+    let name = model
+        .parts
+        .first()
+        .map(|p| p.name.replace(char::is_whitespace, "_"))
+        .unwrap_or_else(|| String::from("model"));
+    let mut out = String::new();
+
+    let _ = writeln!(out, "solid {}", name);
+    for (normal, corners) in mesh.triangles() {
+        let _ = writeln!(out, "  facet normal {:e} {:e} {:e}", normal.x, normal.y, normal.z);
+        let _ = writeln!(out, "    outer loop");
+        for v in corners {
+            let _ = writeln!(out, "      vertex {:e} {:e} {:e}", v.x, v.y, v.z);
+        }
+        let _ = writeln!(out, "    endloop");
+        let _ = writeln!(out, "  endfacet");
+    }
+    let _ = writeln!(out, "endsolid {}", name);
+
+    out
+}
+
+fn write_ply(model: &Model, mesh: &ExportMesh) -> Vec<u8> {
+    let colors = mesh.colors.as_ref();
+    let face_count = mesh.indices.len() / 3;
+
+    let mut header = String::new();
+    let _ = writeln!(header, "ply");
+    let _ = writeln!(header, "format binary_little_endian 1.0");
+    let _ = writeln!(header, "comment Exported by bookish-3d-viewer");
+    let _ = writeln!(header, "element vertex {}", mesh.positions.len());
+    for name in ["x", "y", "z", "nx", "ny", "nz"] {
+        let _ = writeln!(header, "property float {}", name);
+    }
+    if colors.is_some() {
+        for name in ["red", "green", "blue", "alpha"] {
+            let _ = writeln!(header, "property uchar {}", name);
+        }
+    }
+    for property in &model.scalar_properties {
+        let _ = writeln!(header, "property float {}", property.name);
+    }
+    if face_count > 0 {
+        let _ = writeln!(header, "element face {}", face_count);
+        let _ = writeln!(header, "property list uchar int vertex_indices");
+    }
+    let _ = writeln!(header, "end_header");
+
+    let mut out = header.into_bytes();
+    for (i, (p, n)) in mesh.positions.iter().zip(&mesh.normals).enumerate() {
+        for f in [p.x, p.y, p.z, n.x, n.y, n.z] {
+            out.extend_from_slice(&f.to_le_bytes());
+        }
+        if let Some(colors) = colors {
+            let c = colors.get(i).copied().unwrap_or([1.0; 4]);
+            out.extend(c.iter().map(|&channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8));
+        }
+        for k in 0..model.scalar_properties.len() {
+            let value = mesh.scalar_values.get(k).and_then(|values| values.get(i)).copied().unwrap_or(0.0);
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    for tri in mesh.indices.chunks_exact(3) {
+        out.push(3);
+        for &i in tri {
+            out.extend_from_slice(&(i as i32).to_le_bytes());
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Material;
+    use crate::test_meshes::{cube_vertices, CUBE_INDICES};
+
+    fn cube_part() -> Model {
+        let part = ModelPart {
+            name: String::from("cube"),
+            vertex_offset: 0,
+            vertex_count: 8,
+            index_offset: 0,
+            index_count: 36,
+            material_id: None,
+            visible: true,
+        };
+        let mut model = Model::from_mesh(cube_vertices(), CUBE_INDICES.to_vec(), vec![part]);
+        model.apply_normals(NormalSettings {
+            mode: NormalMode::Flat,
+            ..model.normal_settings
+        });
+        model
+    }
+
+    fn ply_header(bytes: &[u8]) -> String {
+        let text = String::from_utf8_lossy(bytes);
+        text[..text.find("end_header").unwrap()].to_string()
+    }
+
+    #[test]
+    fn exports_the_welded_mesh_with_object_transforms() {
+        let mut model = cube_part();
+        assert_eq!(model.vertices.len(), 24);
+        model.set_part_transform(0, Mat4::from_translation(Vec3::new(2.0, 0.0, 0.0)));
+
+        let obj = String::from_utf8(export_to_bytes(&model, ExportFormat::Obj, &ExportOptions::default())).unwrap();
+        let positions: Vec<&str> = obj.lines().filter(|line| line.starts_with("v ")).collect();
+        assert_eq!(positions.len(), 8);
+        assert_eq!(positions[0], "v 2 0 0");
+        assert_eq!(obj.lines().filter(|line| line.starts_with("f ")).count(), 12);
+
+        let ply = export_to_bytes(&model, ExportFormat::Ply, &ExportOptions::default());
+        assert!(ply_header(&ply).contains("element vertex 8\n"));
+    }
+
+    #[test]
+    fn exports_only_loaded_vertex_colors() {
+        let mut model = cube_part();
+        for vertex in &mut model.vertices {
+            vertex.color = [1.0, 0.0, 0.0, 1.0];
+        }
+        let ply = export_to_bytes(&model, ExportFormat::Ply, &ExportOptions::default());
+        assert!(!ply_header(&ply).contains("red"));
+
+        model.vertex_colors = Some(vec![[0.0, 1.0, 0.0, 1.0]; 8]);
+        let ply = export_to_bytes(&model, ExportFormat::Ply, &ExportOptions::default());
+        assert!(ply_header(&ply).contains("property uchar red"));
+        let obj = String::from_utf8(export_to_bytes(&model, ExportFormat::Obj, &ExportOptions::default())).unwrap();
+        assert!(obj.contains("v 0 0 0 0 1 0\n"));
+    }
+
+    #[test]
+    fn writes_textures_referenced_by_the_mtl() {
+        let stl = b"solid t\nfacet normal 0 0 1\nouter loop\nvertex 0 0 0\nvertex 1 0 0\nvertex 0 1 0\nendloop\nendfacet\nendsolid t\n";
+        let mut model = Model::from_stl_bytes(stl).unwrap();
+        model.materials.push(Material {
+            name: String::from("wood"),
+            diffuse_texture: Some(TextureData {
+                name: String::from("textures/Oak Planks.jpg"),
+                width: 1,
+                height: 1,
+                rgba: vec![200, 150, 100, 255],
+            }),
+            ..Material::default()
+        });
+        model.parts[0].material_id = Some(0);
+
+        let dir = std::env::temp_dir().join(format!("bookish-export-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("model.obj");
+        export_model(&model, path.to_str().unwrap(), ExportFormat::Obj, &ExportOptions::default()).unwrap();
+
+        let mtl = std::fs::read_to_string(dir.join("model.mtl")).unwrap();
+        assert!(mtl.contains("map_Kd Oak_Planks.png"));
+        let texture = image::open(dir.join("Oak_Planks.png")).unwrap().to_rgba8();
+        assert_eq!(texture.into_raw(), vec![200, 150, 100, 255]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn textures_sharing_a_name_get_their_own_files() {
+        let mut model = cube_part();
+        let texture = |name: &str| TextureData {
+            name: name.to_string(),
+            width: 1,
+            height: 1,
+            rgba: vec![0, 0, 0, 255],
+        };
+        for (name, texture_name) in [("a", "a/diffuse.jpg"), ("b", "b/diffuse.png"), ("c", "a/diffuse.jpg"), ("d", "")] {
+            model.materials.push(Material {
+                name: name.to_string(),
+                diffuse_texture: Some(texture(texture_name)),
+                ..Material::default()
+            });
+        }
+        model.materials.push(Material::default());
+
+        let names = texture_file_names(&model);
+        assert_eq!(
+            names,
+            [
+                Some(String::from("diffuse.png")),
+                Some(String::from("diffuse_2.png")),
+                Some(String::from("diffuse.png")),
+                Some(String::from("texture.png")),
+                None,
+            ]
+        );
+        let mtl = write_mtl(&model, &names);
+        assert!(mtl.contains("map_Kd diffuse_2.png"));
+    }
+}
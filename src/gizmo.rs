@@ -0,0 +1,361 @@
+use glam::{Mat4, Quat, Vec2, Vec3};
+use crate::camera::Camera;
+use crate::edges::EdgeInstance;
+use crate::transform::{Transform, TransformTool};
+
+pub const GIZMO_PIXELS: f32 = 90.0;
+const PICK_PIXELS: f32 = 8.0;
+const RING_SEGMENTS: usize = 64;
+
+const AXIS_COLORS: [[f32; 4]; 3] = [
+    [1.0, 0.25, 0.25, 1.0],
+    [0.25, 1.0, 0.25, 1.0],
+    [0.3, 0.5, 1.0, 1.0],
+];
+const CENTER_COLOR: [f32; 4] = [0.9, 0.9, 0.9, 1.0];
+const ACTIVE_COLOR: [f32; 4] = [1.0, 1.0, 0.2, 1.0];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GizmoHandle {
+    Axis(usize),
+    Plane(usize),
+    Center,
+}
+
+#[derive(Clone, Copy)]
+pub struct GizmoView<'a> {
+    pub camera: &'a Camera,
+    pub fit: Mat4,
+    pub viewport: Vec2,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Snapping {
+    pub translate: f32,
+    pub rotate_degrees: f32,
+    pub scale: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Frame {
+    center: Vec3,
+    size: f32,
+    axes: [Vec3; 3],
+    model_per_world: f32,
+}
+
+#[derive(Debug, Clone)]
+struct GizmoDrag {
+    handle: GizmoHandle,
+    start_cursor: Vec2,
+    start_transform: Transform,
+    frame: Frame,
+}
+
+#[derive(Debug, Default)]
+pub struct Gizmo {
+    pub hovered: Option<GizmoHandle>,
+    drag: Option<GizmoDrag>,
+}
+
+fn frame(tool: TransformTool, transform: &Transform, view: &GizmoView) -> Frame {
+    let center = view.fit.transform_point3(transform.translation + transform.pivot);
+    let size = GIZMO_PIXELS * view.camera.world_per_pixel(center, view.viewport.y);
+    // Scaling happens along the model's own axes; moves and rotations use the scene axes.
+    let axes = if tool == TransformTool::Scale {
+        let rotation = transform.rotation_quat();
+        [rotation * Vec3::X, rotation * Vec3::Y, rotation * Vec3::Z]
+    } else {
+        [Vec3::X, Vec3::Y, Vec3::Z]
+    };
+    let fit_scale = view.fit.x_axis.truncate().length();
+    Frame {
+        center,
+        size,
+        axes,
+        model_per_world: if fit_scale > 0.0 { 1.0 / fit_scale } else { 1.0 },
+    }
+}
+
+fn segment_distance(point: Vec2, a: Vec2, b: Vec2) -> f32 {
+    let ab = b - a;
+    let t = if ab.length_squared() > 0.0 {
+        ((point - a).dot(ab) / ab.length_squared()).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    point.distance(a + ab * t)
+}
+
+fn polyline_distance(camera: &Camera, viewport: Vec2, points: &[Vec3], cursor: Vec2) -> f32 {
+    points
+        .windows(2)
+        .filter_map(|pair| Some(segment_distance(cursor, camera.project(pair[0], viewport)?, camera.project(pair[1], viewport)?)))
+        .fold(f32::MAX, f32::min)
+}
+
+fn ring(frame: &Frame, axis: usize) -> Vec<Vec3> {
+    let u = frame.axes[(axis + 1) % 3];
+    let v = frame.axes[(axis + 2) % 3];
+    (0..=RING_SEGMENTS)
+        .map(|i| {
+            let angle = i as f32 / RING_SEGMENTS as f32 * std::f32::consts::TAU;
+            frame.center + (u * angle.cos() + v * angle.sin()) * frame.size
+        })
+        .collect()
+}
+
+fn plane_square(frame: &Frame, axis: usize) -> [Vec3; 5] {
+    let u = frame.axes[(axis + 1) % 3] * frame.size;
+    let v = frame.axes[(axis + 2) % 3] * frame.size;
+    let c = frame.center;
+    [
+        c + u * 0.25 + v * 0.25,
+        c + u * 0.45 + v * 0.25,
+        c + u * 0.45 + v * 0.45,
+        c + u * 0.25 + v * 0.45,
+        c + u * 0.25 + v * 0.25,
+    ]
+}
+
+fn ray_plane(origin: Vec3, direction: Vec3, point: Vec3, normal: Vec3) -> Option<Vec3> {
+    let denom = direction.dot(normal);
+    if denom.abs() < 1e-6 {
+        return None;
+    }
+    let t = (point - origin).dot(normal) / denom;
+    (t > 0.0).then(|| origin + direction * t)
+}
+
+fn snap(value: f32, increment: f32) -> f32 {
+    if increment > 0.0 {
+        (value / increment).round() * increment
+    } else {
+        value
+    }
+}
+
+impl Gizmo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    pub fn active_handle(&self) -> Option<GizmoHandle> {
+        self.drag.as_ref().map(|drag| drag.handle).or(self.hovered)
+    }
+
+    pub fn pick(&self, tool: TransformTool, transform: &Transform, view: &GizmoView, cursor: Vec2) -> Option<GizmoHandle> {
+        if tool == TransformTool::None {
+            return None;
+        }
+        let (camera, viewport) = (view.camera, view.viewport);
+        let frame = frame(tool, transform, view);
+        let center = camera.project(frame.center, viewport)?;
+        if cursor.distance(center) < PICK_PIXELS {
+            return Some(GizmoHandle::Center);
+        }
+
+        let mut candidates = Vec::new();
+        for axis in 0..3 {
+            let distance = match tool {
+                TransformTool::Rotate => polyline_distance(camera, viewport, &ring(&frame, axis), cursor),
+                _ => polyline_distance(
+                    camera,
+                    viewport,
+                    &[frame.center, frame.center + frame.axes[axis] * frame.size],
+                    cursor,
+                ),
+            };
+            candidates.push((distance, GizmoHandle::Axis(axis)));
+
+            if tool == TransformTool::Translate {
+                let square = plane_square(&frame, axis);
+                let corners: Option<Vec<Vec2>> = square[..4].iter().map(|p| camera.project(*p, viewport)).collect();
+                if let Some(corners) = corners {
+                    let inside = |a: Vec2, b: Vec2, c: Vec2| {
+                        let sign = |p: Vec2, q: Vec2| (q - p).perp_dot(cursor - p);
+                        let (d1, d2, d3) = (sign(a, b), sign(b, c), sign(c, a));
+                        (d1 >= 0.0 && d2 >= 0.0 && d3 >= 0.0) || (d1 <= 0.0 && d2 <= 0.0 && d3 <= 0.0)
+                    };
+                    if inside(corners[0], corners[1], corners[2]) || inside(corners[0], corners[2], corners[3]) {
+                        candidates.push((0.0, GizmoHandle::Plane(axis)));
+                    }
+                }
+            }
+        }
+
+        candidates
+            .into_iter()
+            .filter(|(distance, _)| *distance < PICK_PIXELS)
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, handle)| handle)
+    }
+
+    pub fn begin_drag(&mut self, handle: GizmoHandle, tool: TransformTool, transform: &Transform, view: &GizmoView, cursor: Vec2) {
+        self.drag = Some(GizmoDrag {
+            handle,
+            start_cursor: cursor,
+            start_transform: transform.clone(),
+            frame: frame(tool, transform, view),
+        });
+    }
+
+    pub fn end_drag(&mut self, transform: &Transform) -> bool {
+        self.drag.take().is_some_and(|drag| {
+            drag.start_transform.translation != transform.translation
+                || drag.start_transform.rotation != transform.rotation
+                || drag.start_transform.scale != transform.scale
+        })
+    }
+
+    /// Recomputes the transform from the drag start, so snapping applies to
+    /// the whole movement rather than to each mouse event.
+    pub fn drag_to(
+        &self,
+        tool: TransformTool,
+        transform: &mut Transform,
+        view: &GizmoView,
+        cursor: Vec2,
+        snapping: Option<Snapping>,
+    ) {
+        let Some(drag) = &self.drag else {
+            return;
+        };
+        let (camera, viewport) = (view.camera, view.viewport);
+        let frame = drag.frame;
+        let Some(center) = camera.project(frame.center, viewport) else {
+            return;
+        };
+        let start = &drag.start_transform;
+        let delta = cursor - drag.start_cursor;
+        *transform = start.clone();
+
+        match (tool, drag.handle) {
+            (TransformTool::Translate, GizmoHandle::Axis(axis)) => {
+                let Some(tip) = camera.project(frame.center + frame.axes[axis] * frame.size, viewport) else {
+                    return;
+                };
+                let screen_axis = tip - center;
+                if screen_axis.length_squared() < 1.0 {
+                    return;
+                }
+                let distance = delta.dot(screen_axis) / screen_axis.length_squared() * frame.size * frame.model_per_world;
+                let distance = snapping.map_or(distance, |s| snap(distance, s.translate));
+                transform.translation = start.translation + frame.axes[axis] * distance;
+            }
+            (TransformTool::Translate, handle) => {
+                let normal = match handle {
+                    GizmoHandle::Plane(axis) => frame.axes[axis],
+                    _ => (camera.eye_position() - frame.center).normalize(),
+                };
+                let (origin, start_dir) = camera.cursor_ray(drag.start_cursor, viewport);
+                let (_, dir) = camera.cursor_ray(cursor, viewport);
+                let (Some(from), Some(to)) = (
+                    ray_plane(origin, start_dir, frame.center, normal),
+                    ray_plane(origin, dir, frame.center, normal),
+                ) else {
+                    return;
+                };
+                let mut offset = (to - from) * frame.model_per_world;
+                if let Some(s) = snapping {
+                    offset = Vec3::new(snap(offset.x, s.translate), snap(offset.y, s.translate), snap(offset.z, s.translate));
+                }
+                transform.translation = start.translation + offset;
+            }
+            (TransformTool::Rotate, GizmoHandle::Axis(axis)) => {
+                let from = drag.start_cursor - center;
+                let to = cursor - center;
+                if from.length_squared() < 1.0 || to.length_squared() < 1.0 {
+                    return;
+                }
+                // Screen y points down, so a positive perp_dot is a clockwise turn.
+                let turn = -from.perp_dot(to).atan2(from.dot(to));
+                let facing = frame.axes[axis].dot(camera.eye_position() - frame.center) >= 0.0;
+                let angle = if facing { turn } else { -turn };
+                let angle = snapping.map_or(angle, |s| snap(angle, s.rotate_degrees.to_radians()));
+                transform.set_rotation_quat(Quat::from_axis_angle(frame.axes[axis], angle) * start.rotation_quat());
+            }
+            (TransformTool::Rotate, _) => {
+                let view_matrix = camera.get_view_matrix();
+                let right = view_matrix.row(0).truncate();
+                let up = view_matrix.row(1).truncate();
+                let rotation = Quat::from_axis_angle(up, delta.x * 0.01) * Quat::from_axis_angle(right, delta.y * 0.01);
+                transform.set_rotation_quat(rotation * start.rotation_quat());
+            }
+            (TransformTool::Scale, GizmoHandle::Axis(axis)) => {
+                let Some(tip) = camera.project(frame.center + frame.axes[axis] * frame.size, viewport) else {
+                    return;
+                };
+                let screen_axis = tip - center;
+                if screen_axis.length_squared() < 1.0 {
+                    return;
+                }
+                let factor = 1.0 + delta.dot(screen_axis) / screen_axis.length_squared();
+                let factor = snapping.map_or(factor, |s| snap(factor, s.scale)).max(0.01);
+                transform.scale[axis] = start.scale[axis] * factor;
+            }
+            (TransformTool::Scale, _) => {
+                let from = (drag.start_cursor - center).length();
+                if from < 1.0 {
+                    return;
+                }
+                let factor = (cursor - center).length() / from;
+                let factor = snapping.map_or(factor, |s| snap(factor, s.scale)).max(0.01);
+                transform.scale = start.scale * factor;
+            }
+            (TransformTool::None, _) => {}
+        }
+    }
+
+    pub fn lines(&self, tool: TransformTool, transform: &Transform, view: &GizmoView) -> Vec<EdgeInstance> {
+        if tool == TransformTool::None {
+            return Vec::new();
+        }
+        let frame = frame(tool, transform, view);
+        let active = self.active_handle();
+        let color = |handle: GizmoHandle, base: [f32; 4]| if active == Some(handle) { ACTIVE_COLOR } else { base };
+        let mut lines = Vec::new();
+        let mut polyline = |points: &[Vec3], color: [f32; 4]| {
+            for pair in points.windows(2) {
+                lines.push(EdgeInstance {
+                    start: pair[0].to_array(),
+                    end: pair[1].to_array(),
+                    color,
+                });
+            }
+        };
+
+        for (axis, base) in AXIS_COLORS.into_iter().enumerate() {
+            let handle = GizmoHandle::Axis(axis);
+            let tip = frame.center + frame.axes[axis] * frame.size;
+            match tool {
+                TransformTool::Rotate => polyline(&ring(&frame, axis), color(handle, base)),
+                TransformTool::Translate => {
+                    polyline(&[frame.center, tip], color(handle, base));
+                    let u = frame.axes[(axis + 1) % 3] * frame.size * 0.06;
+                    let back = tip - frame.axes[axis] * frame.size * 0.15;
+                    polyline(&[back + u, tip, back - u], color(handle, base));
+                    polyline(&plane_square(&frame, axis), color(GizmoHandle::Plane(axis), base));
+                }
+                TransformTool::Scale => {
+                    polyline(&[frame.center, tip], color(handle, base));
+                    let u = frame.axes[(axis + 1) % 3] * frame.size * 0.06;
+                    let v = frame.axes[(axis + 2) % 3] * frame.size * 0.06;
+                    polyline(&[tip + u + v, tip + u - v, tip - u - v, tip - u + v, tip + u + v], color(handle, base));
+                }
+                TransformTool::None => {}
+            }
+        }
+
+        let view_matrix = view.camera.get_view_matrix();
+        let right = view_matrix.row(0).truncate() * frame.size * 0.06;
+        let up = view_matrix.row(1).truncate() * frame.size * 0.06;
+        let c = frame.center;
+        polyline(&[c + right + up, c + right - up, c - right - up, c - right + up, c + right + up], color(GizmoHandle::Center, CENTER_COLOR));
+        lines
+    }
+}
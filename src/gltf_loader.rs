@@ -0,0 +1,329 @@
+use base64::Engine;
+use std::collections::HashSet;
+use glam::{Mat3, Mat4, Vec2, Vec3};
+use crate::material::{Material, TextureData};
+use crate::model::{ModelPart, SceneNode, Vertex};
+
+pub struct GltfScene {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+    pub parts: Vec<ModelPart>,
+    pub materials: Vec<Material>,
+    pub nodes: Vec<SceneNode>,
+    pub has_vertex_colors: bool,
+}
+
+pub fn load<F>(data: &[u8], resolve: F) -> Result<GltfScene, Box<dyn std::error::Error>>
+where
+    F: Fn(&str) -> Option<Vec<u8>>,
+{
+    let gltf = gltf::Gltf::from_slice(data)?;
+    let document = &gltf.document;
+
+    let buffers = document
+        .buffers()
+        .map(|buffer| match buffer.source() {
+            gltf::buffer::Source::Bin => gltf
+                .blob
+                .clone()
+                .ok_or_else(|| "glTF buffer refers to a missing GLB binary chunk".into()),
+            gltf::buffer::Source::Uri(uri) => load_uri(uri, &resolve),
+        })
+        .collect::<Result<Vec<Vec<u8>>, Box<dyn std::error::Error>>>()?;
+
+    let materials = document
+        .materials()
+        .map(|material| load_material(&material, &buffers, &resolve))
+        .collect();
+
+    let mut scene = GltfScene {
+        vertices: Vec::new(),
+        indices: Vec::new(),
+        parts: Vec::new(),
+        materials,
+        nodes: Vec::new(),
+        has_vertex_colors: false,
+    };
+
+    let roots: Vec<gltf::Node> = match document.default_scene().or_else(|| document.scenes().next()) {
+        Some(s) => s.nodes().collect(),
+        None => {
+            let children: HashSet<usize> = document.nodes().flat_map(|n| n.children()).map(|n| n.index()).collect();
+            document.nodes().filter(|n| !children.contains(&n.index())).collect()
+        }
+    };
+
+    // Walked with an explicit stack so deep hierarchies cannot overflow the
+    // call stack; nodes reached twice mean the file's node graph has a cycle.
+    let mut visited = HashSet::new();
+    let mut stack: Vec<(gltf::Node, Option<usize>, Mat4)> =
+        roots.into_iter().rev().map(|node| (node, None, Mat4::IDENTITY)).collect();
+    while let Some((node, parent, parent_transform)) = stack.pop() {
+        if !visited.insert(node.index()) {
+            return Err(format!("glTF node {} is reached twice (cyclic node hierarchy)", node.index()).into());
+        }
+        let (node_index, world_transform) = visit_node(&node, parent, parent_transform, &buffers, &mut scene)?;
+        let children: Vec<gltf::Node> = node.children().collect();
+        stack.extend(children.into_iter().rev().map(|child| (child, Some(node_index), world_transform)));
+    }
+
+    Ok(scene)
+}
+
+fn load_uri<F>(uri: &str, resolve: &F) -> Result<Vec<u8>, Box<dyn std::error::Error>>
+where
+    F: Fn(&str) -> Option<Vec<u8>>,
+{
+    if let Some(rest) = uri.strip_prefix("data:") {
+        let (header, payload) = rest.split_once(',').ok_or("Malformed data URI in glTF file")?;
+        if header.ends_with(";base64") {
+            Ok(base64::engine::general_purpose::STANDARD.decode(payload)?)
+        } else {
+            Ok(payload.as_bytes().to_vec())
+        }
+    } else {
+        let decoded = percent_decode(uri);
+        resolve(&decoded).ok_or_else(|| format!("Failed to resolve glTF resource: {}", uri).into())
+    }
+}
+
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let Ok(value) = u8::from_str_radix(&uri[i + 1..i + 3], 16) {
+                out.push(value);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn load_material<F>(material: &gltf::Material, buffers: &[Vec<u8>], resolve: &F) -> Material
+where
+    F: Fn(&str) -> Option<Vec<u8>>,
+{
+    let pbr = material.pbr_metallic_roughness();
+    let [r, g, b, a] = pbr.base_color_factor();
+
+    let diffuse_texture = pbr.base_color_texture().and_then(|info| {
+        let image = info.texture().source();
+        let name = image.name().map(str::to_string).unwrap_or_else(|| format!("image_{}", image.index()));
+        let bytes = match image.source() {
+            gltf::image::Source::View { view, .. } => {
+                let buffer = buffers.get(view.buffer().index())?;
+                buffer.get(view.offset()..view.offset() + view.length())?.to_vec()
+            }
+            gltf::image::Source::Uri { uri, .. } => match load_uri(uri, resolve) {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    eprintln!("Failed to load texture {}: {}", uri, e);
+                    return None;
+                }
+            },
+        };
+        match TextureData::from_bytes(&name, &bytes) {
+            Ok(texture) => Some(texture),
+            Err(e) => {
+                eprintln!("Failed to decode texture {}: {}", name, e);
+                None
+            }
+        }
+    });
+
+    Material {
+        name: material.name().unwrap_or("material").to_string(),
+        diffuse: [r, g, b],
+        dissolve: a,
+        diffuse_texture,
+        ..Material::default()
+    }
+}
+
+fn visit_node(
+    node: &gltf::Node,
+    parent: Option<usize>,
+    parent_transform: Mat4,
+    buffers: &[Vec<u8>],
+    scene: &mut GltfScene,
+) -> Result<(usize, Mat4), Box<dyn std::error::Error>> {
+    let local_transform = Mat4::from_cols_array_2d(&node.transform().matrix());
+    let world_transform = parent_transform * local_transform;
+    let node_index = scene.nodes.len();
+    let name = node.name().map(str::to_string).unwrap_or_else(|| format!("node_{}", node.index()));
+
+    scene.nodes.push(SceneNode {
+        name: name.clone(),
+        parent,
+        children: Vec::new(),
+        local_transform,
+        world_transform,
+        parts: Vec::new(),
+    });
+    if let Some(parent) = parent {
+        scene.nodes[parent].children.push(node_index);
+    }
+
+    if let Some(mesh) = node.mesh() {
+        let normal_matrix = Mat3::from_mat4(world_transform).inverse().transpose();
+        let mesh_name = mesh.name().map(str::to_string).unwrap_or_else(|| format!("mesh_{}", mesh.index()));
+
+        for primitive in mesh.primitives() {
+            let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(Vec::as_slice));
+            let Some(positions) = reader.read_positions() else {
+                continue;
+            };
+
+            let vertex_offset = scene.vertices.len() as u32;
+            let index_offset = scene.indices.len() as u32;
+
+            let positions: Vec<[f32; 3]> = positions.collect();
+            let normals: Vec<[f32; 3]> = reader.read_normals().map(|n| n.collect()).unwrap_or_default();
+            let uvs: Vec<[f32; 2]> = reader.read_tex_coords(0).map(|t| t.into_f32().collect()).unwrap_or_default();
+            let colors: Vec<[f32; 4]> = reader.read_colors(0).map(|c| c.into_rgba_f32().collect()).unwrap_or_default();
+            scene.has_vertex_colors |= !colors.is_empty();
+
+            for (i, position) in positions.iter().enumerate() {
+                let pos = world_transform.transform_point3(Vec3::from_array(*position));
+                let normal = normals
+                    .get(i)
+                    .map(|n| (normal_matrix * Vec3::from_array(*n)).normalize_or_zero())
+                    .unwrap_or(Vec3::ZERO);
+                let uv = uvs.get(i).map(|uv| Vec2::from_array(*uv)).unwrap_or(Vec2::ZERO);
+
+                let mut vertex = Vertex::with_uv(pos, normal, uv);
+                if let Some(color) = colors.get(i) {
+                    vertex.color = *color;
+                }
+                scene.vertices.push(vertex);
+            }
+
+            let local_indices: Vec<u32> = match reader.read_indices() {
+                Some(indices) => indices.into_u32().collect(),
+                None => (0..positions.len() as u32).collect(),
+            };
+
+            let triangles: Vec<u32> = match primitive.mode() {
+                gltf::mesh::Mode::Triangles => local_indices,
+                gltf::mesh::Mode::TriangleStrip => (2..local_indices.len())
+                    .flat_map(|i| {
+                        if i % 2 == 0 {
+                            [local_indices[i - 2], local_indices[i - 1], local_indices[i]]
+                        } else {
+                            [local_indices[i - 1], local_indices[i - 2], local_indices[i]]
+                        }
+                    })
+                    .collect(),
+                gltf::mesh::Mode::TriangleFan => (2..local_indices.len())
+                    .flat_map(|i| [local_indices[0], local_indices[i - 1], local_indices[i]])
+                    .collect(),
+                gltf::mesh::Mode::Points => Vec::new(),
+                _ => {
+                    scene.vertices.truncate(vertex_offset as usize);
+                    continue;
+                }
+            };
+
+            if world_transform.determinant() < 0.0 {
+                for triangle in triangles.chunks_exact(3) {
+                    scene.indices.extend([triangle[0], triangle[2], triangle[1]].map(|i| i + vertex_offset));
+                }
+            } else {
+                scene.indices.extend(triangles.iter().map(|i| i + vertex_offset));
+            }
+
+            scene.nodes[node_index].parts.push(scene.parts.len());
+            scene.parts.push(ModelPart {
+                name: format!("{}/{}#{}", name, mesh_name, primitive.index()),
+                vertex_offset,
+                vertex_count: scene.vertices.len() as u32 - vertex_offset,
+                index_offset,
+                index_count: scene.indices.len() as u32 - index_offset,
+                material_id: primitive.material().index(),
+                visible: true,
+            });
+        }
+    }
+
+    Ok((node_index, world_transform))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(nodes: &str, scenes: &str) -> String {
+        let mut positions = Vec::new();
+        for value in [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+            positions.extend_from_slice(&value.to_le_bytes());
+        }
+        let uri = format!("data:application/octet-stream;base64,{}", base64::engine::general_purpose::STANDARD.encode(positions));
+        format!(
+            r#"{{
+                "asset": {{"version": "2.0"}},
+                {scenes}
+                "nodes": {nodes},
+                "meshes": [{{"name": "tri", "primitives": [{{"attributes": {{"POSITION": 0}}}}]}}],
+                "accessors": [{{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0, 0, 0], "max": [1, 1, 0]}}],
+                "bufferViews": [{{"buffer": 0, "byteLength": 36}}],
+                "buffers": [{{"byteLength": 36, "uri": "{uri}"}}]
+            }}"#
+        )
+    }
+
+    #[test]
+    fn applies_node_transforms() {
+        let json = document(
+            r#"[{"name": "root", "translation": [0, 0, 5], "children": [1]}, {"name": "leaf", "mesh": 0, "scale": [2, 2, 2]}]"#,
+            r#""scene": 0, "scenes": [{"nodes": [0]}],"#,
+        );
+        let scene = load(json.as_bytes(), |_| None).unwrap();
+        assert_eq!(scene.nodes.len(), 2);
+        assert_eq!(scene.nodes[0].children, vec![1]);
+        assert_eq!(scene.nodes[1].parent, Some(0));
+        assert_eq!(scene.nodes[1].parts, vec![0]);
+        assert_eq!(scene.indices, vec![0, 1, 2]);
+        assert_eq!(scene.vertices[1].position, [2.0, 0.0, 5.0]);
+    }
+
+    #[test]
+    fn visits_only_root_nodes_without_scenes() {
+        let json = document(r#"[{"children": [1]}, {"mesh": 0}]"#, "");
+        let scene = load(json.as_bytes(), |_| None).unwrap();
+        assert_eq!(scene.nodes.len(), 2);
+        assert_eq!(scene.parts.len(), 1);
+    }
+
+    #[test]
+    fn rejects_cyclic_hierarchies() {
+        let json = document(
+            r#"[{"children": [1]}, {"mesh": 0, "children": [0]}]"#,
+            r#""scenes": [{"nodes": [0]}],"#,
+        );
+        assert!(load(json.as_bytes(), |_| None).is_err());
+    }
+
+    #[test]
+    fn loads_deep_hierarchies() {
+        let depth = 50_000;
+        let nodes: Vec<String> = (0..depth)
+            .map(|i| if i + 1 < depth { format!(r#"{{"children": [{}]}}"#, i + 1) } else { String::from(r#"{"mesh": 0}"#) })
+            .collect();
+        let json = document(&format!("[{}]", nodes.join(",")), r#""scenes": [{"nodes": [0]}],"#);
+        let scene = load(json.as_bytes(), |_| None).unwrap();
+        assert_eq!(scene.nodes.len(), depth);
+        assert_eq!(scene.parts.len(), 1);
+    }
+
+    #[test]
+    fn decodes_percent_escapes() {
+        assert_eq!(percent_decode("my%20model.bin"), "my model.bin");
+        assert_eq!(percent_decode("100%"), "100%");
+    }
+}
@@ -1,6 +1,6 @@
 use wgpu::util::DeviceExt;
 use wgpu::*;
-use glam::Vec3;
+use glam::{Vec2, Vec3};
 use crate::model::Vertex;
 
 pub struct ImageViewer {
@@ -19,6 +19,12 @@ pub enum ImageMode {
     Viewer2D,
 }
 
+impl Default for ImageViewer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ImageViewer {
     pub fn new() -> Self {
         Self {
@@ -127,10 +133,10 @@ impl ImageViewer {
         let aspect = width as f32 / height as f32;
         let scale = 2.0;
         let vertices = vec![
-            Vertex::new(Vec3::new(-scale * aspect, -scale, 0.0), Vec3::new(0.0, 0.0, 1.0)),
-            Vertex::new(Vec3::new(scale * aspect, -scale, 0.0), Vec3::new(0.0, 0.0, 1.0)),
-            Vertex::new(Vec3::new(scale * aspect, scale, 0.0), Vec3::new(0.0, 0.0, 1.0)),
-            Vertex::new(Vec3::new(-scale * aspect, scale, 0.0), Vec3::new(0.0, 0.0, 1.0)),
+            Vertex::with_uv(Vec3::new(-scale * aspect, -scale, 0.0), Vec3::new(0.0, 0.0, 1.0), Vec2::new(0.0, 1.0)),
+            Vertex::with_uv(Vec3::new(scale * aspect, -scale, 0.0), Vec3::new(0.0, 0.0, 1.0), Vec2::new(1.0, 1.0)),
+            Vertex::with_uv(Vec3::new(scale * aspect, scale, 0.0), Vec3::new(0.0, 0.0, 1.0), Vec2::new(1.0, 0.0)),
+            Vertex::with_uv(Vec3::new(-scale * aspect, scale, 0.0), Vec3::new(0.0, 0.0, 1.0), Vec2::new(0.0, 0.0)),
         ];
 
         let indices = vec![0, 1, 2, 2, 3, 0];
@@ -1,5 +1,18 @@
 pub mod camera;
 pub mod model;
+pub mod material;
+pub mod stl;
+pub mod ply;
+pub mod gltf_loader;
+pub mod export;
+pub mod units;
+pub mod normals;
+pub mod validation;
+pub mod edges;
+pub mod display;
+pub mod background;
+pub mod lighting;
+pub mod mass_properties;
 pub mod image_viewer;
 pub mod menu;
 pub mod utils;
@@ -10,6 +23,17 @@ pub mod transform;
 pub mod color_picker;
 pub mod selection;
 pub mod editor;
+pub mod gizmo;
+pub mod bvh;
+pub mod topology;
+pub mod mesh_edit;
+pub mod cleanup;
+
+#[cfg(test)]
+mod test_meshes;
+
+#[cfg(feature = "desktop")]
+pub mod renderer;
 
 #[cfg(target_arch = "wasm32")]
 pub mod wasm;
@@ -17,12 +41,34 @@ pub mod wasm;
 #[cfg(feature = "desktop")]
 use renderer::Renderer;
 
+use std::collections::{HashMap, HashSet};
+
+use glam::{Mat4, Vec2, Vec3};
+
 use camera::Camera;
-use model::Model;
+use export::{ExportFormat, ExportOptions};
+use units::Unit;
+use background::BackgroundStyle;
+use edges::{EdgeFilter, EdgeInstance};
+use normals::{NormalMode, NormalSettings, NormalWeighting};
+use model::{Model, FIT_SIZE};
 use image_viewer::ImageViewer;
 use menu::Menu;
+use color_picker::Color;
 use config::Config;
 use model_info::ModelInfo;
+use editor::{Command, Editor};
+use transform::{Transform, TransformTool};
+use gizmo::{Gizmo, GizmoView, Snapping};
+use bvh::{Bvh, RayHit};
+use topology::MeshTopology;
+use mesh_edit::MeshCommand;
+use cleanup::CleanupReport;
+use selection::{DepthImage, SelectOp, Selection, SelectionMode, SelectionRegion, SelectionShape};
+use validation::{MeshHighlight, MeshReport};
+
+const ACTIVE_OBJECT_COLOR: [f32; 4] = [1.0, 0.6, 0.1, 1.0];
+const SELECTED_OBJECT_COLOR: [f32; 4] = [0.7, 0.4, 0.1, 1.0];
 
 pub struct App {
     pub camera: Camera,
@@ -35,11 +81,27 @@ pub struct App {
     pub right_mouse_pressed: bool,
     pub last_mouse_pos: (f64, f64),
     pub show_info: bool,
+    pub image_on_model: bool,
+    pub show_validation: bool,
+    pub validation_highlight: Option<MeshHighlight>,
+    pub editor: Editor,
+    pub gizmo: Gizmo,
+    pub selection_region: Option<SelectionRegion>,
+    pub selection_highlight: Option<MeshHighlight>,
+    bvh: Option<Bvh>,
+    topology: Option<MeshTopology>,
+    cleanup_report: Option<CleanupReport>,
+}
+
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl App {
     pub fn new() -> Self {
-        Self {
+        let mut app = Self {
             camera: Camera::new(800.0, 600.0),
             model: None,
             model_info: None,
@@ -50,7 +112,24 @@ impl App {
             right_mouse_pressed: false,
             last_mouse_pos: (0.0, 0.0),
             show_info: false,
-        }
+            image_on_model: false,
+            show_validation: false,
+            validation_highlight: None,
+            editor: Editor::new(),
+            gizmo: Gizmo::new(),
+            selection_region: None,
+            selection_highlight: None,
+            bvh: None,
+            topology: None,
+            cleanup_report: None,
+        };
+        app.editor.history_budget = app.history_budget();
+        app.editor.color_picker.set_background_color(Color::from_u32(app.config.background_color));
+        app
+    }
+
+    fn history_budget(&self) -> usize {
+        (self.config.history_memory_mb.max(0.0) as f64 * 1024.0 * 1024.0) as usize
     }
 
     pub fn handle_keyboard(&mut self, key: &str) {
@@ -58,6 +137,19 @@ impl App {
             "r" | "R" => {
                 self.camera.reset();
             }
+            "ArrowUp" | "ArrowDown" | "ArrowLeft" | "ArrowRight" | "PageUp" | "PageDown"
+                if self.editor.tool != TransformTool::None && self.model.is_some() =>
+            {
+                let axis = match key {
+                    "ArrowRight" => Vec3::X,
+                    "ArrowLeft" => -Vec3::X,
+                    "ArrowUp" => Vec3::Y,
+                    "ArrowDown" => -Vec3::Y,
+                    "PageUp" => Vec3::Z,
+                    _ => -Vec3::Z,
+                };
+                self.nudge_transform(axis);
+            }
             "ArrowUp" => {
                 self.camera.rotate(0.0, -0.1);
             }
@@ -82,41 +174,276 @@ impl App {
             "m" | "M" => {
                 self.menu.toggle();
             }
-            "i" | "I" => {
-                if self.image_viewer.has_image() {
-                    self.image_viewer.toggle_mode();
+            "i" | "I" if self.image_viewer.has_image() => {
+                self.image_viewer.toggle_mode();
+            }
+            "t" | "T" if self.image_viewer.has_image() && self.model.is_some() => {
+                self.image_on_model = !self.image_on_model;
+            }
+            "e" | "E" => {
+                match self.export_model_next_to_source() {
+                    Ok(path) => println!("Exported model: {}", path),
+                    Err(e) => eprintln!("Failed to export model: {}", e),
+                }
+            }
+            "x" | "X" => {
+                let format = self.export_format().next();
+                self.config.export_format = format.name().to_string();
+                println!("Export format: {}", format.name());
+            }
+            "u" | "U" => {
+                let unit = self.unit().next();
+                self.set_unit(unit);
+                println!("Units: {}", unit.suffix());
+            }
+            "n" | "N" => {
+                let mode = self.normal_settings().mode.next();
+                self.set_normal_mode(mode);
+                println!("Normals: {}", mode.name());
+            }
+            "c" | "C" => {
+                if let Some(model) = self.model.as_mut() {
+                    model.cycle_color_source();
+                    println!("Coloring by: {}", model.color_source_name());
+                }
+            }
+            "l" | "L" => {
+                let filter = EdgeFilter::from_name(&self.config.wireframe_edges).unwrap_or(EdgeFilter::All).next();
+                self.config.wireframe_edges = filter.name().to_string();
+                println!("Wireframe edges: {}", filter.name());
+            }
+            "[" => {
+                self.config.wireframe_thickness = (self.config.wireframe_thickness - 0.5).max(1.0);
+                println!("Wireframe thickness: {:.1}px", self.config.wireframe_thickness);
+            }
+            "]" => {
+                self.config.wireframe_thickness = (self.config.wireframe_thickness + 0.5).min(10.0);
+                println!("Wireframe thickness: {:.1}px", self.config.wireframe_thickness);
+            }
+            "b" | "B" => {
+                let style = BackgroundStyle::from_name(&self.config.background_style).unwrap_or_default().next();
+                self.config.background_style = style.name().to_string();
+                println!("Background: {}", style.name());
+            }
+            "k" | "K" => {
+                let rigs = &self.config.lighting_rigs;
+                if !rigs.is_empty() {
+                    let current = rigs.iter().position(|rig| rig.name == self.config.lighting_rig);
+                    let next = current.map(|i| (i + 1) % rigs.len()).unwrap_or(0);
+                    self.config.lighting_rig = rigs[next].name.clone();
+                    println!("Lighting: {}", self.config.lighting_rig);
+                }
+            }
+            "j" | "J" => {
+                self.config.headlight = !self.config.headlight;
+                println!("Headlight: {}", if self.config.headlight { "on" } else { "off" });
+            }
+            "v" | "V" => {
+                self.show_validation = !self.show_validation;
+                self.update_validation_highlight();
+                if self.show_validation {
+                    if let Some(report) = self.validation_report() {
+                        println!("{}", report.format_report());
+                    }
                 }
             }
             "h" | "H" => {
                 self.show_info = !self.show_info;
+                if self.show_info {
+                    if let Some(info) = self.info_text() {
+                        println!("{}", info);
+                    }
+                }
             }
             "s" | "S" => {
                 if let Err(e) = self.config.save() {
                     eprintln!("Failed to save config: {}", e);
                 }
             }
+            "p" | "P" => {
+                let mode = self.selection_mode().next();
+                self.config.selection_mode = mode.name().to_string();
+                println!("Selection mode: {}", mode.name());
+            }
+            "o" | "O" => {
+                let shape = SelectionShape::from_name(&self.config.selection_shape).unwrap_or(SelectionShape::Box).next();
+                self.config.selection_shape = shape.name().to_string();
+                println!("Selection shape: {}", shape.name());
+            }
+            "y" | "Y" => {
+                self.config.select_visible_only = !self.config.select_visible_only;
+                println!("Select visible only: {}", if self.config.select_visible_only { "on" } else { "off" });
+            }
+            "g" | "G" => {
+                self.config.snapping = !self.config.snapping;
+                println!("Snapping: {}", if self.config.snapping { "on" } else { "off" });
+            }
+            "q" | "Q" => {
+                self.editor.tool = self.editor.tool.next();
+                println!("Transform tool: {}", self.editor.tool.name());
+            }
+            "0" => {
+                let targets = self.transform_targets();
+                if targets.iter().all(|&target| self.editor.transform_of(target).is_identity()) {
+                    return;
+                }
+                for target in targets {
+                    self.editor.transform_of_mut(target).reset();
+                }
+                self.editor.save_state();
+                self.apply_object_transforms();
+                println!("Transform reset");
+            }
+            "z" | "Z" => self.isolate_selected_objects(),
+            "." => self.edit_selection("Grow selection", |selection, topology, _| selection.grow(topology)),
+            "," => self.edit_selection("Shrink selection", |selection, topology, _| selection.shrink(topology)),
+            "SelectLinked" => self.edit_selection("Select linked", |selection, topology, _| selection.select_linked(topology)),
+            "SelectBoundary" => {
+                self.edit_selection("Select boundary", |selection, topology, _| selection.select_boundary(topology))
+            }
+            "SelectByNormal" => {
+                let angle = self.config.select_normal_angle;
+                self.edit_selection("Select by normal", |selection, topology, model| {
+                    let faces = model.visible_face_ranges().into_iter().flatten();
+                    selection.select_by_normal(topology, faces, angle)
+                })
+            }
+            "InvertSelection" => {
+                let mode = match self.editor.selection.mode {
+                    SelectionMode::None => self.selection_mode(),
+                    mode => mode,
+                };
+                self.edit_selection("Invert selection", |selection, _, model| {
+                    let vertices = model.visible_vertex_ranges().into_iter().flatten().map(|i| model.source_vertex(i));
+                    let faces = model.visible_face_ranges().into_iter().flatten();
+                    let objects = (0..model.parts.len()).filter(|&part| model.parts[part].visible);
+                    selection.invert(mode, vertices, faces, objects)
+                })
+            }
+            "Undo" | "Redo" => {
+                let before = self.model.as_ref().map(|m| (m.revision, m.indices.len()));
+                let name = if key == "Undo" {
+                    self.editor.undo(self.model.as_mut())
+                } else {
+                    self.editor.redo(self.model.as_mut())
+                };
+                match name {
+                    Some(name) => println!("{}: {}", key, name),
+                    None => println!("Nothing to {}", key.to_lowercase()),
+                }
+                let after = self.model.as_ref().map(|m| (m.revision, m.indices.len()));
+                if before != after {
+                    self.cleanup_report = None;
+                    // Selected faces are renumbered when faces come and go.
+                    if before.map(|(_, faces)| faces) != after.map(|(_, faces)| faces) {
+                        self.editor.selection.clear();
+                    }
+                    self.refresh_model();
+                }
+                self.apply_object_transforms();
+            }
+            "Delete" => self.edit_mesh(|model, topology, selection| {
+                mesh_edit::delete_faces(model, selection.mesh_faces(topology, model, false))
+            }),
+            "FlipNormals" => self.edit_mesh(|model, topology, selection| {
+                mesh_edit::flip_faces(model, selection.mesh_faces(topology, model, true))
+            }),
+            "MergeVertices" => self.edit_mesh(|model, topology, selection| {
+                mesh_edit::merge_vertices(model, selection.mesh_vertices(topology, model))
+            }),
+            "FillHoles" => self.edit_mesh(|model, topology, selection| {
+                mesh_edit::fill_holes(model, topology, &selection.mesh_vertices(topology, model))
+            }),
+            "CleanUp" => {
+                if let Some(report) = self.clean_up_mesh().filter(|report| !report.is_empty()) {
+                    println!("{}", report.format_report());
+                }
+            }
+            "1" | "2" | "3" | "4" | "5" | "6" | "7" | "8" | "9" => {
+                if let Ok(n) = key.parse::<usize>() {
+                    self.toggle_part(n - 1);
+                }
+            }
             _ => {}
         }
     }
 
+    pub fn export_format(&self) -> ExportFormat {
+        ExportFormat::from_name(&self.config.export_format).unwrap_or(ExportFormat::Obj)
+    }
+
+    pub fn export_options(&self) -> ExportOptions {
+        ExportOptions {
+            transform: (!self.editor.transform.is_identity()).then(|| self.editor.transform.clone()),
+            recalculate_normals: self.config.export_recalculate_normals,
+        }
+    }
+
+    pub fn export_model(&self, path: &str, format: ExportFormat) -> Result<(), Box<dyn std::error::Error>> {
+        let model = self.model.as_ref().ok_or("No model loaded")?;
+        export::export_model(model, path, format, &self.export_options())
+    }
+
+    pub fn export_model_to_bytes(&self, format: ExportFormat) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let model = self.model.as_ref().ok_or("No model loaded")?;
+        Ok(export::export_to_bytes(model, format, &self.export_options()))
+    }
+
+    fn export_model_next_to_source(&self) -> Result<String, Box<dyn std::error::Error>> {
+        let format = self.export_format();
+        let source = self
+            .model_info
+            .as_ref()
+            .and_then(|info| info.file_path.clone())
+            .unwrap_or_else(|| String::from("model"));
+        let source = std::path::Path::new(&source);
+        let stem = source.file_stem().and_then(|s| s.to_str()).unwrap_or("model");
+        let path = source
+            .with_file_name(format!("{}_export.{}", stem, format.extension()))
+            .to_string_lossy()
+            .into_owned();
+        self.export_model(&path, format)?;
+        Ok(path)
+    }
+
+    pub fn part_names(&self) -> Vec<String> {
+        self.model.as_ref().map(|m| m.part_names()).unwrap_or_default()
+    }
+
+    pub fn toggle_part(&mut self, index: usize) -> bool {
+        self.model.as_mut().map(|m| m.toggle_part(index)).unwrap_or(false)
+    }
+
     #[cfg(feature = "desktop")]
     pub fn load_file(&mut self, renderer: &mut Renderer, path: &str) {
+        self.open_file(renderer, path, false);
+    }
+
+    #[cfg(feature = "desktop")]
+    pub fn add_file(&mut self, renderer: &mut Renderer, path: &str) {
+        self.open_file(renderer, path, true);
+    }
+
+    #[cfg(feature = "desktop")]
+    fn open_file(&mut self, renderer: &mut Renderer, path: &str, add: bool) {
         if let Some(ext) = std::path::Path::new(path).extension().and_then(|e| e.to_str()) {
             match ext.to_lowercase().as_str() {
-                "obj" => {
-                    match Model::from_obj(path) {
-                        Ok(mut model) => {
-                            model.calculate_normals();
-                            self.model_info = Some(ModelInfo::from_model(&model, Some(path.to_string())));
-                            self.model = Some(model);
+                "obj" | "stl" | "ply" | "gltf" | "glb" => {
+                    let result = if add { self.add_model_from_path(path) } else { self.load_model_from_path(path) };
+                    match result {
+                        Ok(()) => {
+                            if let Some(model) = &self.model {
+                                renderer.upload_material_textures(model);
+                                renderer.upload_model(model);
+                            }
                             self.config.add_recent_file(path.to_string());
                             if let Err(e) = self.config.save() {
                                 eprintln!("Failed to save config: {}", e);
                             }
-                            println!("Loaded OBJ model: {}", path);
+                            println!("{} {} model: {}", if add { "Added" } else { "Loaded" }, ext.to_uppercase(), path);
                         }
                         Err(e) => {
-                            eprintln!("Failed to load OBJ: {}", e);
+                            eprintln!("Failed to load {}: {}", ext.to_uppercase(), e);
                         }
                     }
                 }
@@ -142,17 +469,670 @@ impl App {
         }
     }
 
-    pub fn load_file_from_bytes(&mut self, data: &[u8], extension: &str) -> Result<(), Box<dyn std::error::Error>> {
-        match extension.to_lowercase().as_str() {
-            "obj" => {
-                let mut model = Model::from_obj_bytes(data)?;
-                model.calculate_normals();
-                self.model_info = Some(ModelInfo::from_model(&model, None));
-                self.model = Some(model);
-                Ok(())
+    fn read_model(path: &str) -> Result<Model, Box<dyn std::error::Error>> {
+        let ext = std::path::Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+        match ext.as_str() {
+            "obj" => Model::from_obj(path),
+            "stl" => Model::from_stl(path),
+            "ply" => Model::from_ply(path),
+            "gltf" | "glb" => Model::from_gltf(path),
+            _ => Err(format!("Unsupported file type: {}", ext).into()),
+        }
+    }
+
+    pub fn load_model_from_path(&mut self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let model = Self::read_model(path)?;
+        self.set_model(model, Some(path.to_string()));
+        Ok(())
+    }
+
+    pub fn add_model_from_path(&mut self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let other = Self::read_model(path)?;
+        let Some(model) = self.model.as_mut() else {
+            self.set_model(other, Some(path.to_string()));
+            return Ok(());
+        };
+        let first_new = model.parts.len();
+        let name = std::path::Path::new(path).file_stem().and_then(|s| s.to_str()).unwrap_or("object");
+        model.append(other, name);
+        self.add_object_transforms(first_new);
+        self.refresh_model();
+        Ok(())
+    }
+
+    fn set_model(&mut self, mut model: Model, path: Option<String>) {
+        model.apply_normals(self.normal_settings());
+        self.model = Some(model);
+        let tool = self.editor.tool;
+        let color_picker = std::mem::take(&mut self.editor.color_picker);
+        self.editor = Editor::new();
+        self.editor.tool = tool;
+        self.editor.color_picker = color_picker;
+        self.editor.history_budget = self.history_budget();
+        self.gizmo = Gizmo::new();
+        self.cleanup_report = None;
+        if let Some(model) = &self.model {
+            let (min, max) = model.bounds();
+            self.editor.set_pivot((min + max) * 0.5);
+        }
+        self.add_object_transforms(0);
+        self.refresh_model_info(path);
+    }
+
+    fn add_object_transforms(&mut self, first: usize) {
+        if let Some(model) = &self.model {
+            let pivots = (first..model.parts.len())
+                .map(|part| model.part_bounds(part).map(|(min, max)| (min + max) * 0.5).unwrap_or(Vec3::ZERO));
+            self.editor.add_objects(pivots);
+        }
+    }
+
+    fn refresh_model(&mut self) {
+        let path = self.model_info.as_ref().and_then(|info| info.file_path.clone());
+        self.refresh_model_info(path);
+    }
+
+    /// Validation and mass properties are only recomputed while the info or
+    /// the validation is displayed; otherwise they wait for `analyze_model`.
+    fn refresh_model_info(&mut self, path: Option<String>) {
+        if let Some(model) = &self.model {
+            let mut info = ModelInfo::summary(model, path);
+            info.unit = self.unit();
+            info.density = self.config.density;
+            info.cleanup = self.cleanup_report.clone();
+            info.transform = self.editor.transform.to_matrix();
+            if let Some(previous) = self.model_info.take() {
+                info.reuse_analysis(previous);
             }
-            _ => Err(format!("Unsupported file type: {}", extension).into())
+            self.model_info = Some(info);
+        }
+        if self.show_info || self.show_validation {
+            self.analyze_model();
         }
+        self.update_validation_highlight();
+        self.update_selection_highlight();
+    }
+
+    fn analyze_model(&mut self) {
+        if let (Some(model), Some(info)) = (&self.model, self.model_info.as_mut()) {
+            info.analyze(model);
+        }
+    }
+
+    pub fn validation_report(&mut self) -> Option<&MeshReport> {
+        self.analyze_model();
+        self.model_info.as_ref().and_then(|info| info.validation.as_ref())
     }
-}
 
+    fn update_validation_highlight(&mut self) {
+        if self.show_validation {
+            self.analyze_model();
+        }
+        let report = self.model_info.as_ref().and_then(|info| info.validation.as_ref());
+        self.validation_highlight = match (&self.model, report) {
+            (Some(model), Some(report)) if self.show_validation => {
+                Some(validation::highlight(&model.vertices, &model.indices, report))
+            }
+            _ => None,
+        };
+    }
+
+    /// Matrix placing the model in the scene: fitted to the view, with the
+    /// editor transform applied in model space as it is on export.
+    pub fn model_matrix(&self) -> Mat4 {
+        self.fit_matrix() * self.editor.transform.to_matrix()
+    }
+
+    fn model_size(&self) -> f32 {
+        self.model
+            .as_ref()
+            .map(|m| {
+                let (min, max) = m.bounds();
+                (max - min).max_element()
+            })
+            .filter(|size| *size > 0.0)
+            .unwrap_or(FIT_SIZE)
+    }
+
+    fn nudge_transform(&mut self, axis: Vec3) {
+        let step = self.model_size() * 0.05;
+        let tool = self.editor.tool;
+        if tool == TransformTool::None {
+            return;
+        }
+        if matches!(self.editor.selection.mode, SelectionMode::Vertex | SelectionMode::Face) {
+            self.edit_mesh(|model, topology, selection| {
+                let vertices = selection.mesh_vertices(topology, model);
+                let positions: Vec<Vec3> = vertices
+                    .iter()
+                    .filter_map(|&vertex| mesh_edit::vertex_position(model, vertex))
+                    .collect();
+                if positions.is_empty() {
+                    return None;
+                }
+                let mut transform = Transform {
+                    pivot: positions.iter().sum::<Vec3>() / positions.len() as f32,
+                    ..Transform::new()
+                };
+                transform.nudge(tool, axis, step);
+                mesh_edit::move_vertices(model, vertices, transform.to_matrix())
+            });
+            return;
+        }
+        for target in self.transform_targets() {
+            self.editor.transform_of_mut(target).nudge(tool, axis, step);
+        }
+        self.editor.save_state();
+        self.apply_object_transforms();
+    }
+
+    pub fn clean_up_mesh(&mut self) -> Option<CleanupReport> {
+        let tolerance = self.config.weld_tolerance.max(0.0);
+        let mut report = None;
+        self.edit_mesh(|model, _, _| {
+            let (command, cleaned) = mesh_edit::clean_up(model, tolerance);
+            report = Some(cleaned);
+            command
+        });
+        if report.as_ref().is_some_and(|report| !report.is_empty()) {
+            self.cleanup_report = report.clone();
+            if let Some(info) = self.model_info.as_mut() {
+                info.cleanup = report.clone();
+            }
+        }
+        report
+    }
+
+    fn edit_mesh(&mut self, build: impl FnOnce(&Model, &MeshTopology, &Selection) -> Option<MeshCommand>) {
+        if self.topology().is_none() {
+            return;
+        }
+        let (Some(model), Some(topology)) = (self.model.as_ref(), self.topology.as_ref()) else {
+            return;
+        };
+        let Some(command) = build(model, topology, &self.editor.selection) else {
+            println!("Nothing to edit");
+            return;
+        };
+        let Some(model) = self.model.as_mut() else {
+            return;
+        };
+        command.apply(model);
+        println!("{}", command.summary());
+        if command.changes_faces() {
+            self.editor.selection.clear();
+        }
+        self.editor.record(Command::Mesh(command));
+        self.refresh_model();
+    }
+
+    pub fn active_object(&self) -> Option<usize> {
+        let selection = &self.editor.selection;
+        selection.active_object.filter(|_| selection.mode == SelectionMode::Object)
+    }
+
+    fn transform_targets(&self) -> Vec<Option<usize>> {
+        let selection = &self.editor.selection;
+        if selection.mode != SelectionMode::Object || selection.selected_objects.is_empty() {
+            return vec![None];
+        }
+        let mut objects: Vec<usize> = selection.selected_objects.iter().copied().collect();
+        objects.sort_unstable();
+        objects.into_iter().map(Some).collect()
+    }
+
+    fn apply_object_transforms(&mut self) {
+        let Some(model) = self.model.as_mut() else {
+            return;
+        };
+        let mut changed = false;
+        for (part, transform) in self.editor.object_transforms.iter().enumerate() {
+            changed |= model.set_part_transform(part, transform.to_matrix());
+        }
+        if changed {
+            self.refresh_model();
+        }
+    }
+
+    /// Shows the objects at the editor's transforms while dragging; they are
+    /// baked when the drag ends.
+    fn preview_object_transforms(&mut self) {
+        if let Some(model) = self.model.as_mut() {
+            for (part, transform) in self.editor.object_transforms.iter().enumerate() {
+                model.preview_part_transform(part, transform.to_matrix());
+            }
+        }
+    }
+
+    fn isolate_selected_objects(&mut self) {
+        let selected = self.editor.selection.selected_objects.clone();
+        let Some(model) = self.model.as_mut() else {
+            return;
+        };
+        if selected.is_empty() {
+            println!("No objects selected");
+            return;
+        }
+        let isolated = model
+            .parts
+            .iter()
+            .enumerate()
+            .all(|(index, part)| part.visible == selected.contains(&index));
+        for (index, part) in model.parts.iter_mut().enumerate() {
+            part.visible = isolated || selected.contains(&index);
+        }
+        println!("{}", if isolated { "Showing all objects" } else { "Isolated selected objects" });
+    }
+
+    pub fn info_text(&mut self) -> Option<String> {
+        self.analyze_model();
+        let info = self.model_info.as_ref()?;
+        let object_info = self.active_object().and_then(|object| {
+            let model = self.model.as_ref()?;
+            let mut object_info = ModelInfo::from_part(model, object, info.file_path.clone(), info.transform)?;
+            object_info.unit = info.unit;
+            object_info.density = info.density;
+            Some(object_info)
+        });
+        Some(object_info.as_ref().unwrap_or(info).format_info())
+    }
+
+    fn fit_matrix(&self) -> Mat4 {
+        self.model.as_ref().map(|m| m.fit_transform(FIT_SIZE)).unwrap_or(Mat4::IDENTITY)
+    }
+
+    fn snapping(&self, invert: bool) -> Option<Snapping> {
+        (self.config.snapping != invert).then_some(Snapping {
+            translate: self.config.snap_translate,
+            rotate_degrees: self.config.snap_rotate,
+            scale: self.config.snap_scale,
+        })
+    }
+
+    fn gizmo_space(&self) -> (Option<usize>, Mat4) {
+        match self.active_object() {
+            Some(object) => (Some(object), self.model_matrix()),
+            None => (None, self.fit_matrix()),
+        }
+    }
+
+    pub fn hover_gizmo(&mut self, cursor: Vec2, viewport: Vec2) {
+        let (target, space) = self.gizmo_space();
+        let view = GizmoView { camera: &self.camera, fit: space, viewport };
+        self.gizmo.hovered = match self.model {
+            Some(_) => self.gizmo.pick(self.editor.tool, self.editor.transform_of(target), &view, cursor),
+            None => None,
+        };
+    }
+
+    /// Starts dragging the gizmo handle under the cursor. Returns false when
+    /// there is none so the drag can orbit the camera instead.
+    pub fn begin_gizmo_drag(&mut self, cursor: Vec2, viewport: Vec2) -> bool {
+        self.hover_gizmo(cursor, viewport);
+        let Some(handle) = self.gizmo.hovered else {
+            return false;
+        };
+        let (target, space) = self.gizmo_space();
+        let view = GizmoView { camera: &self.camera, fit: space, viewport };
+        self.gizmo.begin_drag(handle, self.editor.tool, self.editor.transform_of(target), &view, cursor);
+        true
+    }
+
+    pub fn drag_gizmo(&mut self, cursor: Vec2, viewport: Vec2, invert_snapping: bool) {
+        let snapping = self.snapping(invert_snapping);
+        let (target, space) = self.gizmo_space();
+        let view = GizmoView { camera: &self.camera, fit: space, viewport };
+        let before = self.editor.transform_of(target).clone();
+        self.gizmo.drag_to(self.editor.tool, self.editor.transform_of_mut(target), &view, cursor, snapping);
+        if target.is_none() {
+            return;
+        }
+        let after = self.editor.transform_of(target).clone();
+        for other in self.transform_targets().into_iter().filter(|&other| other != target) {
+            self.editor.transform_of_mut(other).apply_change(&before, &after);
+        }
+        self.preview_object_transforms();
+    }
+
+    pub fn end_gizmo_drag(&mut self) {
+        let (target, _) = self.gizmo_space();
+        if self.gizmo.end_drag(self.editor.transform_of(target)) {
+            self.editor.save_state();
+        }
+        self.apply_object_transforms();
+    }
+
+    pub fn selection_mode(&self) -> SelectionMode {
+        SelectionMode::from_name(&self.config.selection_mode).unwrap_or(SelectionMode::Face)
+    }
+
+    pub fn pick(&mut self, cursor: Vec2, viewport: Vec2) -> Option<RayHit> {
+        self.update_bvh();
+        let model = self.model.as_ref()?;
+        let (origin, direction) = self.camera.cursor_ray(cursor, viewport);
+        let to_model = self.model_matrix().inverse();
+        let origin = to_model.transform_point3(origin);
+        let direction = to_model.transform_vector3(direction);
+        self.bvh.as_ref()?.intersect(model, origin, direction, |face| model.is_face_visible(face))
+    }
+
+    fn update_bvh(&mut self) {
+        if let Some(model) = &self.model {
+            if self.bvh.as_ref().map(|bvh| bvh.revision) != Some(model.revision) {
+                self.bvh = Some(Bvh::new(model));
+            }
+        }
+    }
+
+    pub fn select_at(&mut self, cursor: Vec2, viewport: Vec2, op: SelectOp) {
+        let hit = self.pick(cursor, viewport);
+        let mode = self.selection_mode();
+        let (Some(hit), Some(model)) = (hit, self.model.as_ref()) else {
+            if op == SelectOp::Replace {
+                self.editor.selection.clear();
+                self.update_selection_highlight();
+            }
+            return;
+        };
+        let selection = &mut self.editor.selection;
+        match mode {
+            SelectionMode::Vertex => {
+                let vertex = model.indices[hit.face * 3 + hit.nearest_corner()] as usize;
+                let vertex = model.source_vertex(vertex);
+                selection.apply_vertices(op, [vertex]);
+                println!("Selected vertex {}", vertex);
+            }
+            SelectionMode::Object => {
+                let Some(part) = model.part_of_face(hit.face) else {
+                    return;
+                };
+                selection.apply_objects(op, [part]);
+                println!("Selected object {}: {}", part + 1, model.parts[part].name);
+            }
+            _ => {
+                selection.apply_faces(op, [hit.face]);
+                let p = hit.point;
+                println!("Selected face {} at ({:.3}, {:.3}, {:.3})", hit.face, p.x, p.y, p.z);
+            }
+        }
+        self.update_selection_highlight();
+    }
+
+    pub fn begin_selection(&mut self, cursor: Vec2) {
+        let shape = SelectionShape::from_name(&self.config.selection_shape).unwrap_or(SelectionShape::Box);
+        self.selection_region = Some(SelectionRegion::new(shape, cursor));
+    }
+
+    pub fn extend_selection(&mut self, cursor: Vec2) {
+        if let Some(region) = self.selection_region.as_mut() {
+            region.extend(cursor);
+        }
+    }
+
+    pub fn end_selection(&mut self, viewport: Vec2, op: SelectOp, depth: Option<&DepthImage>) {
+        let Some(region) = self.selection_region.take() else {
+            return;
+        };
+        if region.is_click() {
+            self.select_at(region.points[0], viewport, op);
+        } else {
+            self.select_region(&region, viewport, op, depth);
+        }
+    }
+
+    /// Selects the vertices or faces (by centroid) of visible parts whose
+    /// projection falls inside `region`. With `select_visible_only`, elements
+    /// hidden behind the surface are skipped; faces are two-sided like the
+    /// shading, so back faces only count as hidden when something covers them.
+    pub fn select_region(&mut self, region: &SelectionRegion, viewport: Vec2, op: SelectOp, depth: Option<&DepthImage>) {
+        self.update_bvh();
+        let (Some(model), Some(bvh)) = (self.model.as_ref(), self.bvh.as_ref()) else {
+            return;
+        };
+        let model_matrix = self.model_matrix();
+        let eye_in_model = model_matrix.inverse().transform_point3(self.camera.eye_position());
+        // Face centroids sit slightly inside curved surfaces, so allow some slack.
+        let tolerance = self.model_size() * 0.01;
+        let to_clip = self.camera.get_projection_matrix() * self.camera.get_view_matrix() * model_matrix;
+        let visible_only = self.config.select_visible_only;
+        let depth = depth.filter(|_| visible_only);
+        let (near, far) = (self.camera.near, self.camera.far);
+        let linear_depth = |z: f32| near * far / (far - z * (far - near));
+        let inside = |point: Vec3| {
+            let clip = to_clip * point.extend(1.0);
+            if clip.w <= 1e-6 {
+                return false;
+            }
+            let ndc = clip.truncate() / clip.w;
+            let pixel = Vec2::new((ndc.x + 1.0) * 0.5 * viewport.x, (1.0 - ndc.y) * 0.5 * viewport.y);
+            if !region.contains(pixel) {
+                return false;
+            }
+            match depth {
+                Some(depth) => {
+                    let scale = Vec2::new(depth.width as f32, depth.height as f32) / viewport.max(Vec2::ONE);
+                    depth
+                        .sample(pixel * scale)
+                        .map(|sample| linear_depth(ndc.z) <= linear_depth(sample) * 1.01 + 1e-3)
+                        .unwrap_or(true)
+                }
+                // Without a depth buffer, cast a ray towards the point instead.
+                None if visible_only => {
+                    let direction = point - eye_in_model;
+                    let distance = direction.length();
+                    let nearest = bvh.intersect(model, eye_in_model, direction / distance.max(1e-12), |face| model.is_face_visible(face));
+                    nearest.map(|hit| hit.distance >= distance - tolerance).unwrap_or(true)
+                }
+                None => true,
+            }
+        };
+
+        match self.selection_mode() {
+            SelectionMode::Vertex => {
+                let mut vertices = HashSet::new();
+                for index in model.visible_vertex_ranges().into_iter().flatten() {
+                    let source = model.source_vertex(index);
+                    if !vertices.contains(&source) && inside(model.vertices[index].position_vec3()) {
+                        vertices.insert(source);
+                    }
+                }
+                println!("Selected {} vertices", vertices.len());
+                self.editor.selection.apply_vertices(op, vertices);
+            }
+            SelectionMode::Object => {
+                // An object is picked when any of its faces is.
+                let objects: Vec<usize> = (0..model.parts.len())
+                    .filter(|&part| model.parts[part].visible)
+                    .filter(|&part| {
+                        let range = model.parts[part].index_range();
+                        (range.start as usize / 3..range.end as usize / 3).any(|face| {
+                            let corner = |i: usize| model.vertices[model.indices[face * 3 + i] as usize].position_vec3();
+                            inside((corner(0) + corner(1) + corner(2)) / 3.0)
+                        })
+                    })
+                    .collect();
+                println!("Selected {} objects", objects.len());
+                self.editor.selection.apply_objects(op, objects);
+            }
+            _ => {
+                let faces: Vec<usize> = model
+                    .visible_face_ranges()
+                    .into_iter()
+                    .flatten()
+                    .filter(|&face| {
+                        let corner = |i: usize| model.vertices[model.indices[face * 3 + i] as usize].position_vec3();
+                        inside((corner(0) + corner(1) + corner(2)) / 3.0)
+                    })
+                    .collect();
+                println!("Selected {} faces", faces.len());
+                self.editor.selection.apply_faces(op, faces);
+            }
+        }
+        self.update_selection_highlight();
+    }
+
+    pub fn topology(&mut self) -> Option<&MeshTopology> {
+        let model = self.model.as_ref()?;
+        if self.topology.as_ref().map(|topology| topology.revision) != Some(model.revision) {
+            self.topology = Some(MeshTopology::new(model));
+        }
+        self.topology.as_ref()
+    }
+
+    fn edit_selection(&mut self, name: &str, edit: impl FnOnce(&mut Selection, &MeshTopology, &Model)) {
+        if self.topology().is_none() {
+            return;
+        }
+        let (Some(model), Some(topology)) = (self.model.as_ref(), self.topology.as_ref()) else {
+            return;
+        };
+        edit(&mut self.editor.selection, topology, model);
+        let selection = &self.editor.selection;
+        if selection.mode == SelectionMode::Object {
+            println!("{}: {} objects", name, selection.selected_objects.len());
+        } else {
+            println!(
+                "{}: {} vertices, {} faces",
+                name,
+                selection.selected_vertices.len(),
+                selection.selected_faces.len()
+            );
+        }
+        self.update_selection_highlight();
+    }
+
+    fn update_selection_highlight(&mut self) {
+        self.selection_highlight = match &self.model {
+            Some(model) if self.editor.selection.is_selected() => Some(selection::highlight(model, &self.editor.selection)),
+            _ => None,
+        };
+    }
+
+    pub fn highlights(&self) -> Vec<&MeshHighlight> {
+        self.selection_highlight.iter().chain(&self.validation_highlight).collect()
+    }
+
+    pub fn overlay_lines(&self, viewport: Vec2) -> Vec<EdgeInstance> {
+        let mut lines = self.gizmo_lines(viewport);
+        lines.extend(self.object_outlines());
+        if let Some(region) = self.selection_region.as_ref().filter(|region| !region.is_click()) {
+            // Points just past the near plane land exactly on their pixels.
+            let points: Vec<Vec3> = region
+                .outline()
+                .into_iter()
+                .map(|pixel| {
+                    let (origin, direction) = self.camera.cursor_ray(pixel, viewport);
+                    origin + direction * self.camera.near
+                })
+                .collect();
+            lines.extend(points.windows(2).map(|pair| EdgeInstance {
+                start: pair[0].to_array(),
+                end: pair[1].to_array(),
+                color: [1.0, 1.0, 1.0, 1.0],
+            }));
+        }
+        lines
+    }
+
+    fn object_outlines(&self) -> Vec<EdgeInstance> {
+        let Some(model) = self.model.as_ref() else {
+            return Vec::new();
+        };
+        let model_matrix = self.model_matrix();
+        let active = self.active_object();
+        let mut lines = Vec::new();
+        for object in self.transform_targets().into_iter().flatten() {
+            let Some((min, max)) = model.part_bounds(object) else {
+                continue;
+            };
+            let color = if Some(object) == active { ACTIVE_OBJECT_COLOR } else { SELECTED_OBJECT_COLOR };
+            let to_scene = model_matrix * model.part_display_transform(object);
+            let corner = |i: usize| {
+                let pick = |axis: usize| if i & (1 << axis) == 0 { min[axis] } else { max[axis] };
+                to_scene.transform_point3(Vec3::new(pick(0), pick(1), pick(2)))
+            };
+            // Corners differing in exactly one coordinate bit share an edge.
+            for i in 0..8 {
+                for axis in 0..3 {
+                    let j = i | (1 << axis);
+                    if j != i {
+                        lines.push(EdgeInstance {
+                            start: corner(i).to_array(),
+                            end: corner(j).to_array(),
+                            color,
+                        });
+                    }
+                }
+            }
+        }
+        lines
+    }
+
+    pub fn gizmo_lines(&self, viewport: Vec2) -> Vec<EdgeInstance> {
+        if self.model.is_none() {
+            return Vec::new();
+        }
+        let (target, space) = self.gizmo_space();
+        let view = GizmoView { camera: &self.camera, fit: space, viewport };
+        self.gizmo.lines(self.editor.tool, self.editor.transform_of(target), &view)
+    }
+
+    pub fn normal_settings(&self) -> NormalSettings {
+        NormalSettings {
+            mode: NormalMode::from_name(&self.config.normal_mode).unwrap_or(NormalMode::File),
+            weighting: NormalWeighting::from_name(&self.config.normal_weighting).unwrap_or(NormalWeighting::Area),
+            crease_angle: self.config.crease_angle.clamp(0.0, 180.0),
+        }
+    }
+
+    pub fn set_normal_mode(&mut self, mode: NormalMode) {
+        self.config.normal_mode = mode.name().to_string();
+        let settings = self.normal_settings();
+        if let Some(model) = self.model.as_mut() {
+            model.apply_normals(settings);
+            let path = self.model_info.as_ref().and_then(|info| info.file_path.clone());
+            self.refresh_model_info(path);
+        }
+    }
+
+    pub fn unit(&self) -> Unit {
+        Unit::from_name(&self.config.unit).unwrap_or_default()
+    }
+
+    pub fn set_unit(&mut self, unit: Unit) {
+        self.config.unit = unit.suffix().to_string();
+        if let Some(info) = self.model_info.as_mut() {
+            info.unit = unit;
+        }
+    }
+
+    pub fn set_background_color(&mut self, color: Color) {
+        self.editor.color_picker.set_background_color(color);
+        self.config.background_color = color.to_u32();
+    }
+
+    pub fn load_file_from_bytes(&mut self, data: &[u8], extension: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.load_file_from_bytes_with_resources(data, extension, &HashMap::new())
+    }
+
+    pub fn load_file_from_bytes_with_resources(
+        &mut self,
+        data: &[u8],
+        extension: &str,
+        resources: &HashMap<String, Vec<u8>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let model = match extension.to_lowercase().as_str() {
+            "obj" => Model::from_obj_bytes_with_resources(data, resources)?,
+            "stl" => Model::from_stl_bytes(data)?,
+            "ply" => Model::from_ply_bytes(data)?,
+            "gltf" | "glb" => Model::from_gltf_bytes_with_resources(data, resources)?,
+            _ => return Err(format!("Unsupported file type: {}", extension).into()),
+        };
+        self.set_model(model, None);
+        Ok(())
+    }
+}
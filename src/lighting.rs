@@ -0,0 +1,178 @@
+use glam::{Mat4, Vec3};
+use serde::{Deserialize, Serialize};
+use crate::display::ShadingMode;
+use crate::utils;
+
+pub const MAX_LIGHTS: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightKind {
+    Directional,
+    Point,
+}
+
+impl LightKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "directional" => Some(LightKind::Directional),
+            "point" => Some(LightKind::Point),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LightKind::Directional => "directional",
+            LightKind::Point => "point",
+        }
+    }
+}
+
+/// A single light. Directional lights shine from `position` towards the
+/// origin; point lights sit at `position` and fade out at `range` (0 = no falloff).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Light {
+    pub kind: String,
+    pub position: [f32; 3],
+    pub color: u32,
+    pub intensity: f32,
+    pub range: f32,
+}
+
+impl Default for Light {
+    fn default() -> Self {
+        Self::directional([0.5, 1.0, 0.7], 0xFFFFFF, 1.0)
+    }
+}
+
+impl Light {
+    pub fn directional(direction: [f32; 3], color: u32, intensity: f32) -> Self {
+        Self {
+            kind: LightKind::Directional.name().to_string(),
+            position: direction,
+            color,
+            intensity,
+            range: 0.0,
+        }
+    }
+
+    pub fn point(position: [f32; 3], color: u32, intensity: f32, range: f32) -> Self {
+        Self {
+            kind: LightKind::Point.name().to_string(),
+            position,
+            color,
+            intensity,
+            range,
+        }
+    }
+
+    pub fn kind(&self) -> LightKind {
+        LightKind::from_name(&self.kind).unwrap_or(LightKind::Directional)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LightingRig {
+    pub name: String,
+    pub ambient: f32,
+    pub specular: f32,
+    pub shininess: f32,
+    pub lights: Vec<Light>,
+}
+
+impl Default for LightingRig {
+    fn default() -> Self {
+        Self {
+            name: String::from("default"),
+            ambient: 0.3,
+            specular: 0.25,
+            shininess: 32.0,
+            lights: vec![Light::default()],
+        }
+    }
+}
+
+impl LightingRig {
+    pub fn defaults() -> Vec<Self> {
+        vec![
+            Self::default(),
+            Self {
+                name: String::from("studio"),
+                ambient: 0.15,
+                specular: 0.4,
+                shininess: 48.0,
+                lights: vec![
+                    Light::directional([1.0, 1.0, 1.0], 0xFFF4E0, 0.8),
+                    Light::directional([-1.0, 0.3, 0.5], 0xC8D8FF, 0.35),
+                    Light::directional([0.0, 0.5, -1.0], 0xFFFFFF, 0.5),
+                ],
+            },
+            Self {
+                name: String::from("lamp"),
+                ambient: 0.1,
+                specular: 0.5,
+                shininess: 64.0,
+                lights: vec![Light::point([2.0, 3.0, 3.0], 0xFFFFFF, 1.0, 0.0)],
+            },
+        ]
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GpuLight {
+    pub position: [f32; 4],
+    pub color: [f32; 4],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LightingUniforms {
+    pub eye: [f32; 4],
+    pub ambient: f32,
+    pub specular: f32,
+    pub shininess: f32,
+    pub light_count: u32,
+    pub shading: u32,
+    pub _padding: [u32; 3],
+    pub lights: [GpuLight; MAX_LIGHTS],
+}
+
+impl LightingUniforms {
+    /// Packs a rig for the shader. With `headlight` the rig is defined in
+    /// camera space and follows the view.
+    pub fn new(rig: &LightingRig, view: Mat4, headlight: bool, shading: ShadingMode) -> Self {
+        let camera_to_world = view.inverse();
+        let eye = camera_to_world.transform_point3(Vec3::ZERO);
+        let mut lights = [GpuLight::default(); MAX_LIGHTS];
+        for (gpu, light) in lights.iter_mut().zip(&rig.lights) {
+            let position = Vec3::from(light.position);
+            let [r, g, b, _] = utils::color_to_rgba(light.color);
+            gpu.position = match (light.kind(), headlight) {
+                (LightKind::Directional, false) => position.normalize_or_zero().extend(0.0),
+                (LightKind::Directional, true) => camera_to_world.transform_vector3(position).normalize_or_zero().extend(0.0),
+                (LightKind::Point, false) => position.extend(light.range.max(0.0)),
+                (LightKind::Point, true) => camera_to_world.transform_point3(position).extend(light.range.max(0.0)),
+            }
+            .to_array();
+            let point = if light.kind() == LightKind::Point { 1.0 } else { 0.0 };
+            gpu.color = [r * light.intensity, g * light.intensity, b * light.intensity, point];
+        }
+        Self {
+            eye: eye.extend(1.0).to_array(),
+            ambient: rig.ambient,
+            specular: rig.specular,
+            shininess: rig.shininess.max(1.0),
+            light_count: rig.lights.len().min(MAX_LIGHTS) as u32,
+            shading: match shading {
+                ShadingMode::Faceted => 0,
+                ShadingMode::Smooth => 1,
+                ShadingMode::Unlit => 2,
+            },
+            _padding: [0; 3],
+            lights,
+        }
+    }
+}
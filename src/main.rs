@@ -1,14 +1,69 @@
-use bookish_3d_viewer::{App, renderer::Renderer, image_viewer::ImageMode};
+use bookish_3d_viewer::{App, renderer::Renderer, export::ExportFormat, image_viewer::ImageMode, display::{DisplayMode, ShadingMode}, selection::SelectOp};
+use glam::Vec2;
 use std::rc::Rc;
 use winit::{
     event::*,
     event_loop::{ControlFlow, EventLoop},
     window::WindowBuilder,
-    keyboard::{Key, NamedKey},
+    keyboard::{Key, ModifiersState, NamedKey},
 };
 
 fn main() {
     env_logger::init();
+
+    if std::env::args().nth(1).as_deref() == Some("--validate") {
+        let Some(path) = std::env::args().nth(2) else {
+            eprintln!("Usage: bookish-3d-viewer --validate <model>");
+            std::process::exit(2);
+        };
+        let mut app = App::new();
+        if let Err(e) = app.load_model_from_path(&path) {
+            eprintln!("Failed to load model: {}", e);
+            std::process::exit(2);
+        }
+        match app.validation_report() {
+            Some(report) => {
+                println!("{}", report.format_report());
+                std::process::exit(if report.is_clean() { 0 } else { 1 });
+            }
+            None => {
+                println!("No faces to validate");
+                std::process::exit(0);
+            }
+        }
+    }
+
+    if std::env::args().nth(1).as_deref() == Some("--cleanup") {
+        let Some(path) = std::env::args().nth(2) else {
+            eprintln!("Usage: bookish-3d-viewer --cleanup <model> [output]");
+            std::process::exit(2);
+        };
+        let mut app = App::new();
+        if let Err(e) = app.load_model_from_path(&path) {
+            eprintln!("Failed to load model: {}", e);
+            std::process::exit(2);
+        }
+        if let Some(report) = app.clean_up_mesh() {
+            println!("{}", report.format_report());
+        }
+        if let Some(output) = std::env::args().nth(3) {
+            let extension = std::path::Path::new(&output)
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or_default();
+            let Some(format) = ExportFormat::from_name(extension) else {
+                eprintln!("Unknown export format: {}", extension);
+                std::process::exit(2);
+            };
+            if let Err(e) = app.export_model(&output, format) {
+                eprintln!("Failed to export model: {}", e);
+                std::process::exit(1);
+            }
+            println!("Saved {}", output);
+        }
+        std::process::exit(0);
+    }
+
     let event_loop = EventLoop::new().unwrap();
     let window = Rc::new(WindowBuilder::new()
         .with_title("Bookish 3D Viewer")
@@ -33,10 +88,19 @@ fn main() {
         }
     };
 
-    if let Some(path) = std::env::args().nth(1) {
-        app.load_file(&mut renderer, &path);
+    renderer.set_display_mode(DisplayMode::from_name(&app.config.display_mode).unwrap_or_default());
+    renderer.set_shading_mode(ShadingMode::from_name(&app.config.shading_mode).unwrap_or_default());
+
+    // Further models on the command line are added as objects of the first.
+    for (index, path) in std::env::args().skip(1).enumerate() {
+        if index == 0 {
+            app.load_file(&mut renderer, &path);
+        } else {
+            app.add_file(&mut renderer, &path);
+        }
     }
 
+    let mut modifiers = ModifiersState::empty();
     let window_clone = window.clone();
     event_loop.run(move |event, elwt| {
         elwt.set_control_flow(ControlFlow::Poll);
@@ -56,6 +120,9 @@ fn main() {
                     renderer.resize(new_size.width, new_size.height);
                     app.camera.update_aspect(new_size.width as f32, new_size.height as f32);
                 }
+                WindowEvent::ModifiersChanged(new_modifiers) => {
+                    modifiers = new_modifiers.state();
+                }
                 WindowEvent::KeyboardInput {
                     event:
                         KeyEvent {
@@ -66,8 +133,42 @@ fn main() {
                     ..
                 } => {
                     match key {
-                        Key::Character(ref c) if c == "w" || c == "W" => renderer.toggle_wireframe(),
-                        Key::Character(ref c) if c == "f" || c == "F" => renderer.toggle_flat_shading(),
+                        Key::Character(ref c) if modifiers.control_key() && (c == "z" || c == "Z") => {
+                            app.handle_keyboard(if modifiers.shift_key() { "Redo" } else { "Undo" });
+                        }
+                        Key::Character(ref c) if modifiers.control_key() && (c == "y" || c == "Y") => {
+                            app.handle_keyboard("Redo");
+                        }
+                        Key::Character(ref c) if modifiers.control_key() => {
+                            let action = match c.to_lowercase().as_str() {
+                                "l" => "SelectLinked",
+                                "n" => "SelectByNormal",
+                                "b" => "SelectBoundary",
+                                "i" => "InvertSelection",
+                                "f" => "FlipNormals",
+                                "m" => "MergeVertices",
+                                "h" => "FillHoles",
+                                "w" => "CleanUp",
+                                _ => "",
+                            };
+                            app.handle_keyboard(action);
+                        }
+                        Key::Character(ref c) if c == "w" || c == "W" => {
+                            let mode = renderer.display_mode().next();
+                            renderer.set_display_mode(mode);
+                            app.config.display_mode = mode.name().to_string();
+                            println!("Display mode: {}", mode.name());
+                        }
+                        Key::Character(ref c) if c == "f" || c == "F" => {
+                            if !renderer.display_mode().shows_surfaces() {
+                                renderer.set_display_mode(DisplayMode::Shaded);
+                                app.config.display_mode = DisplayMode::Shaded.name().to_string();
+                            }
+                            let mode = renderer.shading_mode().next();
+                            renderer.set_shading_mode(mode);
+                            app.config.shading_mode = mode.name().to_string();
+                            println!("Shading: {}", mode.name());
+                        }
                         _ => {
                             let key_str = match key {
                                 Key::Character(c) => c.as_str(),
@@ -75,7 +176,10 @@ fn main() {
                                 Key::Named(NamedKey::ArrowDown) => "ArrowDown",
                                 Key::Named(NamedKey::ArrowLeft) => "ArrowLeft",
                                 Key::Named(NamedKey::ArrowRight) => "ArrowRight",
+                                Key::Named(NamedKey::PageUp) => "PageUp",
+                                Key::Named(NamedKey::PageDown) => "PageDown",
                                 Key::Named(NamedKey::Escape) => "Escape",
+                                Key::Named(NamedKey::Delete | NamedKey::Backspace) => "Delete",
                                 _ => "",
                             };
                             app.handle_keyboard(key_str);
@@ -87,7 +191,15 @@ fn main() {
                     state: ElementState::Pressed,
                     ..
                 } => {
-                    app.mouse_pressed = true;
+                    let size = window_clone.inner_size();
+                    let viewport = Vec2::new(size.width as f32, size.height as f32);
+                    let cursor = Vec2::new(app.last_mouse_pos.0 as f32, app.last_mouse_pos.1 as f32);
+                    if modifiers.shift_key() {
+                        app.begin_selection(cursor);
+                    } else {
+                        app.begin_gizmo_drag(cursor, viewport);
+                        app.mouse_pressed = true;
+                    }
                 }
                 WindowEvent::MouseInput {
                     button: MouseButton::Left,
@@ -95,6 +207,14 @@ fn main() {
                     ..
                 } => {
                     app.mouse_pressed = false;
+                    app.end_gizmo_drag();
+                    if app.selection_region.is_some() {
+                        let size = window_clone.inner_size();
+                        let viewport = Vec2::new(size.width as f32, size.height as f32);
+                        let depth = if app.config.select_visible_only { renderer.read_depth() } else { None };
+                        let op = SelectOp::from_modifiers(modifiers.control_key(), modifiers.alt_key());
+                        app.end_selection(viewport, op, depth.as_ref());
+                    }
                 }
                 WindowEvent::MouseInput {
                     button: MouseButton::Right,
@@ -111,10 +231,19 @@ fn main() {
                     app.right_mouse_pressed = false;
                 }
                 WindowEvent::CursorMoved { position, .. } => {
-                    if app.mouse_pressed {
+                    let size = window_clone.inner_size();
+                    let viewport = Vec2::new(size.width as f32, size.height as f32);
+                    let cursor = Vec2::new(position.x as f32, position.y as f32);
+                    if app.selection_region.is_some() {
+                        app.extend_selection(cursor);
+                    } else if app.gizmo.is_dragging() {
+                        app.drag_gizmo(cursor, viewport, modifiers.control_key());
+                    } else if app.mouse_pressed {
                         let delta_x = position.x - app.last_mouse_pos.0;
                         let delta_y = position.y - app.last_mouse_pos.1;
                         app.camera.rotate(delta_x as f32 * app.config.camera_sensitivity, delta_y as f32 * app.config.camera_sensitivity);
+                    } else if !app.right_mouse_pressed {
+                        app.hover_gizmo(cursor, viewport);
                     }
                     if app.right_mouse_pressed {
                         let delta_x = position.x - app.last_mouse_pos.0;
@@ -134,7 +263,11 @@ fn main() {
                     }
                 }
                 WindowEvent::DroppedFile(path) => {
-                    app.load_file(&mut renderer, path.to_str().unwrap());
+                    if modifiers.shift_key() {
+                        app.add_file(&mut renderer, path.to_str().unwrap());
+                    } else {
+                        app.load_file(&mut renderer, path.to_str().unwrap());
+                    }
                 }
                 _ => {}
             },
@@ -142,8 +275,15 @@ fn main() {
                 event: WindowEvent::RedrawRequested,
                 window_id,
             } if window_id == window_clone.id() => {
+                let model_texture = if app.image_on_model {
+                    app.image_viewer.bind_group.as_ref()
+                } else {
+                    None
+                };
+
                 let image_plane = if app.image_viewer.has_image()
                     && app.image_viewer.mode == ImageMode::Texture3D
+                    && !app.image_on_model
                 {
                     if let (Some(vb), Some(ib), Some(bg)) = (
                         app.image_viewer.vertex_buffer.as_ref(),
@@ -158,18 +298,29 @@ fn main() {
                     None
                 };
 
+                renderer.set_model_transform(app.editor.transform.to_matrix());
+                let size = window_clone.inner_size();
+                renderer.set_overlay_lines(&app.overlay_lines(Vec2::new(size.width as f32, size.height as f32)));
+                let previous_stats = renderer.frame_stats().map(|stats| stats.fps);
                 match renderer.render(
                     &app.camera,
                     app.model.as_ref(),
+                    model_texture,
                     image_plane,
-                    app.config.wireframe_color,
-                    app.config.flat_color,
+                    &app.highlights(),
+                    &app.config,
                 ) {
                     Ok(_) => {}
                     Err(wgpu::SurfaceError::Lost) => renderer.resize(renderer.size().0, renderer.size().1),
                     Err(wgpu::SurfaceError::OutOfMemory) => elwt.exit(),
                     Err(e) => eprintln!("Render error: {:?}", e),
                 }
+                if let Some(stats) = renderer.frame_stats().filter(|stats| Some(stats.fps) != previous_stats) {
+                    window_clone.set_title(&format!(
+                        "Bookish 3D Viewer - {:.1} fps, {:.2} ms/frame ({:.2} ms render)",
+                        stats.fps, stats.frame_ms, stats.render_ms
+                    ));
+                }
             }
             Event::AboutToWait => {
                 window_clone.request_redraw();
@@ -0,0 +1,209 @@
+use glam::{DMat3, DVec3, Mat4, Vec3};
+use crate::model::Vertex;
+use crate::utils;
+
+#[derive(Debug, Clone, Copy)]
+pub struct MassProperties {
+    pub surface_area: f64,
+    pub volume: Option<f64>,
+    pub centroid: Vec3,
+    pub center_of_mass: Option<Vec3>,
+    pub inertia: Option<DMat3>,
+    pub principal_moments: Option<DVec3>,
+    pub obb_size: Vec3,
+    pub obb_axes: [Vec3; 3],
+}
+
+impl MassProperties {
+    pub fn compute(vertices: &[Vertex], indices: &[u32], closed: bool, transform: Mat4) -> Self {
+        let transform = transform.as_dmat4();
+        let positions: Vec<DVec3> = vertices.iter().map(|v| transform.transform_point3(v.position_vec3().as_dvec3())).collect();
+
+        let mut surface_area = 0.0;
+        let mut area_moment = DVec3::ZERO;
+        let mut volume = 0.0;
+        let mut volume_moment = DVec3::ZERO;
+        let mut covariance = DMat3::ZERO;
+        let canonical = DMat3::from_cols_array(&[2.0, 1.0, 1.0, 1.0, 2.0, 1.0, 1.0, 1.0, 2.0]) * (1.0 / 120.0);
+
+        for tri in indices.chunks_exact(3) {
+            let [a, b, c] = [positions[tri[0] as usize], positions[tri[1] as usize], positions[tri[2] as usize]];
+            let area = (b - a).cross(c - a).length() * 0.5;
+            surface_area += area;
+            area_moment += (a + b + c) / 3.0 * area;
+
+            let det = a.dot(b.cross(c));
+            volume += det / 6.0;
+            volume_moment += (a + b + c) / 4.0 * (det / 6.0);
+            let basis = DMat3::from_cols(a, b, c);
+            covariance += basis * canonical * basis.transpose() * det;
+        }
+
+        let centroid = if surface_area > 0.0 {
+            (area_moment / surface_area).as_vec3()
+        } else {
+            utils::calculate_center(&positions.iter().map(|p| p.as_vec3()).collect::<Vec<_>>())
+        };
+
+        let solid = closed && volume.abs() > f64::EPSILON;
+        let (volume, center_of_mass, inertia, principal_moments) = if solid {
+            let sign = volume.signum();
+            let (volume, covariance) = (volume * sign, covariance * sign);
+            let center = volume_moment * sign / volume;
+            let centered = covariance - outer(center, center) * volume;
+            let inertia = DMat3::from_diagonal(DVec3::splat(trace(centered))) - centered;
+            let (moments, _) = symmetric_eigen(inertia);
+            (Some(volume), Some(center.as_vec3()), Some(inertia), Some(moments))
+        } else {
+            (None, None, None, None)
+        };
+
+        let (obb_size, obb_axes) = oriented_box(&positions);
+
+        Self {
+            surface_area,
+            volume,
+            centroid,
+            center_of_mass,
+            inertia,
+            principal_moments,
+            obb_size,
+            obb_axes,
+        }
+    }
+}
+
+fn outer(a: DVec3, b: DVec3) -> DMat3 {
+    DMat3::from_cols(a * b.x, a * b.y, a * b.z)
+}
+
+fn trace(m: DMat3) -> f64 {
+    m.x_axis.x + m.y_axis.y + m.z_axis.z
+}
+
+fn symmetric_eigen(m: DMat3) -> (DVec3, DMat3) {
+    let mut a = m.to_cols_array_2d();
+    let mut v = DMat3::IDENTITY.to_cols_array_2d();
+
+    for _ in 0..32 {
+        let (mut p, mut q) = (0, 1);
+        for (i, j) in [(0, 2), (1, 2)] {
+            if a[j][i].abs() > a[q][p].abs() {
+                p = i;
+                q = j;
+            }
+        }
+        if a[q][p].abs() <= f64::EPSILON * (a[p][p].abs() + a[q][q].abs()) {
+            break;
+        }
+
+        let theta = (a[q][q] - a[p][p]) / (2.0 * a[q][p]);
+        let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+        let c = 1.0 / (t * t + 1.0).sqrt();
+        let s = t * c;
+
+        rotate_columns(&mut a, p, q, c, s);
+        for column in a.iter_mut() {
+            let (x, y) = (column[p], column[q]);
+            column[p] = c * x - s * y;
+            column[q] = s * x + c * y;
+        }
+        rotate_columns(&mut v, p, q, c, s);
+    }
+
+    (DVec3::new(a[0][0], a[1][1], a[2][2]), DMat3::from_cols_array_2d(&v))
+}
+
+fn rotate_columns(m: &mut [[f64; 3]; 3], p: usize, q: usize, c: f64, s: f64) {
+    let (left, right) = m.split_at_mut(q);
+    for (x, y) in left[p].iter_mut().zip(right[0].iter_mut()) {
+        let (a, b) = (*x, *y);
+        *x = c * a - s * b;
+        *y = s * a + c * b;
+    }
+}
+
+fn extents(positions: &[DVec3], axes: [DVec3; 3]) -> DVec3 {
+    let mut min = DVec3::splat(f64::MAX);
+    let mut max = DVec3::splat(f64::MIN);
+    for p in positions {
+        let projected = DVec3::new(p.dot(axes[0]), p.dot(axes[1]), p.dot(axes[2]));
+        min = min.min(projected);
+        max = max.max(projected);
+    }
+    (max - min).max(DVec3::ZERO)
+}
+
+fn oriented_box(positions: &[DVec3]) -> (Vec3, [Vec3; 3]) {
+    if positions.is_empty() {
+        return (Vec3::ZERO, [Vec3::X, Vec3::Y, Vec3::Z]);
+    }
+
+    let mean = positions.iter().copied().sum::<DVec3>() / positions.len() as f64;
+    let covariance = positions
+        .iter()
+        .map(|&p| outer(p - mean, p - mean))
+        .fold(DMat3::ZERO, |sum, m| sum + m);
+    let (_, vectors) = symmetric_eigen(covariance);
+    let principal = [vectors.x_axis.normalize(), vectors.y_axis.normalize(), vectors.z_axis.normalize()];
+    let aligned = [DVec3::X, DVec3::Y, DVec3::Z];
+
+    let principal_size = extents(positions, principal);
+    let aligned_size = extents(positions, aligned);
+    let (size, axes) = if principal_size.x * principal_size.y * principal_size.z < aligned_size.x * aligned_size.y * aligned_size.z {
+        (principal_size, principal)
+    } else {
+        (aligned_size, aligned)
+    };
+
+    (size.as_vec3(), axes.map(|a| a.as_vec3()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_meshes::{cube_vertices, CUBE_INDICES};
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    #[test]
+    fn unit_cube() {
+        let mass = MassProperties::compute(&cube_vertices(), &CUBE_INDICES, true, Mat4::IDENTITY);
+        assert!(close(mass.surface_area, 6.0));
+        assert!(close(mass.volume.unwrap(), 1.0));
+        assert!(mass.center_of_mass.unwrap().abs_diff_eq(Vec3::splat(0.5), 1e-6));
+        // A solid cube of unit mass has I = m(a² + b²) / 12 about its center.
+        let moments = mass.principal_moments.unwrap();
+        assert!(moments.abs_diff_eq(DVec3::splat(1.0 / 6.0), 1e-6));
+        assert!(mass.obb_size.abs_diff_eq(Vec3::ONE, 1e-6));
+    }
+
+    #[test]
+    fn applies_the_transform() {
+        let transform = Mat4::from_translation(Vec3::X) * Mat4::from_scale(Vec3::new(2.0, 2.0, 2.0));
+        let mass = MassProperties::compute(&cube_vertices(), &CUBE_INDICES, true, transform);
+        assert!(close(mass.surface_area, 24.0));
+        assert!(close(mass.volume.unwrap(), 8.0));
+        assert!(mass.center_of_mass.unwrap().abs_diff_eq(Vec3::new(2.0, 1.0, 1.0), 1e-6));
+    }
+
+    #[test]
+    fn mirrored_and_inside_out_meshes_keep_a_positive_volume() {
+        let mass = MassProperties::compute(&cube_vertices(), &CUBE_INDICES, true, Mat4::from_scale(Vec3::new(-1.0, 1.0, 1.0)));
+        assert!(close(mass.volume.unwrap(), 1.0));
+
+        let reversed: Vec<u32> = CUBE_INDICES.chunks(3).flat_map(|tri| [tri[0], tri[2], tri[1]]).collect();
+        let mass = MassProperties::compute(&cube_vertices(), &reversed, true, Mat4::IDENTITY);
+        assert!(close(mass.volume.unwrap(), 1.0));
+    }
+
+    #[test]
+    fn open_meshes_have_no_volume() {
+        let mass = MassProperties::compute(&cube_vertices(), &CUBE_INDICES[6..], false, Mat4::IDENTITY);
+        assert!(close(mass.surface_area, 5.0));
+        assert!(mass.volume.is_none());
+        assert!(mass.inertia.is_none());
+    }
+}
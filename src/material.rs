@@ -0,0 +1,79 @@
+#[derive(Debug, Clone)]
+pub struct TextureData {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+impl TextureData {
+    pub fn from_bytes(name: &str, data: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let img = image::load_from_memory(data)?.to_rgba8();
+        let (width, height) = img.dimensions();
+        Ok(Self {
+            name: name.to_string(),
+            width,
+            height,
+            rgba: img.into_raw(),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Material {
+    pub name: String,
+    pub ambient: [f32; 3],
+    pub diffuse: [f32; 3],
+    pub specular: [f32; 3],
+    pub shininess: f32,
+    pub dissolve: f32,
+    pub diffuse_texture: Option<TextureData>,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            name: String::from("default"),
+            ambient: [0.0, 0.0, 0.0],
+            diffuse: [0.8, 0.8, 0.8],
+            specular: [0.0, 0.0, 0.0],
+            shininess: 0.0,
+            dissolve: 1.0,
+            diffuse_texture: None,
+        }
+    }
+}
+
+impl Material {
+    pub fn from_tobj<F>(material: &tobj::Material, resolve: F) -> Self
+    where
+        F: Fn(&str) -> Option<Vec<u8>>,
+    {
+        let defaults = Self::default();
+
+        let diffuse_texture = material.diffuse_texture.as_ref().and_then(|name| {
+            let bytes = resolve(name)?;
+            match TextureData::from_bytes(name, &bytes) {
+                Ok(texture) => Some(texture),
+                Err(e) => {
+                    eprintln!("Failed to load texture {}: {}", name, e);
+                    None
+                }
+            }
+        });
+
+        Self {
+            name: material.name.clone(),
+            ambient: material.ambient.unwrap_or(defaults.ambient),
+            diffuse: material.diffuse.unwrap_or(defaults.diffuse),
+            specular: material.specular.unwrap_or(defaults.specular),
+            shininess: material.shininess.unwrap_or(defaults.shininess),
+            dissolve: material.dissolve.unwrap_or(defaults.dissolve),
+            diffuse_texture,
+        }
+    }
+
+    pub fn diffuse_rgba(&self) -> [f32; 4] {
+        [self.diffuse[0], self.diffuse[1], self.diffuse[2], self.dissolve]
+    }
+}
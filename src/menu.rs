@@ -2,6 +2,12 @@ pub struct Menu {
     pub visible: bool,
 }
 
+impl Default for Menu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Menu {
     pub fn new() -> Self {
         Self { visible: false }
@@ -38,22 +44,56 @@ impl Menu {
 ║    Left Click + Drag    - Rotate view                    ║
 ║    Right Click + Drag   - Pan view                       ║
 ║    Scroll Wheel         - Zoom in/out                    ║
+║    Left Drag (gizmo)    - Drag axis/plane/center         ║
+║    Ctrl + Gizmo Drag    - Invert snapping                ║
+║    Shift + Left Click   - Select face/vertex             ║
+║    Shift + Left Drag    - Box/lasso select               ║
+║    + Ctrl/Alt/Both      - Add/subtract/toggle            ║
 ║                                                           ║
 ║  Keyboard:                                               ║
 ║    R                    - Reset camera                   ║
-║    W                    - Toggle wireframe mode          ║
-║    F                    - Toggle flat shading            ║
+║    W                    - Cycle display mode             ║
+║    L                    - Cycle wireframe edge filter    ║
+║    [ / ]                - Thinner/thicker wireframe      ║
+║    F                    - Cycle faceted/smooth/unlit     ║
+║    B                    - Cycle background style         ║
+║    K                    - Cycle lighting rig             ║
+║    J                    - Toggle headlight               ║
 ║    Arrow Keys           - Rotate view                    ║
 ║    +/-                  - Zoom in/out                    ║
 ║    M / ESC              - Toggle menu                    ║
 ║    I                    - Toggle image mode (if image)  ║
+║    T                    - Apply image as model texture   ║
+║    C                    - Cycle vertex color source      ║
+║    U                    - Cycle units (mm/cm/m/in)       ║
+║    N                    - Cycle normals (file/flat/...)  ║
+║    V                    - Highlight mesh problems        ║
 ║    H                    - Toggle model info              ║
 ║    S                    - Save configuration             ║
+║    E                    - Export model (save as)         ║
+║    X                    - Cycle export format            ║
+║    1-9                  - Toggle model part visibility   ║
+║    Q                    - Cycle transform tool           ║
+║    P                    - Cycle vertex/face/object mode  ║
+║    Z                    - Isolate selected objects       ║
+║    O                    - Box/lasso selection            ║
+║    Y                    - Select visible only            ║
+║    . / ,                - Grow/shrink selection          ║
+║    Ctrl+L / Ctrl+I      - Select linked / invert         ║
+║    Ctrl+N / Ctrl+B      - Select by normal/boundary      ║
+║    Delete               - Delete selected faces          ║
+║    Ctrl+F / Ctrl+M      - Flip normals / merge vertices  ║
+║    Ctrl+H / Ctrl+W      - Fill holes / clean up mesh     ║
+║    G                    - Toggle snapping                ║
+║    Arrows/PgUp/PgDn     - Nudge selection with tool      ║
+║    0                    - Reset model transform          ║
+║    Ctrl+Z / Ctrl+Y      - Undo / redo edits              ║
 ║                                                           ║
 ║  FILE LOADING                                            ║
 ║  ─────────────────────────────────────────────────────  ║
-║  Drag and drop OBJ files or images (PNG, JPG, BMP, etc) ║
+║  Drag and drop OBJ/STL/PLY/glTF/GLB models or images    ║
 ║  into the window, or pass file path as argument          ║
+║  Shift + drop adds a model as new objects                ║
 ║                                                           ║
 ║  VERSION                                                 ║
 ║  ─────────────────────────────────────────────────────  ║
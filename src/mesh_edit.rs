@@ -0,0 +1,561 @@
+use glam::{Mat3, Mat4, Vec2, Vec3};
+use std::collections::{HashMap, HashSet};
+use crate::cleanup::{self, CleanupReport};
+use crate::model::{BaseMesh, Model, PlacedFace, Vertex};
+use crate::normals::face_normal;
+use crate::topology::MeshTopology;
+
+/// One reversible change to the mesh as loaded (see `Model::base_face`).
+#[derive(Debug, Clone)]
+pub enum MeshEdit {
+    /// Faces removed, by their index before removal, in ascending order.
+    RemoveFaces(Vec<PlacedFace>),
+    /// Faces inserted, by their index after insertion, in ascending order.
+    AddFaces(Vec<PlacedFace>),
+    SetFaces(Vec<(usize, [u32; 3], [u32; 3])>),
+    SetVertices(Vec<(usize, Vertex, Vertex)>),
+    Replace(Box<BaseMesh>, Box<BaseMesh>),
+}
+
+impl MeshEdit {
+    fn apply(&self, model: &mut Model, forward: bool) {
+        match self {
+            MeshEdit::RemoveFaces(faces) | MeshEdit::AddFaces(faces) => {
+                let remove = forward == matches!(self, MeshEdit::RemoveFaces(_));
+                if remove {
+                    let faces: Vec<usize> = faces.iter().map(|placed| placed.face).collect();
+                    model.remove_base_faces(&faces);
+                } else {
+                    model.insert_base_faces(faces);
+                }
+            }
+            MeshEdit::SetFaces(faces) => {
+                for &(face, before, after) in faces {
+                    model.set_base_face(face, if forward { after } else { before });
+                }
+            }
+            MeshEdit::SetVertices(vertices) => {
+                for &(index, before, after) in vertices {
+                    model.set_base_vertex(index, if forward { after } else { before });
+                }
+            }
+            MeshEdit::Replace(before, after) => {
+                let mesh = if forward { after } else { before };
+                model.set_base_mesh(mesh.as_ref().clone());
+            }
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        match self {
+            MeshEdit::RemoveFaces(faces) | MeshEdit::AddFaces(faces) => faces.is_empty(),
+            MeshEdit::SetFaces(faces) => faces.is_empty(),
+            MeshEdit::SetVertices(vertices) => vertices.is_empty(),
+            MeshEdit::Replace(..) => false,
+        }
+    }
+
+    fn memory_size(&self) -> usize {
+        match self {
+            MeshEdit::RemoveFaces(faces) | MeshEdit::AddFaces(faces) => faces.len() * std::mem::size_of::<PlacedFace>(),
+            MeshEdit::SetFaces(faces) => faces.len() * std::mem::size_of::<(usize, [u32; 3], [u32; 3])>(),
+            MeshEdit::SetVertices(vertices) => vertices.len() * std::mem::size_of::<(usize, Vertex, Vertex)>(),
+            MeshEdit::Replace(before, after) => before.memory_size() + after.memory_size(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MeshCommand {
+    pub name: String,
+    pub edits: Vec<MeshEdit>,
+}
+
+impl MeshCommand {
+    fn new(name: &str, mut edits: Vec<MeshEdit>) -> Option<Self> {
+        edits.retain(|edit| !edit.is_empty());
+        (!edits.is_empty()).then(|| Self {
+            name: name.to_string(),
+            edits,
+        })
+    }
+
+    pub fn apply(&self, model: &mut Model) {
+        for edit in &self.edits {
+            edit.apply(model, true);
+        }
+        model.rebuild();
+    }
+
+    pub fn revert(&self, model: &mut Model) {
+        for edit in self.edits.iter().rev() {
+            edit.apply(model, false);
+        }
+        model.rebuild();
+    }
+
+    pub fn changes_faces(&self) -> bool {
+        self.edits
+            .iter()
+            .any(|edit| matches!(edit, MeshEdit::RemoveFaces(_) | MeshEdit::AddFaces(_) | MeshEdit::Replace(..)))
+    }
+
+    pub fn summary(&self) -> String {
+        let mut counts = [0usize; 4];
+        for edit in &self.edits {
+            match edit {
+                MeshEdit::RemoveFaces(faces) => counts[0] += faces.len(),
+                MeshEdit::AddFaces(faces) => counts[1] += faces.len(),
+                MeshEdit::SetFaces(faces) => counts[2] += faces.len(),
+                MeshEdit::SetVertices(vertices) => counts[3] += vertices.len(),
+                MeshEdit::Replace(..) => {}
+            }
+        }
+        let changes: Vec<String> = ["faces removed", "faces added", "faces changed", "vertices changed"]
+            .iter()
+            .zip(counts)
+            .filter(|(_, count)| *count > 0)
+            .map(|(label, count)| format!("{} {}", count, label))
+            .collect();
+        if changes.is_empty() {
+            return self.name.clone();
+        }
+        format!("{}: {}", self.name, changes.join(", "))
+    }
+
+    pub fn memory_size(&self) -> usize {
+        std::mem::size_of::<Self>() + self.name.len() + self.edits.iter().map(MeshEdit::memory_size).sum::<usize>()
+    }
+}
+
+fn placed(model: &Model, face: usize) -> Option<PlacedFace> {
+    Some(PlacedFace {
+        face,
+        part: model.base_part_of_face(face),
+        corners: model.base_face(face)?,
+    })
+}
+
+fn vertex_transform(model: &Model, vertex: usize) -> Mat4 {
+    model.base_part_of_vertex(vertex).map(|part| model.part_transform(part)).unwrap_or(Mat4::IDENTITY)
+}
+
+pub fn vertex_position(model: &Model, vertex: usize) -> Option<Vec3> {
+    let base = model.base_vertex(vertex)?;
+    Some(vertex_transform(model, vertex).transform_point3(base.position_vec3()))
+}
+
+pub fn delete_faces(model: &Model, faces: impl IntoIterator<Item = usize>) -> Option<MeshCommand> {
+    let mut faces: Vec<usize> = faces.into_iter().collect();
+    faces.sort_unstable();
+    faces.dedup();
+    let removed = faces.into_iter().filter_map(|face| placed(model, face)).collect();
+    MeshCommand::new("Delete faces", vec![MeshEdit::RemoveFaces(removed)])
+}
+
+pub fn flip_faces(model: &Model, faces: impl IntoIterator<Item = usize>) -> Option<MeshCommand> {
+    let faces: HashSet<usize> = faces.into_iter().filter(|&face| face < model.base_face_count()).collect();
+    if faces.is_empty() {
+        return None;
+    }
+    let mut sorted: Vec<usize> = faces.iter().copied().collect();
+    sorted.sort_unstable();
+    let flipped = sorted
+        .iter()
+        .filter_map(|&face| {
+            let [a, b, c] = model.base_face(face)?;
+            Some((face, [a, b, c], [a, c, b]))
+        })
+        .collect();
+
+    let mut only_flipped: HashMap<u32, bool> = HashMap::new();
+    for face in 0..model.base_face_count() {
+        for corner in model.base_face(face).into_iter().flatten() {
+            *only_flipped.entry(corner).or_insert(true) &= faces.contains(&face);
+        }
+    }
+    let mut vertices: Vec<u32> = only_flipped
+        .into_iter()
+        .filter_map(|(vertex, only)| only.then_some(vertex))
+        .collect();
+    vertices.sort_unstable();
+    let normals = vertices
+        .into_iter()
+        .filter_map(|vertex| {
+            let before = *model.base_vertex(vertex as usize)?;
+            let after = Vertex {
+                normal: (-before.normal_vec3()).to_array(),
+                ..before
+            };
+            Some((vertex as usize, before, after))
+        })
+        .collect();
+
+    MeshCommand::new("Flip normals", vec![MeshEdit::SetFaces(flipped), MeshEdit::SetVertices(normals)])
+}
+
+pub fn move_vertices(model: &Model, vertices: impl IntoIterator<Item = usize>, matrix: Mat4) -> Option<MeshCommand> {
+    let mut vertices: Vec<usize> = vertices.into_iter().collect();
+    vertices.sort_unstable();
+    vertices.dedup();
+    let moved = vertices
+        .into_iter()
+        .filter_map(|index| {
+            let before = *model.base_vertex(index)?;
+            let object = vertex_transform(model, index);
+            let local = object.inverse() * matrix * object;
+            let normal = Mat3::from_mat4(local).inverse().transpose() * before.normal_vec3();
+            let after = Vertex {
+                position: local.transform_point3(before.position_vec3()).to_array(),
+                normal: normal.normalize_or_zero().to_array(),
+                ..before
+            };
+            Some((index, before, after))
+        })
+        .collect();
+    MeshCommand::new("Move vertices", vec![MeshEdit::SetVertices(moved)])
+}
+
+/// Merges loaded vertices into the first of them, placed at their center.
+/// Faces left with repeated corners are removed; the other vertices stay
+/// in the mesh unreferenced.
+pub fn merge_vertices(model: &Model, vertices: impl IntoIterator<Item = usize>) -> Option<MeshCommand> {
+    let merged: HashSet<u32> = vertices
+        .into_iter()
+        .filter(|&vertex| vertex < model.base_vertex_count())
+        .map(|vertex| vertex as u32)
+        .collect();
+    let kept = *merged.iter().min()?;
+    if merged.len() < 2 {
+        return None;
+    }
+    let center = merged
+        .iter()
+        .filter_map(|&vertex| vertex_position(model, vertex as usize))
+        .sum::<Vec3>()
+        / merged.len() as f32;
+    let before = *model.base_vertex(kept as usize)?;
+    let after = Vertex {
+        position: vertex_transform(model, kept as usize).inverse().transform_point3(center).to_array(),
+        ..before
+    };
+
+    let mut remapped = Vec::new();
+    let mut removed = Vec::new();
+    for face in 0..model.base_face_count() {
+        let Some(corners) = model.base_face(face) else {
+            continue;
+        };
+        if !corners.iter().any(|corner| merged.contains(corner)) {
+            continue;
+        }
+        let new = corners.map(|corner| if merged.contains(&corner) { kept } else { corner });
+        if new[0] == new[1] || new[1] == new[2] || new[0] == new[2] {
+            removed.extend(placed(model, face));
+        } else if new != corners {
+            remapped.push((face, corners, new));
+        }
+    }
+
+    MeshCommand::new(
+        "Merge vertices",
+        vec![
+            MeshEdit::SetVertices(vec![(kept as usize, before, after)]),
+            MeshEdit::SetFaces(remapped),
+            MeshEdit::RemoveFaces(removed),
+        ],
+    )
+}
+
+pub fn fill_holes(model: &Model, topology: &MeshTopology, vertices: &HashSet<usize>) -> Option<MeshCommand> {
+    let boundary: Vec<usize> = (0..topology.faces.len() * 3).filter(|&he| topology.is_boundary(he)).collect();
+    let start_of = |he: usize| topology.faces[he / 3][he % 3];
+    let end_of = |he: usize| topology.faces[he / 3][(he + 1) % 3];
+    let mut outgoing: HashMap<u32, Vec<usize>> = HashMap::new();
+    for &he in &boundary {
+        outgoing.entry(start_of(he)).or_default().push(he);
+    }
+
+    let mut used = HashSet::new();
+    let mut loops = Vec::new();
+    for &first in &boundary {
+        if used.contains(&first) {
+            continue;
+        }
+        let mut half_edges = vec![first];
+        used.insert(first);
+        let mut current = first;
+        let closed = loop {
+            let next_vertex = end_of(current);
+            if next_vertex == start_of(first) {
+                break true;
+            }
+            let next = outgoing
+                .get(&next_vertex)
+                .and_then(|candidates| candidates.iter().copied().find(|he| !used.contains(he)));
+            let Some(next) = next else {
+                break false;
+            };
+            used.insert(next);
+            half_edges.push(next);
+            current = next;
+        };
+        if closed && half_edges.len() >= 3 {
+            loops.push(half_edges);
+        }
+    }
+
+    // Loaded vertex at the start corner of each half-edge.
+    let corner = |he: usize| model.base_face(he / 3).map(|face| face[he % 3]);
+    let selected = |he: usize| {
+        vertices.is_empty() || topology.sources(start_of(he)).iter().any(|source| vertices.contains(source))
+    };
+
+    let mut part_ends: Vec<usize> = (0..model.parts.len()).filter_map(|part| model.base_part_end(part)).collect();
+    let mut face_count = model.base_face_count();
+    let mut edits = Vec::new();
+    for half_edges in loops.into_iter().filter(|half_edges| half_edges.iter().any(|&he| selected(he))) {
+        // Walk the loop backwards so the new faces face the same way as their neighbours.
+        let corners: Option<Vec<u32>> = half_edges.iter().rev().map(|&he| corner(he)).collect();
+        let Some(corners) = corners else {
+            continue;
+        };
+        let points: Vec<Vec3> = half_edges
+            .iter()
+            .rev()
+            .map(|&he| model.vertices[model.indices[he] as usize].position_vec3())
+            .collect();
+        let part = model.base_part_of_face(half_edges[0] / 3).filter(|&part| part < part_ends.len());
+        let start = part.map(|part| part_ends[part]).unwrap_or(face_count);
+        let faces: Vec<PlacedFace> = triangulate(&points)
+            .into_iter()
+            .enumerate()
+            .map(|(i, [a, b, c])| PlacedFace {
+                face: start + i,
+                part,
+                corners: [corners[a], corners[b], corners[c]],
+            })
+            .collect();
+        let added = faces.len();
+        for (other, end) in part_ends.iter_mut().enumerate() {
+            if Some(other) == part || *end > start {
+                *end += added;
+            }
+        }
+        face_count += added;
+        edits.push(MeshEdit::AddFaces(faces));
+    }
+    MeshCommand::new("Fill holes", edits)
+}
+
+pub fn clean_up(model: &Model, tolerance: f32) -> (Option<MeshCommand>, CleanupReport) {
+    let before = model.base_mesh();
+    let (after, report) = cleanup::clean_up(&before, tolerance, model.normal_settings.weighting);
+    let edits = if report.is_empty() {
+        Vec::new()
+    } else {
+        vec![MeshEdit::Replace(Box::new(before), Box::new(after))]
+    };
+    (MeshCommand::new("Clean up", edits), report)
+}
+
+/// Ear clipping of a polygon in the plane that fits it best. Triangles keep
+/// the polygon's winding; a fan is used when clipping gets stuck on
+/// self-intersecting outlines.
+fn triangulate(points: &[Vec3]) -> Vec<[usize; 3]> {
+    let count = points.len();
+    // Newell's method: the normal of the best fitting plane.
+    let normal: Vec3 = (0..count)
+        .map(|i| points[i].cross(points[(i + 1) % count]))
+        .sum::<Vec3>()
+        .normalize_or_zero();
+    let (u, v) = if normal == Vec3::ZERO { (Vec3::X, Vec3::Y) } else { normal.any_orthonormal_pair() };
+    let flat: Vec<Vec2> = points.iter().map(|p| Vec2::new(p.dot(u), p.dot(v))).collect();
+    let orientation = (0..count)
+        .map(|i| flat[i].perp_dot(flat[(i + 1) % count]))
+        .sum::<f32>()
+        .signum();
+    let convex = |a: Vec2, b: Vec2, c: Vec2| (b - a).perp_dot(c - a) * orientation > 0.0;
+
+    let mut remaining: Vec<usize> = (0..count).collect();
+    let mut triangles = Vec::with_capacity(count.saturating_sub(2));
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let ear = (0..n).find(|&i| {
+            let (a, b, c) = (remaining[(i + n - 1) % n], remaining[i], remaining[(i + 1) % n]);
+            convex(flat[a], flat[b], flat[c])
+                && remaining.iter().filter(|&&p| p != a && p != b && p != c).all(|&p| {
+                    !(convex(flat[a], flat[b], flat[p]) && convex(flat[b], flat[c], flat[p]) && convex(flat[c], flat[a], flat[p]))
+                })
+        });
+        let Some(i) = ear else {
+            break;
+        };
+        triangles.push([remaining[(i + n - 1) % n], remaining[i], remaining[(i + 1) % n]]);
+        remaining.remove(i);
+    }
+    triangles.extend((1..remaining.len() - 1).map(|i| [remaining[0], remaining[i], remaining[i + 1]]));
+    triangles.retain(|&[a, b, c]| face_normal(points[a], points[b], points[c]).is_some());
+    triangles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::{Command, Editor};
+    use crate::model::ModelPart;
+    use crate::test_meshes::{cube, cube_vertices, CUBE_INDICES};
+    use crate::validation;
+
+    type Snapshot = (Vec<[f32; 3]>, Vec<[f32; 3]>, Vec<u32>, Vec<[u32; 4]>);
+
+    fn snapshot(model: &Model) -> Snapshot {
+        let mesh = model.base_mesh();
+        (
+            mesh.vertices.iter().map(|v| v.position).collect(),
+            mesh.vertices.iter().map(|v| v.normal).collect(),
+            mesh.indices,
+            mesh.parts
+                .iter()
+                .map(|p| [p.vertex_offset, p.vertex_count, p.index_offset, p.index_count])
+                .collect(),
+        )
+    }
+
+    fn round_trip(model: &mut Model, command: &MeshCommand) -> Snapshot {
+        let before = snapshot(model);
+        command.apply(model);
+        let after = snapshot(model);
+        assert_ne!(after, before);
+        command.revert(model);
+        assert_eq!(snapshot(model), before);
+        command.apply(model);
+        assert_eq!(snapshot(model), after);
+        after
+    }
+
+    fn two_cubes() -> Model {
+        let mut vertices = cube_vertices();
+        vertices.extend(cube_vertices().into_iter().map(|mut v| {
+            v.position[0] += 2.0;
+            v
+        }));
+        let mut indices = CUBE_INDICES.to_vec();
+        indices.extend(CUBE_INDICES.iter().map(|i| i + 8));
+        let part = |name: &str, vertex_offset, index_offset| ModelPart {
+            name: name.to_string(),
+            vertex_offset,
+            vertex_count: 8,
+            index_offset,
+            index_count: 36,
+            material_id: None,
+            visible: true,
+        };
+        let mut model = Model::from_mesh(vertices, indices, vec![part("left", 0, 0), part("right", 8, 36)]);
+        model.apply_normals(model.normal_settings);
+        model
+    }
+
+    #[test]
+    fn flipping_faces_round_trips() {
+        let mut model = cube();
+        let command = flip_faces(&model, [0, 1, 99]).unwrap();
+        round_trip(&mut model, &command);
+        assert_eq!(model.base_face(0), Some([0, 3, 2]));
+        assert_eq!(model.base_face(1), Some([0, 1, 3]));
+        assert!(flip_faces(&model, [99]).is_none());
+    }
+
+    #[test]
+    fn moving_vertices_round_trips() {
+        let mut model = cube();
+        let command = move_vertices(&model, [7, 7], Mat4::from_translation(Vec3::X)).unwrap();
+        round_trip(&mut model, &command);
+        assert_eq!(model.base_vertex(7).unwrap().position, [2.0, 1.0, 1.0]);
+        assert_eq!(command.summary(), "Move vertices: 1 vertices changed");
+    }
+
+    #[test]
+    fn merging_vertices_round_trips() {
+        let mut model = cube();
+        let command = merge_vertices(&model, [7, 6]).unwrap();
+        round_trip(&mut model, &command);
+        assert_eq!(model.base_vertex(6).unwrap().position, [0.5, 1.0, 1.0]);
+        assert_eq!(model.base_face_count(), 10);
+        assert!((0..10).all(|face| !model.base_face(face).unwrap().contains(&7)));
+        assert!(merge_vertices(&model, [6]).is_none());
+    }
+
+    #[test]
+    fn deleting_faces_keeps_part_ranges() {
+        let mut model = two_cubes();
+        let command = delete_faces(&model, [1, 0, 1]).unwrap();
+        let (_, _, indices, parts) = round_trip(&mut model, &command);
+        assert_eq!(indices.len(), 66);
+        assert_eq!(parts, [[0, 8, 0, 30], [8, 8, 30, 36]]);
+        assert!(command.changes_faces());
+    }
+
+    #[test]
+    fn filling_a_deleted_hole_closes_the_cube() {
+        let mut model = two_cubes();
+        let delete = delete_faces(&model, [2, 3]).unwrap();
+        delete.apply(&mut model);
+        let holes = snapshot(&model);
+
+        let topology = MeshTopology::new(&model);
+        let fill = fill_holes(&model, &topology, &HashSet::new()).unwrap();
+        let (_, _, _, parts) = round_trip(&mut model, &fill);
+        assert_eq!(parts, [[0, 8, 0, 36], [8, 8, 36, 36]]);
+        let report = validation::validate(&model.base_mesh().vertices, &model.base_mesh().indices);
+        assert!(report.is_clean(), "{}", report.format_report());
+
+        fill.revert(&mut model);
+        assert_eq!(snapshot(&model), holes);
+        delete.revert(&mut model);
+        assert_eq!(snapshot(&model), snapshot(&two_cubes()));
+    }
+
+    #[test]
+    fn cleaning_up_round_trips() {
+        let mut vertices = cube_vertices();
+        vertices.push(vertices[0]);
+        let mut indices = CUBE_INDICES.to_vec();
+        indices[0] = 8;
+        indices.extend_from_slice(&[0, 2, 3]);
+        let mut model = Model::from_mesh(vertices, indices, Vec::new());
+        model.apply_normals(model.normal_settings);
+
+        let (command, report) = clean_up(&model, 0.0);
+        assert_eq!(report.welded_vertices, 1);
+        assert_eq!(report.duplicate_faces, 1);
+        let (vertices, _, indices, _) = round_trip(&mut model, &command.unwrap());
+        assert_eq!((vertices.len(), indices.len()), (8, 36));
+
+        let (command, report) = clean_up(&model, 0.0);
+        assert!(command.is_none());
+        assert!(report.is_empty());
+    }
+
+    #[test]
+    fn undo_and_redo_replay_mesh_commands() {
+        let mut model = cube();
+        let original = snapshot(&model);
+        let mut editor = Editor::new();
+        for command in [flip_faces(&model, [4]).unwrap(), delete_faces(&model, [0]).unwrap()] {
+            command.apply(&mut model);
+            editor.record(Command::Mesh(command));
+        }
+        let edited = snapshot(&model);
+
+        assert_eq!(editor.undo(Some(&mut model)).as_deref(), Some("Delete faces"));
+        assert_eq!(editor.undo(Some(&mut model)).as_deref(), Some("Flip normals"));
+        assert!(editor.undo(Some(&mut model)).is_none());
+        assert_eq!(snapshot(&model), original);
+
+        editor.redo(Some(&mut model));
+        editor.redo(Some(&mut model));
+        assert!(editor.redo(Some(&mut model)).is_none());
+        assert_eq!(snapshot(&model), edited);
+    }
+}
@@ -1,14 +1,33 @@
-use glam::Vec3;
+use glam::{Mat3, Mat4, Vec2, Vec3};
+use std::cell::{Ref, RefCell};
+use std::collections::HashMap;
 use std::fs::File;
-use std::io::{BufReader, Cursor};
+use std::io::{BufRead, BufReader, Cursor};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use crate::gltf_loader;
+use crate::normals::{self, NormalSettings};
+use crate::material::Material;
+use crate::ply;
+use crate::stl;
 use crate::utils;
 use bytemuck::{Pod, Zeroable};
 
+pub const FIT_SIZE: f32 = 2.0;
+
+static REVISION: AtomicU64 = AtomicU64::new(1);
+
+pub fn next_revision() -> u64 {
+    REVISION.fetch_add(1, Ordering::Relaxed)
+}
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
 pub struct Vertex {
     pub position: [f32; 3],
     pub normal: [f32; 3],
+    pub uv: [f32; 2],
+    pub color: [f32; 4],
 }
 
 unsafe impl Pod for Vertex {}
@@ -19,6 +38,17 @@ impl Vertex {
         Self {
             position: position.to_array(),
             normal: normal.to_array(),
+            uv: [0.0, 0.0],
+            color: [1.0, 1.0, 1.0, 1.0],
+        }
+    }
+
+    pub fn with_uv(position: Vec3, normal: Vec3, uv: Vec2) -> Self {
+        Self {
+            position: position.to_array(),
+            normal: normal.to_array(),
+            uv: uv.to_array(),
+            color: [1.0, 1.0, 1.0, 1.0],
         }
     }
 
@@ -29,6 +59,110 @@ impl Vertex {
     pub fn normal_vec3(&self) -> Vec3 {
         Vec3::from_array(self.normal)
     }
+
+    pub fn uv_vec2(&self) -> Vec2 {
+        Vec2::from_array(self.uv)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ModelPart {
+    pub name: String,
+    pub vertex_offset: u32,
+    pub vertex_count: u32,
+    pub index_offset: u32,
+    pub index_count: u32,
+    pub material_id: Option<usize>,
+    pub visible: bool,
+}
+
+impl ModelPart {
+    pub fn face_count(&self) -> usize {
+        self.index_count as usize / 3
+    }
+
+    pub fn index_range(&self) -> std::ops::Range<u32> {
+        self.index_offset..self.index_offset + self.index_count
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SceneNode {
+    pub name: String,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    pub local_transform: Mat4,
+    pub world_transform: Mat4,
+    pub parts: Vec<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ScalarProperty {
+    pub name: String,
+    pub values: Vec<f32>,
+    pub min: f32,
+    pub max: f32,
+}
+
+impl ScalarProperty {
+    pub fn new(name: String, values: Vec<f32>) -> Self {
+        let (min, max) = values
+            .iter()
+            .filter(|v| v.is_finite())
+            .fold((f32::MAX, f32::MIN), |(min, max), &v| (min.min(v), max.max(v)));
+        let (min, max) = if min > max { (0.0, 0.0) } else { (min, max) };
+        Self { name, values, min, max }
+    }
+
+    pub fn normalized(&self, index: usize) -> f32 {
+        let range = self.max - self.min;
+        if range > 0.0 {
+            ((self.values[index] - self.min) / range).clamp(0.0, 1.0)
+        } else {
+            0.5
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PlacedFace {
+    pub face: usize,
+    pub part: Option<usize>,
+    pub corners: [u32; 3],
+}
+
+#[derive(Debug, Clone)]
+pub struct BaseMesh {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+    pub parts: Vec<ModelPart>,
+    pub vertex_colors: Option<Vec<[f32; 4]>>,
+    pub scalar_values: Vec<Vec<f32>>,
+}
+
+impl BaseMesh {
+    pub fn memory_size(&self) -> usize {
+        self.vertices.len() * std::mem::size_of::<Vertex>()
+            + self.indices.len() * std::mem::size_of::<u32>()
+            + self.parts.iter().map(|part| std::mem::size_of::<ModelPart>() + part.name.len()).sum::<usize>()
+            + self.vertex_colors.as_ref().map_or(0, |colors| colors.len() * std::mem::size_of::<[f32; 4]>())
+            + self.scalar_values.iter().map(|values| values.len() * std::mem::size_of::<f32>()).sum::<usize>()
+    }
+}
+
+#[derive(Debug)]
+struct BoundsCache {
+    revision: u64,
+    bounds: (Vec3, Vec3),
+    base_bounds: (Vec3, Vec3),
+    parts: Vec<Option<(Vec3, Vec3)>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorSource {
+    Uniform,
+    VertexColors,
+    Property(usize),
 }
 
 #[derive(Debug)]
@@ -36,38 +170,89 @@ pub struct Model {
     pub vertices: Vec<Vertex>,
     pub indices: Vec<u32>,
     pub center: Vec3,
+    pub parts: Vec<ModelPart>,
+    pub materials: Vec<Material>,
+    pub nodes: Vec<SceneNode>,
+    pub vertex_colors: Option<Vec<[f32; 4]>>,
+    pub scalar_properties: Vec<ScalarProperty>,
+    pub vertex_properties: Vec<String>,
+    pub color_source: ColorSource,
+    pub normal_settings: NormalSettings,
+    pub vertex_sources: Vec<u32>,
+    pub revision: u64,
+    base_vertices: Vec<Vertex>,
+    base_indices: Vec<u32>,
+    base_parts: Vec<ModelPart>,
+    part_transforms: HashMap<usize, Mat4>,
+    part_previews: HashMap<usize, Mat4>,
+    bounds_cache: RefCell<Option<BoundsCache>>,
 }
 
 impl Model {
     pub fn from_obj(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
         let file = File::open(path)?;
         let mut reader = BufReader::new(file);
-        let (models, _materials) = tobj::load_obj_buf(
-            &mut reader,
-            &tobj::LoadOptions {
-                triangulate: true,
-                single_index: true,
-                ..Default::default()
-            },
-            |_| Err(tobj::LoadError::GenericFailure),
-        )?;
+        let base_dir = Path::new(path).parent().map(Path::to_path_buf).unwrap_or_default();
 
-        Self::from_tobj_models(models)
+        Self::from_obj_reader(&mut reader, |name| std::fs::read(base_dir.join(name)).ok())
     }
 
     pub fn from_obj_bytes(data: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::from_obj_bytes_with_resources(data, &HashMap::new())
+    }
+
+    pub fn from_obj_bytes_with_resources(
+        data: &[u8],
+        resources: &HashMap<String, Vec<u8>>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
         let mut reader = BufReader::new(Cursor::new(data));
-        let (models, _materials) = tobj::load_obj_buf(
-            &mut reader,
+        Self::from_obj_reader(&mut reader, |name| {
+            resources
+                .get(name)
+                .or_else(|| resources.get(&utils::get_file_name(name)))
+                .cloned()
+        })
+    }
+
+    fn from_obj_reader<R, F>(reader: &mut R, resolve: F) -> Result<Self, Box<dyn std::error::Error>>
+    where
+        R: BufRead,
+        F: Fn(&str) -> Option<Vec<u8>>,
+    {
+        let (models, materials) = tobj::load_obj_buf(
+            reader,
             &tobj::LoadOptions {
                 triangulate: true,
                 single_index: true,
                 ..Default::default()
             },
-            |_| Err(tobj::LoadError::GenericFailure),
+            |mtl_path| {
+                let name = mtl_path.to_string_lossy();
+                let bytes = resolve(&name).ok_or(tobj::LoadError::OpenFileFailed)?;
+                let (mut materials, names) = tobj::load_mtl_buf(&mut BufReader::new(Cursor::new(bytes)))?;
+                // Texture paths are relative to the MTL file, not the OBJ.
+                let mtl_dir = mtl_path.parent().unwrap_or(Path::new(""));
+                for texture in materials.iter_mut().filter_map(|m| m.diffuse_texture.as_mut()) {
+                    *texture = mtl_dir.join(&*texture).to_string_lossy().into_owned();
+                }
+                Ok((materials, names))
+            },
         )?;
 
-        Self::from_tobj_models(models)
+        let materials = match materials {
+            Ok(materials) => materials
+                .iter()
+                .map(|m| Material::from_tobj(m, &resolve))
+                .collect(),
+            Err(e) => {
+                eprintln!("Failed to load materials: {}", e);
+                Vec::new()
+            }
+        };
+
+        let mut model = Self::from_tobj_models(models)?;
+        model.materials = materials;
+        Ok(model)
     }
 
     fn from_tobj_models(models: Vec<tobj::Model>) -> Result<Self, Box<dyn std::error::Error>> {
@@ -76,92 +261,879 @@ impl Model {
             return Err("No models found in OBJ file".into());
         }
 
-        let mesh = &models[0].mesh;
-
         let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        let mut parts = Vec::new();
 
-        let positions = &mesh.positions;
-        let normals = &mesh.normals;
+        for model in &models {
+            let mesh = &model.mesh;
+            let positions = &mesh.positions;
+            let normals = &mesh.normals;
+            let texcoords = &mesh.texcoords;
+            let colors = &mesh.vertex_color;
 
-        for i in 0..positions.len() / 3 {
-            let pos = Vec3::new(
-                positions[i * 3],
-                positions[i * 3 + 1],
-                positions[i * 3 + 2],
-            );
+            if positions.is_empty() {
+                continue;
+            }
 
-            let normal = if i * 3 + 2 < normals.len() {
-                Vec3::new(
-                    normals[i * 3],
-                    normals[i * 3 + 1],
-                    normals[i * 3 + 2],
-                )
-            } else {
-                Vec3::ZERO
-            };
+            let vertex_offset = vertices.len() as u32;
+            let index_offset = indices.len() as u32;
 
-            vertices.push(Vertex::new(pos, normal));
-        }
+            for i in 0..positions.len() / 3 {
+                let pos = Vec3::new(
+                    positions[i * 3],
+                    positions[i * 3 + 1],
+                    positions[i * 3 + 2],
+                );
+
+                let normal = if i * 3 + 2 < normals.len() {
+                    Vec3::new(
+                        normals[i * 3],
+                        normals[i * 3 + 1],
+                        normals[i * 3 + 2],
+                    )
+                } else {
+                    Vec3::ZERO
+                };
 
-        let indices = mesh.indices.clone();
+                let uv = if i * 2 + 1 < texcoords.len() {
+                    Vec2::new(texcoords[i * 2], 1.0 - texcoords[i * 2 + 1])
+                } else {
+                    Vec2::ZERO
+                };
+
+                let mut vertex = Vertex::with_uv(pos, normal, uv);
+                if i * 3 + 2 < colors.len() {
+                    vertex.color = [colors[i * 3], colors[i * 3 + 1], colors[i * 3 + 2], 1.0];
+                }
+                vertices.push(vertex);
+            }
+
+            indices.extend(mesh.indices.iter().map(|&i| i + vertex_offset));
+
+            parts.push(ModelPart {
+                name: model.name.clone(),
+                vertex_offset,
+                vertex_count: vertices.len() as u32 - vertex_offset,
+                index_offset,
+                index_count: indices.len() as u32 - index_offset,
+                material_id: mesh.material_id,
+                visible: true,
+            });
+        }
 
         if vertices.is_empty() {
             return Err("No vertices found in OBJ file".into());
         }
 
-        let positions_vec: Vec<Vec3> = vertices.iter().map(|v| v.position_vec3()).collect();
-        let center = utils::calculate_center(&positions_vec);
-        let mut positions_mut: Vec<Vec3> = vertices.iter().map(|v| v.position_vec3()).collect();
-        utils::scale_to_fit(&mut positions_mut, 2.0);
-        for (i, pos) in positions_mut.iter().enumerate() {
-            vertices[i] = Vertex::new(*pos, vertices[i].normal_vec3());
+        let has_colors = models.iter().any(|m| !m.mesh.vertex_color.is_empty());
+        let mut model = Self::from_mesh(vertices, indices, parts);
+        if has_colors {
+            model.vertex_colors = Some(model.vertices.iter().map(|v| v.color).collect());
+            model.color_source = ColorSource::VertexColors;
         }
+        Ok(model)
+    }
+
+    pub fn from_mesh(mut vertices: Vec<Vertex>, indices: Vec<u32>, parts: Vec<ModelPart>) -> Self {
+        let positions: Vec<Vec3> = vertices.iter().map(|v| v.position_vec3()).collect();
+        let center = utils::calculate_center(&positions);
 
         for vertex in &mut vertices {
             let normal = vertex.normal_vec3();
             if normal.length_squared() < 0.001 {
-                *vertex = Vertex::new(vertex.position_vec3(), Vec3::ZERO);
+                vertex.normal = Vec3::ZERO.to_array();
             }
         }
 
-        Ok(Model {
+        Model {
             vertices,
             indices,
             center,
+            parts,
+            materials: Vec::new(),
+            nodes: Vec::new(),
+            vertex_colors: None,
+            scalar_properties: Vec::new(),
+            vertex_properties: Vec::new(),
+            color_source: ColorSource::Uniform,
+            normal_settings: NormalSettings::default(),
+            vertex_sources: Vec::new(),
+            revision: next_revision(),
+            base_vertices: Vec::new(),
+            base_indices: Vec::new(),
+            base_parts: Vec::new(),
+            part_transforms: HashMap::new(),
+            part_previews: HashMap::new(),
+            bounds_cache: RefCell::new(None),
+        }
+    }
+
+    pub fn from_stl(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let data = std::fs::read(path)?;
+        Self::from_stl_bytes(&data)
+    }
+
+    pub fn from_stl_bytes(data: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let mesh = stl::parse(data)?;
+        let (vertices, indices) = stl::weld(&mesh.triangles);
+
+        if vertices.is_empty() {
+            return Err("No triangles found in STL file".into());
+        }
+
+        let parts = vec![ModelPart {
+            name: mesh.name,
+            vertex_offset: 0,
+            vertex_count: vertices.len() as u32,
+            index_offset: 0,
+            index_count: indices.len() as u32,
+            material_id: None,
+            visible: true,
+        }];
+
+        Ok(Self::from_mesh(vertices, indices, parts))
+    }
+
+    pub fn from_ply(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let data = std::fs::read(path)?;
+        Self::from_ply_bytes(&data)
+    }
+
+    pub fn from_ply_bytes(data: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let ply = ply::parse(data)?;
+        let (element, columns) = ply.element("vertex").ok_or("PLY file has no vertex element")?;
+
+        let scalar = |name: &str| {
+            element
+                .property_index(name)
+                .and_then(|i| columns[i].scalars())
+        };
+        let first_scalar = |names: &[&str]| names.iter().find_map(|name| scalar(name));
+
+        let (xs, ys, zs) = match (scalar("x"), scalar("y"), scalar("z")) {
+            (Some(x), Some(y), Some(z)) => (x, y, z),
+            _ => return Err("PLY vertex element is missing x/y/z properties".into()),
+        };
+        let normals = match (scalar("nx"), scalar("ny"), scalar("nz")) {
+            (Some(x), Some(y), Some(z)) => Some((x, y, z)),
+            _ => None,
+        };
+        let texcoords = match (
+            first_scalar(&["u", "s", "texture_u", "texture_s"]),
+            first_scalar(&["v", "t", "texture_v", "texture_t"]),
+        ) {
+            (Some(u), Some(v)) => Some((u, v)),
+            _ => None,
+        };
+
+        let color_channel = |names: &[&str]| {
+            names.iter().find_map(|name| {
+                let index = element.property_index(name)?;
+                let values = columns[index].scalars()?;
+                let scale = match element.properties[index].kind {
+                    ply::PropertyKind::Scalar(ply::ScalarType::UInt8) => 255.0,
+                    ply::PropertyKind::Scalar(ply::ScalarType::UInt16) => 65535.0,
+                    _ => 1.0,
+                };
+                Some((element.properties[index].name.as_str(), values, scale))
+            })
+        };
+        let red = color_channel(&["red", "diffuse_red", "r"]);
+        let green = color_channel(&["green", "diffuse_green", "g"]);
+        let blue = color_channel(&["blue", "diffuse_blue", "b"]);
+        let alpha = color_channel(&["alpha", "diffuse_alpha", "a"]);
+
+        let mut vertices = Vec::with_capacity(xs.len());
+        for i in 0..xs.len() {
+            let pos = Vec3::new(xs[i] as f32, ys[i] as f32, zs[i] as f32);
+            let normal = normals
+                .map(|(x, y, z)| Vec3::new(x[i] as f32, y[i] as f32, z[i] as f32))
+                .unwrap_or(Vec3::ZERO);
+            let uv = texcoords
+                .map(|(u, v)| Vec2::new(u[i] as f32, 1.0 - v[i] as f32))
+                .unwrap_or(Vec2::ZERO);
+
+            let mut vertex = Vertex::with_uv(pos, normal, uv);
+            if let (Some(r), Some(g), Some(b)) = (red, green, blue) {
+                let a = alpha.map(|(_, a, scale)| (a[i] / scale) as f32).unwrap_or(1.0);
+                vertex.color = [(r.1[i] / r.2) as f32, (g.1[i] / g.2) as f32, (b.1[i] / b.2) as f32, a];
+            }
+            vertices.push(vertex);
+        }
+
+        let mut indices = Vec::new();
+        if let Some((face_element, face_columns)) = ply.element("face") {
+            let lists = face_element
+                .property_index("vertex_indices")
+                .or_else(|| face_element.property_index("vertex_index"))
+                .and_then(|i| face_columns[i].lists());
+
+            for (index, face) in lists.into_iter().flatten().enumerate() {
+                if let Some(&v) = face.iter().find(|&&v| !(v >= 0.0 && v < vertices.len() as f64 && v.fract() == 0.0)) {
+                    return Err(format!("PLY face {} has invalid vertex index {}", index, v).into());
+                }
+                for k in 1..face.len().saturating_sub(1) {
+                    indices.extend_from_slice(&[face[0], face[k], face[k + 1]].map(|v| v as u32));
+                }
+            }
+        }
+
+        if vertices.is_empty() {
+            return Err("No vertices found in PLY file".into());
+        }
+
+        let known = [
+            "x", "y", "z", "nx", "ny", "nz",
+            "u", "v", "s", "t", "texture_u", "texture_v", "texture_s", "texture_t",
+        ];
+        let color_names: Vec<&str> = [red, green, blue, alpha]
+            .iter()
+            .flatten()
+            .map(|(name, _, _)| *name)
+            .collect();
+
+        let scalar_properties = element
+            .properties
+            .iter()
+            .zip(columns)
+            .filter(|(p, _)| !known.contains(&p.name.as_str()) && !color_names.contains(&p.name.as_str()))
+            .filter_map(|(p, column)| {
+                let values = column.scalars()?.iter().map(|&v| v as f32).collect();
+                Some(ScalarProperty::new(p.name.clone(), values))
+            })
+            .collect();
+
+        let parts = vec![ModelPart {
+            name: String::from(if indices.is_empty() { "points" } else { "mesh" }),
+            vertex_offset: 0,
+            vertex_count: vertices.len() as u32,
+            index_offset: 0,
+            index_count: indices.len() as u32,
+            material_id: None,
+            visible: true,
+        }];
+
+        let has_colors = red.is_some() && green.is_some() && blue.is_some();
+        let mut model = Self::from_mesh(vertices, indices, parts);
+        model.vertex_properties = element.properties.iter().map(|p| p.name.clone()).collect();
+        model.scalar_properties = scalar_properties;
+        if has_colors {
+            model.vertex_colors = Some(model.vertices.iter().map(|v| v.color).collect());
+            model.color_source = ColorSource::VertexColors;
+        }
+        Ok(model)
+    }
+
+    pub fn from_gltf(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let data = std::fs::read(path)?;
+        let base_dir = Path::new(path).parent().map(Path::to_path_buf).unwrap_or_default();
+        Self::from_gltf_reader(&data, |name| std::fs::read(base_dir.join(name)).ok())
+    }
+
+    pub fn from_gltf_bytes(data: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::from_gltf_bytes_with_resources(data, &HashMap::new())
+    }
+
+    pub fn from_gltf_bytes_with_resources(
+        data: &[u8],
+        resources: &HashMap<String, Vec<u8>>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::from_gltf_reader(data, |name| {
+            resources
+                .get(name)
+                .or_else(|| resources.get(&utils::get_file_name(name)))
+                .cloned()
         })
     }
 
-    pub fn calculate_normals(&mut self) {
-        for i in (0..self.indices.len()).step_by(3) {
-            if i + 2 < self.indices.len() {
-                let i0 = self.indices[i] as usize;
-                let i1 = self.indices[i + 1] as usize;
-                let i2 = self.indices[i + 2] as usize;
+    fn from_gltf_reader<F>(data: &[u8], resolve: F) -> Result<Self, Box<dyn std::error::Error>>
+    where
+        F: Fn(&str) -> Option<Vec<u8>>,
+    {
+        let scene = gltf_loader::load(data, resolve)?;
+
+        if scene.vertices.is_empty() {
+            return Err("No mesh data found in glTF file".into());
+        }
+
+        let mut model = Self::from_mesh(scene.vertices, scene.indices, scene.parts);
+        model.materials = scene.materials;
+        model.nodes = scene.nodes;
+        if scene.has_vertex_colors {
+            model.vertex_colors = Some(model.vertices.iter().map(|v| v.color).collect());
+            model.color_source = ColorSource::VertexColors;
+        }
+        Ok(model)
+    }
+
+    fn bounds_cache(&self) -> Ref<'_, BoundsCache> {
+        if self.bounds_cache.borrow().as_ref().map(|cache| cache.revision) != Some(self.revision) {
+            let bounds = vertex_bounds(&self.vertices);
+            let base_bounds = if self.base_vertices.is_empty() { bounds } else { vertex_bounds(&self.base_vertices) };
+            let parts = (0..self.parts.len())
+                .map(|part| {
+                    let range = self.part_vertex_range(part);
+                    (!range.is_empty()).then(|| vertex_bounds(&self.vertices[range]))
+                })
+                .collect();
+            *self.bounds_cache.borrow_mut() = Some(BoundsCache { revision: self.revision, bounds, base_bounds, parts });
+        }
+        Ref::map(self.bounds_cache.borrow(), |cache| cache.as_ref().expect("bounds cache filled above"))
+    }
+
+    pub fn bounds(&self) -> (Vec3, Vec3) {
+        self.bounds_cache().bounds
+    }
+
+    fn base_bounds(&self) -> (Vec3, Vec3) {
+        self.bounds_cache().base_bounds
+    }
+
+    /// Fits the mesh as loaded to the view, so moving objects around does
+    /// not rescale the scene.
+    pub fn fit_transform(&self, target_size: f32) -> Mat4 {
+        let (min, max) = self.base_bounds();
+        let size = (max - min).max_element();
+        let scale = if size > 0.0 { target_size / size } else { 1.0 };
+        Mat4::from_scale(Vec3::splat(scale)) * Mat4::from_translation(-(min + max) * 0.5)
+    }
+
+    pub fn is_point_cloud(&self) -> bool {
+        self.indices.is_empty()
+    }
 
-                if i0 < self.vertices.len() && i1 < self.vertices.len() && i2 < self.vertices.len() {
-                    let v0 = self.vertices[i0].position_vec3();
-                    let v1 = self.vertices[i1].position_vec3();
-                    let v2 = self.vertices[i2].position_vec3();
+    pub fn set_color_source(&mut self, source: ColorSource) {
+        match source {
+            ColorSource::Uniform => {
+                for vertex in &mut self.vertices {
+                    vertex.color = [1.0, 1.0, 1.0, 1.0];
+                }
+            }
+            ColorSource::VertexColors => {
+                let Some(colors) = &self.vertex_colors else {
+                    return;
+                };
+                for (i, vertex) in self.vertices.iter_mut().enumerate() {
+                    if let Some(color) = colors.get(source_index(&self.vertex_sources, i)) {
+                        vertex.color = *color;
+                    }
+                }
+            }
+            ColorSource::Property(index) => {
+                let Some(property) = self.scalar_properties.get(index) else {
+                    return;
+                };
+                for (i, vertex) in self.vertices.iter_mut().enumerate() {
+                    vertex.color = utils::color_map(property.normalized(source_index(&self.vertex_sources, i)));
+                }
+            }
+        }
+        self.color_source = source;
+        self.mark_dirty();
+    }
 
-                    let edge1 = v1 - v0;
-                    let edge2 = v2 - v0;
-                    let normal = edge1.cross(edge2).normalize();
+    pub fn mark_dirty(&mut self) {
+        self.revision = next_revision();
+    }
+
+    pub fn cycle_color_source(&mut self) {
+        let mut sources = vec![ColorSource::Uniform];
+        if self.vertex_colors.is_some() {
+            sources.push(ColorSource::VertexColors);
+        }
+        sources.extend((0..self.scalar_properties.len()).map(ColorSource::Property));
+
+        let current = sources.iter().position(|&s| s == self.color_source).unwrap_or(0);
+        let next = sources[(current + 1) % sources.len()];
+        self.set_color_source(next);
+    }
 
-                    let n0 = self.vertices[i0].normal_vec3() + normal;
-                    let n1 = self.vertices[i1].normal_vec3() + normal;
-                    let n2 = self.vertices[i2].normal_vec3() + normal;
+    pub fn color_source_name(&self) -> String {
+        match self.color_source {
+            ColorSource::Uniform => String::from("uniform"),
+            ColorSource::VertexColors => String::from("vertex colors"),
+            ColorSource::Property(index) => self
+                .scalar_properties
+                .get(index)
+                .map(|p| p.name.clone())
+                .unwrap_or_default(),
+        }
+    }
 
-                    self.vertices[i0] = Vertex::new(self.vertices[i0].position_vec3(), n0);
-                    self.vertices[i1] = Vertex::new(self.vertices[i1].position_vec3(), n1);
-                    self.vertices[i2] = Vertex::new(self.vertices[i2].position_vec3(), n2);
+    pub fn part_names(&self) -> Vec<String> {
+        self.parts.iter().map(|p| p.name.clone()).collect()
+    }
+
+    pub fn toggle_part(&mut self, index: usize) -> bool {
+        if let Some(part) = self.parts.get_mut(index) {
+            part.visible = !part.visible;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn part_material(&self, part: &ModelPart) -> Option<&Material> {
+        part.material_id.and_then(|id| self.materials.get(id))
+    }
+
+    pub fn has_texcoords(&self) -> bool {
+        self.vertices.iter().any(|v| v.uv != [0.0, 0.0])
+    }
+
+    pub fn part_of_face(&self, face: usize) -> Option<usize> {
+        let index = (face * 3) as u32;
+        self.parts
+            .iter()
+            .position(|part| (part.index_offset..part.index_offset + part.index_count).contains(&index))
+    }
+
+    pub fn is_face_visible(&self, face: usize) -> bool {
+        self.part_of_face(face).filter(|&part| !self.parts[part].visible).is_none()
+    }
+
+    pub fn visible_parts(&self) -> impl Iterator<Item = &ModelPart> {
+        self.parts.iter().filter(|p| p.visible)
+    }
+
+    pub fn visible_face_ranges(&self) -> Vec<std::ops::Range<usize>> {
+        if self.parts.is_empty() {
+            return std::iter::once(0..self.indices.len() / 3).collect();
+        }
+        self.visible_parts()
+            .map(|p| p.index_offset as usize / 3..(p.index_offset + p.index_count) as usize / 3)
+            .collect()
+    }
+
+    pub fn visible_vertex_ranges(&self) -> Vec<std::ops::Range<usize>> {
+        if self.parts.is_empty() {
+            return std::iter::once(0..self.vertices.len()).collect();
+        }
+        self.visible_parts()
+            .map(|p| p.vertex_offset as usize..(p.vertex_offset + p.vertex_count) as usize)
+            .collect()
+    }
+
+    fn part_vertex_range(&self, part: usize) -> std::ops::Range<usize> {
+        self.parts
+            .get(part)
+            .map(|p| p.vertex_offset as usize..(p.vertex_offset + p.vertex_count) as usize)
+            .unwrap_or(0..0)
+    }
+
+    pub fn part_bounds(&self, part: usize) -> Option<(Vec3, Vec3)> {
+        self.bounds_cache().parts.get(part).copied().flatten()
+    }
+
+    pub fn part_model(&self, part: usize) -> Option<Model> {
+        let source = self.parts.get(part)?;
+        let range = self.part_vertex_range(part);
+        let vertices = self.vertices[range.clone()].to_vec();
+        let indices: Vec<u32> = self.indices[source.index_range().start as usize..source.index_range().end as usize]
+            .iter()
+            .filter_map(|&index| index.checked_sub(range.start as u32))
+            .collect();
+        let parts = vec![ModelPart {
+            vertex_offset: 0,
+            index_offset: 0,
+            index_count: indices.len() as u32,
+            material_id: source.material_id.map(|_| 0),
+            ..source.clone()
+        }];
+        let mut model = Model::from_mesh(vertices, indices, parts);
+        model.materials = self.part_material(source).cloned().into_iter().collect();
+        model.normal_settings = self.normal_settings;
+        Some(model)
+    }
+
+    pub fn part_transform(&self, part: usize) -> Mat4 {
+        self.part_transforms.get(&part).copied().unwrap_or(Mat4::IDENTITY)
+    }
+
+    pub fn set_part_transform(&mut self, part: usize, matrix: Mat4) -> bool {
+        self.part_previews.remove(&part);
+        let previous = self.part_transform(part);
+        if previous == matrix || part >= self.parts.len() {
+            return false;
+        }
+        let normal_matrix = Mat3::from_mat4(matrix * previous.inverse()).inverse().transpose();
+        for index in self.part_vertex_range(part) {
+            let position = match self.base_vertices.get(self.source_vertex(index)) {
+                Some(base) => matrix.transform_point3(base.position_vec3()),
+                None => (matrix * previous.inverse()).transform_point3(self.vertices[index].position_vec3()),
+            };
+            let vertex = &mut self.vertices[index];
+            vertex.position = position.to_array();
+            vertex.normal = (normal_matrix * vertex.normal_vec3()).normalize_or_zero().to_array();
+        }
+        if matrix == Mat4::IDENTITY {
+            self.part_transforms.remove(&part);
+        } else {
+            self.part_transforms.insert(part, matrix);
+        }
+        self.mark_dirty();
+        true
+    }
+
+    /// Shows a part at `matrix` without moving its vertices, so dragging an
+    /// object doesn't rebuild the mesh on every step. `set_part_transform`
+    /// bakes it.
+    pub fn preview_part_transform(&mut self, part: usize, matrix: Mat4) {
+        if matrix == self.part_transform(part) {
+            self.part_previews.remove(&part);
+        } else {
+            self.part_previews.insert(part, matrix);
+        }
+    }
+
+    pub fn part_display_transform(&self, part: usize) -> Mat4 {
+        match self.part_previews.get(&part) {
+            Some(matrix) => *matrix * self.part_transform(part).inverse(),
+            None => Mat4::IDENTITY,
+        }
+    }
+
+    pub fn append(&mut self, other: Model, name: &str) {
+        self.store_base();
+        if self.base_parts.is_empty() {
+            let part = ModelPart {
+                name: String::from("mesh"),
+                vertex_offset: 0,
+                vertex_count: self.base_vertices.len() as u32,
+                index_offset: 0,
+                index_count: self.base_indices.len() as u32,
+                material_id: None,
+                visible: true,
+            };
+            self.base_parts.push(part.clone());
+            self.parts.push(part);
+        }
+
+        let vertex_offset = self.base_vertices.len() as u32;
+        let index_offset = self.base_indices.len() as u32;
+        let material_offset = self.materials.len();
+        let part_offset = self.base_parts.len();
+        let mut parts = other.parts;
+        if parts.is_empty() {
+            parts.push(ModelPart {
+                name: String::new(),
+                vertex_offset: 0,
+                vertex_count: other.vertices.len() as u32,
+                index_offset: 0,
+                index_count: other.indices.len() as u32,
+                material_id: None,
+                visible: true,
+            });
+        }
+        for part in parts {
+            let part = ModelPart {
+                name: if part.name.is_empty() { name.to_string() } else { part.name },
+                vertex_offset: part.vertex_offset + vertex_offset,
+                index_offset: part.index_offset + index_offset,
+                material_id: part.material_id.map(|id| id + material_offset),
+                ..part
+            };
+            self.base_parts.push(part.clone());
+            self.parts.push(part);
+        }
+
+        let added = other.vertices.len();
+        if self.vertex_colors.is_some() || other.vertex_colors.is_some() {
+            self.vertex_colors = Some(
+                self.base_vertices
+                    .iter()
+                    .map(|v| v.color)
+                    .chain(other.vertices.iter().map(|v| v.color))
+                    .collect(),
+            );
+        }
+        // Scalar fields of the first file only; new vertices map to the minimum.
+        for property in &mut self.scalar_properties {
+            property.values.resize(property.values.len() + added, property.min);
+        }
+        let node_offset = self.nodes.len();
+        self.nodes.extend(other.nodes.into_iter().map(|node| SceneNode {
+            parent: node.parent.map(|parent| parent + node_offset),
+            children: node.children.iter().map(|child| child + node_offset).collect(),
+            parts: node.parts.iter().map(|part| part + part_offset).collect(),
+            ..node
+        }));
+        self.materials.extend(other.materials);
+        self.base_vertices.extend(other.vertices);
+        self.base_indices.extend(other.indices.iter().map(|&index| index + vertex_offset));
+        self.center = utils::calculate_center(&self.base_vertices.iter().map(|v| v.position_vec3()).collect::<Vec<_>>());
+
+        let settings = self.normal_settings;
+        self.apply_normals(settings);
+    }
+
+    /// Vertex of the mesh as loaded, before normals are recomputed and
+    /// object transforms applied. Source vertices index these.
+    pub fn base_vertex(&self, index: usize) -> Option<&Vertex> {
+        self.base_vertices.get(index)
+    }
+
+    pub fn base_vertex_count(&self) -> usize {
+        self.base_vertices.len()
+    }
+
+    pub fn base_face(&self, face: usize) -> Option<[u32; 3]> {
+        self.base_indices.get(face * 3..face * 3 + 3).map(|tri| [tri[0], tri[1], tri[2]])
+    }
+
+    pub fn base_face_count(&self) -> usize {
+        self.base_indices.len() / 3
+    }
+
+    pub fn base_part_of_face(&self, face: usize) -> Option<usize> {
+        let index = (face * 3) as u32;
+        self.base_parts
+            .iter()
+            .position(|part| (part.index_offset..part.index_offset + part.index_count).contains(&index))
+    }
+
+    pub fn base_part_of_vertex(&self, vertex: usize) -> Option<usize> {
+        let vertex = vertex as u32;
+        self.base_parts
+            .iter()
+            .position(|part| (part.vertex_offset..part.vertex_offset + part.vertex_count).contains(&vertex))
+    }
+
+    pub fn base_part_end(&self, part: usize) -> Option<usize> {
+        self.base_parts.get(part).map(|p| (p.index_offset + p.index_count) as usize / 3)
+    }
+
+    pub fn set_base_vertex(&mut self, index: usize, vertex: Vertex) {
+        self.store_base();
+        self.base_vertices[index] = vertex;
+    }
+
+    pub fn set_base_face(&mut self, face: usize, corners: [u32; 3]) {
+        self.store_base();
+        self.base_indices[face * 3..face * 3 + 3].copy_from_slice(&corners);
+    }
+
+    /// Removes faces of the mesh as loaded, given by their index in
+    /// ascending order.
+    pub fn remove_base_faces(&mut self, faces: &[usize]) {
+        self.store_base();
+        let mut removed = faces.iter().peekable();
+        let mut face = 0;
+        self.base_indices = std::mem::take(&mut self.base_indices)
+            .chunks_exact(3)
+            .filter(|_| {
+                let keep = removed.next_if_eq(&&face).is_none();
+                face += 1;
+                keep
+            })
+            .flatten()
+            .copied()
+            .collect();
+        for part in &mut self.base_parts {
+            let first = part.index_offset as usize / 3;
+            let end = first + part.index_count as usize / 3;
+            let before = faces.partition_point(|&face| face < first);
+            let inside = faces.partition_point(|&face| face < end) - before;
+            part.index_offset -= before as u32 * 3;
+            part.index_count -= inside as u32 * 3;
+        }
+    }
+
+    /// Inserts faces into the mesh as loaded, given by their index after
+    /// insertion in ascending order.
+    pub fn insert_base_faces(&mut self, faces: &[PlacedFace]) {
+        self.store_base();
+        let old = std::mem::take(&mut self.base_indices);
+        let mut old_faces = old.chunks_exact(3);
+        let mut inserted = faces.iter().peekable();
+        let total = old.len() / 3 + faces.len();
+        self.base_indices = Vec::with_capacity(total * 3);
+        for face in 0..total {
+            match inserted.next_if(|placed| placed.face == face) {
+                Some(placed) => self.base_indices.extend_from_slice(&placed.corners),
+                None => self.base_indices.extend(old_faces.next().into_iter().flatten()),
+            }
+        }
+        for (index, part) in self.base_parts.iter_mut().enumerate() {
+            // A face inserted at the start of a part belongs to the part before it.
+            let mut offset = part.index_offset as usize / 3;
+            for placed in faces {
+                if placed.part == Some(index) {
+                    continue;
                 }
+                if placed.face > offset {
+                    break;
+                }
+                offset += 1;
             }
+            let added = faces.iter().filter(|placed| placed.part == Some(index)).count();
+            part.index_offset = offset as u32 * 3;
+            part.index_count += added as u32 * 3;
+        }
+    }
+
+    pub fn base_mesh(&self) -> BaseMesh {
+        let (vertices, indices, parts) = if self.vertex_sources.is_empty() {
+            (&self.vertices, &self.indices, &self.parts)
+        } else {
+            (&self.base_vertices, &self.base_indices, &self.base_parts)
+        };
+        BaseMesh {
+            vertices: vertices.clone(),
+            indices: indices.clone(),
+            parts: parts.clone(),
+            vertex_colors: self.vertex_colors.clone(),
+            scalar_values: self.scalar_properties.iter().map(|p| p.values.clone()).collect(),
         }
+    }
+
+    /// Replaces the mesh as loaded. The parts must be the same, with new
+    /// vertex and index ranges.
+    pub fn set_base_mesh(&mut self, mesh: BaseMesh) {
+        self.store_base();
+        self.center = utils::calculate_center(&mesh.vertices.iter().map(|v| v.position_vec3()).collect::<Vec<_>>());
+        self.base_vertices = mesh.vertices;
+        self.base_indices = mesh.indices;
+        self.base_parts = mesh.parts;
+        self.vertex_colors = mesh.vertex_colors;
+        for (property, values) in self.scalar_properties.iter_mut().zip(mesh.scalar_values) {
+            property.values = values;
+        }
+    }
+
+    pub fn rebuild(&mut self) {
+        let settings = self.normal_settings;
+        self.apply_normals(settings);
+    }
+
+    pub fn source_vertex(&self, index: usize) -> usize {
+        source_index(&self.vertex_sources, index)
+    }
 
-        for vertex in &mut self.vertices {
-            let normal = vertex.normal_vec3().normalize();
-            *vertex = Vertex::new(vertex.position_vec3(), normal);
+    fn store_base(&mut self) {
+        if self.vertex_sources.is_empty() {
+            self.base_vertices = self.vertices.clone();
+            self.base_indices = self.indices.clone();
+            self.base_parts = self.parts.clone();
         }
     }
+
+    pub fn apply_normals(&mut self, settings: NormalSettings) {
+        self.store_base();
+
+        let result = normals::compute(&self.base_vertices, &self.base_indices, &self.base_parts, &settings);
+        for (part, updated) in self.parts.iter_mut().zip(result.parts) {
+            *part = ModelPart {
+                visible: part.visible,
+                ..updated
+            };
+        }
+        self.vertices = result.vertices;
+        self.indices = result.indices;
+        self.vertex_sources = result.sources;
+        self.normal_settings = settings;
+        for (part, matrix) in self.part_transforms.clone() {
+            self.part_transforms.remove(&part);
+            self.set_part_transform(part, matrix);
+        }
+        let source = self.color_source;
+        self.set_color_source(source);
+        self.mark_dirty();
+    }
+}
+
+fn source_index(sources: &[u32], index: usize) -> usize {
+    sources.get(index).map(|&s| s as usize).unwrap_or(index)
+}
+
+fn vertex_bounds(vertices: &[Vertex]) -> (Vec3, Vec3) {
+    if vertices.is_empty() {
+        return (Vec3::ZERO, Vec3::ZERO);
+    }
+    vertices.iter().fold((Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)), |(min, max), v| {
+        (min.min(v.position_vec3()), max.max(v.position_vec3()))
+    })
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_textures_next_to_the_mtl_file() {
+        let obj = b"mtllib sub/materials.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nusemtl wood\nf 1/1 2/1 3/1\n";
+        let mut png = Vec::new();
+        image::RgbaImage::from_pixel(1, 1, image::Rgba([10, 20, 30, 255]))
+            .write_to(&mut Cursor::new(&mut png), image::ImageOutputFormat::Png)
+            .unwrap();
+        let resources = HashMap::from([
+            (String::from("sub/materials.mtl"), b"newmtl wood\nmap_Kd textures/wood.png\n".to_vec()),
+            (String::from("sub/textures/wood.png"), png.clone()),
+            (String::from("textures/wood.png"), Vec::new()),
+        ]);
+        let model = Model::from_obj_bytes_with_resources(obj, &resources).unwrap();
+        let texture = model.materials[0].diffuse_texture.as_ref().unwrap();
+        assert_eq!(texture.rgba, [10, 20, 30, 255]);
+    }
+
+    #[test]
+    fn rejects_ply_faces_with_invalid_vertex_indices() {
+        let ply = |list_type: &str, face: &str| {
+            format!(
+                "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\nelement face 2\nproperty list uchar {} vertex_indices\nend_header\n0 0 0\n1 0 0\n0 1 0\n3 0 1 2\n{}\n",
+                list_type, face
+            )
+        };
+        assert_eq!(Model::from_ply_bytes(ply("int", "3 2 1 0").as_bytes()).unwrap().indices.len(), 6);
+        for (list_type, face) in [("int", "3 0 1 -1"), ("int", "3 0 1 3"), ("float", "3 0 nan 1"), ("float", "3 0 0.5 1")] {
+            let error = Model::from_ply_bytes(ply(list_type, face).as_bytes()).err().unwrap().to_string();
+            assert!(error.starts_with("PLY face 1 "), "{}", error);
+        }
+    }
+
+    #[test]
+    fn removes_and_inserts_faces_across_parts() {
+        let part = |name: &str, vertex_offset, vertex_count, index_offset, index_count| ModelPart {
+            name: name.to_string(),
+            vertex_offset,
+            vertex_count,
+            index_offset,
+            index_count,
+            material_id: None,
+            visible: true,
+        };
+        let vertices = (0..7).map(|i| Vertex::new(Vec3::new(i as f32, (i % 2) as f32, 0.0), Vec3::Z)).collect();
+        let indices = vec![0, 1, 2, 1, 2, 3, 2, 3, 4, 3, 4, 5];
+        let parts = vec![part("a", 0, 4, 0, 6), part("b", 2, 4, 6, 6), part("points", 6, 1, 12, 0)];
+        let mut model = Model::from_mesh(vertices, indices, parts);
+        model.apply_normals(model.normal_settings);
+        let ranges = |model: &Model| -> Vec<[u32; 2]> {
+            model.base_mesh().parts.iter().map(|p| [p.index_offset, p.index_count]).collect()
+        };
+
+        let removed: Vec<PlacedFace> = [1, 2]
+            .map(|face| PlacedFace { face, part: model.base_part_of_face(face), corners: model.base_face(face).unwrap() })
+            .to_vec();
+        model.remove_base_faces(&[1, 2]);
+        assert_eq!(model.base_mesh().indices, [0, 1, 2, 3, 4, 5]);
+        assert_eq!(ranges(&model), [[0, 3], [3, 3], [6, 0]]);
+
+        model.insert_base_faces(&removed);
+        assert_eq!(model.base_mesh().indices, [0, 1, 2, 1, 2, 3, 2, 3, 4, 3, 4, 5]);
+        assert_eq!(ranges(&model), [[0, 6], [6, 6], [12, 0]]);
+    }
+
+    #[test]
+    fn bounds_follow_object_transforms() {
+        let stl = b"solid t\nfacet normal 0 0 1\nouter loop\nvertex 0 0 0\nvertex 1 0 0\nvertex 0 1 0\nendloop\nendfacet\nendsolid t\n";
+        let mut model = Model::from_stl_bytes(stl).unwrap();
+        model.apply_normals(model.normal_settings);
+        assert_eq!(model.bounds(), (Vec3::ZERO, Vec3::new(1.0, 1.0, 0.0)));
+
+        assert!(model.set_part_transform(0, Mat4::from_translation(Vec3::Z)));
+        assert_eq!(model.bounds(), (Vec3::Z, Vec3::new(1.0, 1.0, 1.0)));
+        // The view stays fitted to the mesh as loaded.
+        assert_eq!(model.fit_transform(2.0).transform_point3(Vec3::new(1.0, 1.0, 0.0)), Vec3::new(1.0, 1.0, 0.0));
+    }
+}
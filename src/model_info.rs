@@ -1,5 +1,16 @@
-use glam::Vec3;
+use glam::{Mat4, Vec3};
+use crate::normals::NormalMode;
+use crate::units::Unit;
 use crate::utils;
+use crate::mass_properties::MassProperties;
+use crate::validation::{self, MeshReport};
+use crate::cleanup::CleanupReport;
+
+pub struct PartInfo {
+    pub name: String,
+    pub vertex_count: usize,
+    pub face_count: usize,
+}
 
 pub struct ModelInfo {
     pub vertex_count: usize,
@@ -9,19 +20,51 @@ pub struct ModelInfo {
     pub center: Vec3,
     pub file_path: Option<String>,
     pub file_size: Option<u64>,
+    pub object_name: Option<String>,
+    pub parts: Vec<PartInfo>,
+    pub material_count: usize,
+    pub has_texcoords: bool,
+    pub has_vertex_colors: bool,
+    pub is_point_cloud: bool,
+    pub vertex_properties: Vec<String>,
+    pub scalar_properties: Vec<String>,
+    pub node_count: usize,
+    pub normals: String,
+    pub validation: Option<MeshReport>,
+    pub cleanup: Option<CleanupReport>,
+    pub mass: Option<MassProperties>,
+    pub transform: Mat4,
+    pub unit: Unit,
+    pub density: f32,
+    pub revision: u64,
+    analyzed: bool,
 }
 
 impl ModelInfo {
     pub fn from_model(model: &crate::model::Model, path: Option<String>) -> Self {
+        let mut info = Self::summary(model, path);
+        info.analyze(model);
+        info
+    }
+
+    /// The info without the validation and mass properties, which take a
+    /// while on large meshes and are filled in by `analyze`.
+    pub fn summary(model: &crate::model::Model, path: Option<String>) -> Self {
         let vertex_count = model.vertices.len();
         let face_count = model.indices.len() / 3;
-        let (min, max) = utils::calculate_bounds(
-            &model.vertices.iter().map(|v| v.position_vec3()).collect::<Vec<_>>()
-        );
-        
+        let (min, max) = model.bounds();
+
         let file_size = path.as_ref()
             .and_then(|p| std::fs::metadata(p).ok())
             .map(|m| m.len());
+
+        let parts = model.parts.iter()
+            .map(|p| PartInfo {
+                name: p.name.clone(),
+                vertex_count: p.vertex_count as usize,
+                face_count: p.face_count(),
+            })
+            .collect();
         
         Self {
             vertex_count,
@@ -31,9 +74,114 @@ impl ModelInfo {
             center: model.center,
             file_path: path,
             file_size,
+            object_name: None,
+            parts,
+            material_count: model.materials.len(),
+            has_texcoords: model.has_texcoords(),
+            has_vertex_colors: model.vertex_colors.is_some(),
+            is_point_cloud: model.is_point_cloud(),
+            vertex_properties: model.vertex_properties.clone(),
+            scalar_properties: model.scalar_properties.iter().map(|p| p.name.clone()).collect(),
+            node_count: model.nodes.len(),
+            normals: match model.normal_settings.mode {
+                NormalMode::Crease => format!("crease {:.0}°, {} weighted", model.normal_settings.crease_angle, model.normal_settings.weighting.name()),
+                mode => format!("{}, {} weighted", mode.name(), model.normal_settings.weighting.name()),
+            },
+            validation: None,
+            cleanup: None,
+            mass: None,
+            unit: Unit::default(),
+            density: 1.0,
+            transform: Mat4::IDENTITY,
+            revision: model.revision,
+            analyzed: false,
+        }
+    }
+
+    pub fn analyze(&mut self, model: &crate::model::Model) {
+        if self.analyzed {
+            return;
+        }
+        self.validation = (!model.is_point_cloud()).then(|| validation::validate(&model.vertices, &model.indices));
+        self.mass = self
+            .validation
+            .as_ref()
+            .map(|report| MassProperties::compute(&model.vertices, &model.indices, report.is_watertight(), self.transform));
+        self.analyzed = true;
+    }
+
+    pub fn reuse_analysis(&mut self, previous: ModelInfo) {
+        if previous.analyzed && previous.revision == self.revision && previous.transform == self.transform {
+            self.validation = previous.validation;
+            self.mass = previous.mass;
+            self.analyzed = true;
         }
     }
 
+    pub fn from_part(model: &crate::model::Model, part: usize, path: Option<String>, transform: Mat4) -> Option<Self> {
+        let part_model = model.part_model(part)?;
+        let mut info = Self::summary(&part_model, path);
+        info.object_name = Some(model.parts[part].name.clone());
+        info.transform = transform;
+        info.analyze(&part_model);
+        Some(info)
+    }
+
+    pub fn dimensions(&self) -> Vec3 {
+        self.bounds_max - self.bounds_min
+    }
+
+    pub fn format_mass(&self) -> String {
+        let Some(mass) = &self.mass else {
+            return String::new();
+        };
+        let unit = self.unit.suffix();
+        let grams_per_cubic_unit = self.density as f64 * (self.unit.millimeters() as f64 / 10.0).powi(3);
+
+        let mut info = String::new();
+        if self.transform != Mat4::IDENTITY {
+            info.push_str("\nMass properties of the transformed model:");
+        }
+        info.push_str(&format!("\nSurface area: {:.3} {}²", mass.surface_area, unit));
+        match mass.volume {
+            Some(volume) => {
+                info.push_str(&format!("\nVolume: {:.3} {}³", volume, unit));
+                info.push_str(&format!(
+                    "\nMass: {:.3} g (density {:.2} g/cm³)",
+                    volume * grams_per_cubic_unit,
+                    self.density
+                ));
+            }
+            None => info.push_str("\nVolume: n/a (mesh is not closed)"),
+        }
+        info.push_str(&format!(
+            "\nCentroid: ({:.3}, {:.3}, {:.3})",
+            mass.centroid.x, mass.centroid.y, mass.centroid.z
+        ));
+        if let Some(com) = mass.center_of_mass {
+            info.push_str(&format!("\nCenter of mass: ({:.3}, {:.3}, {:.3})", com.x, com.y, com.z));
+        }
+        if let (Some(inertia), Some(moments)) = (mass.inertia, mass.principal_moments) {
+            let inertia = inertia * grams_per_cubic_unit;
+            let moments = moments * grams_per_cubic_unit;
+            info.push_str(&format!(
+                "\nInertia (g·{}²): Ixx {:.4e}, Iyy {:.4e}, Izz {:.4e}, Ixy {:.4e}, Ixz {:.4e}, Iyz {:.4e}",
+                unit,
+                inertia.x_axis.x, inertia.y_axis.y, inertia.z_axis.z,
+                inertia.y_axis.x, inertia.z_axis.x, inertia.z_axis.y,
+            ));
+            info.push_str(&format!(
+                "\nPrincipal moments (g·{}²): {:.4e}, {:.4e}, {:.4e}",
+                unit, moments.x, moments.y, moments.z
+            ));
+        }
+        info.push_str(&format!(
+            "\nOriented box: {:.2} x {:.2} x {:.2} {}",
+            mass.obb_size.x, mass.obb_size.y, mass.obb_size.z, unit
+        ));
+        info
+    }
+
     pub fn format_info(&self) -> String {
         let file_info = if let Some(ref path) = self.file_path {
             let name = utils::get_file_name(path);
@@ -44,15 +192,66 @@ impl ModelInfo {
         } else {
             String::from("File: Unknown")
         };
+        let file_info = match &self.object_name {
+            Some(name) => format!("{}\nObject: {}", file_info, name),
+            None => file_info,
+        };
         
+        let unit = self.unit.suffix();
+        let size = self.dimensions();
+
+        let mut properties_info = format!(
+            "\nVertex colors: {}",
+            if self.has_vertex_colors { "yes" } else { "no" }
+        );
+        if self.is_point_cloud {
+            properties_info.push_str("\nPoint cloud: yes");
+        }
+        if !self.vertex_properties.is_empty() {
+            properties_info.push_str(&format!("\nVertex properties: {}", self.vertex_properties.join(", ")));
+        }
+        if !self.scalar_properties.is_empty() {
+            properties_info.push_str(&format!("\nScalar fields: {}", self.scalar_properties.join(", ")));
+        }
+
+        properties_info.push_str(&format!("\nNormals: {}", self.normals));
+
+        if let Some(report) = &self.validation {
+            properties_info.push('\n');
+            properties_info.push_str(&report.format_report());
+        }
+
+        if let Some(report) = &self.cleanup {
+            properties_info.push_str("\nLast cleanup:\n");
+            properties_info.push_str(&report.format_report());
+        }
+
+        if self.node_count > 0 {
+            properties_info.push_str(&format!("\nNodes: {}", self.node_count));
+        }
+
+        let parts_info: String = self.parts.iter()
+            .enumerate()
+            .map(|(i, p)| format!("\n  [{}] {}: {} vertices, {} faces", i + 1, p.name, p.vertex_count, p.face_count))
+            .collect();
+
         format!(
-            "{}\nVertices: {}\nFaces: {}\nCenter: ({:.2}, {:.2}, {:.2})\nBounds: ({:.2}, {:.2}, {:.2}) to ({:.2}, {:.2}, {:.2})",
+            "{}\nVertices: {}\nFaces: {}\nCenter: ({:.2}, {:.2}, {:.2})\nBounds: ({:.2}, {:.2}, {:.2}) to ({:.2}, {:.2}, {:.2})\nUnits: {}\nDimensions: {:.2} x {:.2} x {:.2} {}{}\nTexcoords: {}{}\nMaterials: {}\nParts: {}{}",
             file_info,
             self.vertex_count,
             self.face_count,
             self.center.x, self.center.y, self.center.z,
             self.bounds_min.x, self.bounds_min.y, self.bounds_min.z,
             self.bounds_max.x, self.bounds_max.y, self.bounds_max.z,
+            unit,
+            size.x, size.y, size.z,
+            unit,
+            self.format_mass(),
+            if self.has_texcoords { "yes" } else { "no" },
+            properties_info,
+            self.material_count,
+            self.parts.len(),
+            parts_info,
         )
     }
 }
@@ -0,0 +1,385 @@
+use glam::Vec3;
+use std::collections::HashMap;
+use crate::model::{ModelPart, Vertex};
+use crate::utils::position_key;
+
+const DEGENERATE_EPSILON: f32 = 1e-12;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NormalMode {
+    File,
+    Flat,
+    Smooth,
+    Crease,
+}
+
+impl NormalMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "file" => Some(NormalMode::File),
+            "flat" => Some(NormalMode::Flat),
+            "smooth" => Some(NormalMode::Smooth),
+            "crease" => Some(NormalMode::Crease),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            NormalMode::File => "file",
+            NormalMode::Flat => "flat",
+            NormalMode::Smooth => "smooth",
+            NormalMode::Crease => "crease",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            NormalMode::File => NormalMode::Flat,
+            NormalMode::Flat => NormalMode::Smooth,
+            NormalMode::Smooth => NormalMode::Crease,
+            NormalMode::Crease => NormalMode::File,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NormalWeighting {
+    Uniform,
+    Area,
+    Angle,
+}
+
+impl NormalWeighting {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "uniform" => Some(NormalWeighting::Uniform),
+            "area" => Some(NormalWeighting::Area),
+            "angle" => Some(NormalWeighting::Angle),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            NormalWeighting::Uniform => "uniform",
+            NormalWeighting::Area => "area",
+            NormalWeighting::Angle => "angle",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NormalSettings {
+    pub mode: NormalMode,
+    pub weighting: NormalWeighting,
+    pub crease_angle: f32,
+}
+
+impl Default for NormalSettings {
+    fn default() -> Self {
+        Self {
+            mode: NormalMode::File,
+            weighting: NormalWeighting::Area,
+            crease_angle: 30.0,
+        }
+    }
+}
+
+pub struct NormalResult {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+    pub sources: Vec<u32>,
+    pub parts: Vec<ModelPart>,
+}
+
+pub fn face_normal(a: Vec3, b: Vec3, c: Vec3) -> Option<Vec3> {
+    let cross = (b - a).cross(c - a);
+    if cross.length_squared() > DEGENERATE_EPSILON {
+        Some(cross.normalize())
+    } else {
+        None
+    }
+}
+
+fn corner_weight(positions: [Vec3; 3], corner: usize, weighting: NormalWeighting) -> f32 {
+    match weighting {
+        NormalWeighting::Uniform => 1.0,
+        NormalWeighting::Area => {
+            (positions[1] - positions[0]).cross(positions[2] - positions[0]).length() * 0.5
+        }
+        NormalWeighting::Angle => {
+            let p = positions[corner];
+            let e1 = (positions[(corner + 1) % 3] - p).normalize_or_zero();
+            let e2 = (positions[(corner + 2) % 3] - p).normalize_or_zero();
+            e1.dot(e2).clamp(-1.0, 1.0).acos()
+        }
+    }
+}
+
+pub fn vertex_normals(positions: &[Vec3], indices: &[u32], weighting: NormalWeighting) -> Vec<Vec3> {
+    let mut groups: HashMap<[u32; 3], Vec3> = HashMap::new();
+    let mut fallback: HashMap<[u32; 3], Vec3> = HashMap::new();
+
+    for tri in indices.chunks_exact(3) {
+        let corners = [positions[tri[0] as usize], positions[tri[1] as usize], positions[tri[2] as usize]];
+        let Some(normal) = face_normal(corners[0], corners[1], corners[2]) else {
+            continue;
+        };
+        for (k, corner) in corners.iter().enumerate() {
+            let key = position_key(*corner);
+            *groups.entry(key).or_insert(Vec3::ZERO) += normal * corner_weight(corners, k, weighting);
+            fallback.entry(key).or_insert(normal);
+        }
+    }
+
+    positions
+        .iter()
+        .map(|p| {
+            let key = position_key(*p);
+            let summed = groups.get(&key).copied().unwrap_or(Vec3::ZERO);
+            if summed.length_squared() > DEGENERATE_EPSILON {
+                summed.normalize()
+            } else {
+                fallback.get(&key).copied().unwrap_or(Vec3::ZERO)
+            }
+        })
+        .collect()
+}
+
+pub fn compute(
+    vertices: &[Vertex],
+    indices: &[u32],
+    parts: &[ModelPart],
+    settings: &NormalSettings,
+) -> NormalResult {
+    let positions: Vec<Vec3> = vertices.iter().map(|v| v.position_vec3()).collect();
+    let smooth = vertex_normals(&positions, indices, settings.weighting);
+
+    match settings.mode {
+        NormalMode::File | NormalMode::Smooth => {
+            let keep_file = settings.mode == NormalMode::File;
+            let vertices = vertices
+                .iter()
+                .zip(&smooth)
+                .map(|(vertex, normal)| {
+                    let mut vertex = *vertex;
+                    if !keep_file || vertex.normal_vec3().length_squared() < DEGENERATE_EPSILON {
+                        vertex.normal = normal.to_array();
+                    }
+                    vertex
+                })
+                .collect();
+            NormalResult {
+                vertices,
+                indices: indices.to_vec(),
+                sources: (0..positions.len() as u32).collect(),
+                parts: parts.to_vec(),
+            }
+        }
+        NormalMode::Flat | NormalMode::Crease => {
+            let threshold = if settings.mode == NormalMode::Flat {
+                1.0 - 1e-4
+            } else {
+                settings.crease_angle.to_radians().cos()
+            };
+            split_by_crease(vertices, indices, parts, &positions, &smooth, threshold, settings.weighting)
+        }
+    }
+}
+
+fn split_by_crease(
+    vertices: &[Vertex],
+    indices: &[u32],
+    parts: &[ModelPart],
+    positions: &[Vec3],
+    smooth: &[Vec3],
+    cos_threshold: f32,
+    weighting: NormalWeighting,
+) -> NormalResult {
+    let face_normals: Vec<Option<Vec3>> = indices
+        .chunks_exact(3)
+        .map(|tri| face_normal(positions[tri[0] as usize], positions[tri[1] as usize], positions[tri[2] as usize]))
+        .collect();
+
+    let mut incident: HashMap<[u32; 3], Vec<(usize, usize)>> = HashMap::new();
+    for (face, tri) in indices.chunks_exact(3).enumerate() {
+        for (k, &v) in tri.iter().enumerate() {
+            incident.entry(position_key(positions[v as usize])).or_default().push((face, k));
+        }
+    }
+
+    let whole;
+    let parts = if parts.is_empty() {
+        whole = [ModelPart {
+            name: String::from("mesh"),
+            vertex_offset: 0,
+            vertex_count: vertices.len() as u32,
+            index_offset: 0,
+            index_count: indices.len() as u32,
+            material_id: None,
+            visible: true,
+        }];
+        &whole[..]
+    } else {
+        parts
+    };
+
+    let mut out_vertices = Vec::with_capacity(vertices.len());
+    let mut out_indices = Vec::with_capacity(indices.len());
+    let mut sources = Vec::with_capacity(vertices.len());
+    let mut out_parts = Vec::with_capacity(parts.len());
+
+    for part in parts {
+        let vertex_offset = out_vertices.len() as u32;
+        let index_offset = out_indices.len() as u32;
+        let mut lookup: HashMap<(u32, [u32; 3]), u32> = HashMap::new();
+
+        let start = part.index_offset as usize;
+        let end = start + part.index_count as usize;
+        for (local, tri) in indices[start..end].chunks_exact(3).enumerate() {
+            let face = start / 3 + local;
+            for &v in tri {
+                let normal = match face_normals[face] {
+                    Some(own) => {
+                        let mut sum = Vec3::ZERO;
+                        for &(other, k) in &incident[&position_key(positions[v as usize])] {
+                            let Some(other_normal) = face_normals[other] else {
+                                continue;
+                            };
+                            if other_normal.dot(own) >= cos_threshold {
+                                let ot = &indices[other * 3..other * 3 + 3];
+                                let corners = [positions[ot[0] as usize], positions[ot[1] as usize], positions[ot[2] as usize]];
+                                sum += other_normal * corner_weight(corners, k, weighting);
+                            }
+                        }
+                        if sum.length_squared() > DEGENERATE_EPSILON {
+                            sum.normalize()
+                        } else {
+                            own
+                        }
+                    }
+                    None => smooth[v as usize],
+                };
+
+                let key = (v, position_key(normal));
+                let index = *lookup.entry(key).or_insert_with(|| {
+                    let mut vertex = vertices[v as usize];
+                    vertex.normal = normal.to_array();
+                    out_vertices.push(vertex);
+                    sources.push(v);
+                    (out_vertices.len() - 1) as u32
+                });
+                out_indices.push(index);
+            }
+        }
+
+        if part.index_count == 0 {
+            let first = part.vertex_offset as usize;
+            for v in first..first + part.vertex_count as usize {
+                let mut vertex = vertices[v];
+                if vertex.normal_vec3().length_squared() < DEGENERATE_EPSILON {
+                    vertex.normal = smooth[v].to_array();
+                }
+                out_vertices.push(vertex);
+                sources.push(v as u32);
+            }
+        }
+
+        out_parts.push(ModelPart {
+            vertex_offset,
+            vertex_count: out_vertices.len() as u32 - vertex_offset,
+            index_offset,
+            index_count: out_indices.len() as u32 - index_offset,
+            ..part.clone()
+        });
+    }
+
+    NormalResult {
+        vertices: out_vertices,
+        indices: out_indices,
+        sources,
+        parts: out_parts,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_meshes::{cube_vertices, CUBE_INDICES};
+
+    fn settings(mode: NormalMode) -> NormalSettings {
+        NormalSettings { mode, ..Default::default() }
+    }
+
+    fn assert_faces_face_outwards(result: &NormalResult) {
+        for tri in result.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|k| result.vertices[tri[k] as usize]);
+            let face = face_normal(a.position_vec3(), b.position_vec3(), c.position_vec3()).unwrap();
+            for vertex in [a, b, c] {
+                assert!(vertex.normal_vec3().dot(face) > 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn face_normals_follow_the_winding() {
+        assert_eq!(face_normal(Vec3::ZERO, Vec3::X, Vec3::Y), Some(Vec3::Z));
+        assert_eq!(face_normal(Vec3::ZERO, Vec3::Y, Vec3::X), Some(-Vec3::Z));
+        assert_eq!(face_normal(Vec3::ZERO, Vec3::X, Vec3::X * 2.0), None);
+    }
+
+    #[test]
+    fn angle_weighted_cube_corners_point_along_the_diagonal() {
+        let settings = NormalSettings { weighting: NormalWeighting::Angle, ..settings(NormalMode::Smooth) };
+        let result = compute(&cube_vertices(), &CUBE_INDICES, &[], &settings);
+        assert_eq!(result.vertices.len(), 8);
+        assert_eq!(result.indices, CUBE_INDICES);
+        for vertex in &result.vertices {
+            let diagonal = (vertex.position_vec3() - Vec3::splat(0.5)).normalize();
+            assert!(vertex.normal_vec3().dot(diagonal) > 0.999, "{:?}", vertex.normal);
+        }
+        assert_faces_face_outwards(&result);
+    }
+
+    #[test]
+    fn flat_and_crease_modes_split_cube_corners() {
+        for mode in [NormalMode::Flat, NormalMode::Crease] {
+            let result = compute(&cube_vertices(), &CUBE_INDICES, &[], &settings(mode));
+            assert_eq!(result.vertices.len(), 24);
+            assert_eq!(result.parts.len(), 1);
+            assert_eq!(result.parts[0].index_count, 36);
+            for (vertex, &source) in result.vertices.iter().zip(&result.sources) {
+                assert_eq!(vertex.position, cube_vertices()[source as usize].position);
+                assert!(vertex.normal.iter().filter(|c| c.abs() > 0.999).count() == 1);
+            }
+            assert_faces_face_outwards(&result);
+        }
+    }
+
+    #[test]
+    fn a_wide_crease_angle_keeps_the_cube_smooth() {
+        let settings = NormalSettings { crease_angle: 120.0, ..settings(NormalMode::Crease) };
+        let result = compute(&cube_vertices(), &CUBE_INDICES, &[], &settings);
+        assert_eq!(result.vertices.len(), 8);
+    }
+
+    #[test]
+    fn file_mode_keeps_existing_normals() {
+        let mut vertices = cube_vertices();
+        vertices[0].normal = [0.0, 0.0, 1.0];
+        let result = compute(&vertices, &CUBE_INDICES, &[], &settings(NormalMode::File));
+        assert_eq!(result.vertices[0].normal, [0.0, 0.0, 1.0]);
+        assert!(result.vertices[1].normal_vec3().length() > 0.999);
+    }
+
+    #[test]
+    fn weightings_differ_on_uneven_fans() {
+        let positions = [Vec3::ZERO, Vec3::X, Vec3::Y, Vec3::new(0.0, 0.0, 4.0)];
+        let indices = [0, 1, 2, 0, 3, 1];
+        let uniform = vertex_normals(&positions, &indices, NormalWeighting::Uniform)[0];
+        let area = vertex_normals(&positions, &indices, NormalWeighting::Area)[0];
+        assert!((uniform - Vec3::new(0.0, 1.0, 1.0).normalize()).length() < 1e-5);
+        assert!(area.y > area.z);
+    }
+}
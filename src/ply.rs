@@ -0,0 +1,371 @@
+use std::io::{BufRead, Cursor, Read};
+
+const MAX_PREALLOCATED: usize = 1 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScalarType {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64,
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(match name {
+            "char" | "int8" => ScalarType::Int8,
+            "uchar" | "uint8" => ScalarType::UInt8,
+            "short" | "int16" => ScalarType::Int16,
+            "ushort" | "uint16" => ScalarType::UInt16,
+            "int" | "int32" => ScalarType::Int32,
+            "uint" | "uint32" => ScalarType::UInt32,
+            "float" | "float32" => ScalarType::Float32,
+            "double" | "float64" => ScalarType::Float64,
+            _ => return Err(format!("Unknown PLY property type: {}", name).into()),
+        })
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            ScalarType::Int8 | ScalarType::UInt8 => 1,
+            ScalarType::Int16 | ScalarType::UInt16 => 2,
+            ScalarType::Int32 | ScalarType::UInt32 | ScalarType::Float32 => 4,
+            ScalarType::Float64 => 8,
+        }
+    }
+
+    pub fn is_integer(&self) -> bool {
+        !matches!(self, ScalarType::Float32 | ScalarType::Float64)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum PropertyKind {
+    Scalar(ScalarType),
+    List { count: ScalarType, item: ScalarType },
+}
+
+#[derive(Debug, Clone)]
+pub struct PlyProperty {
+    pub name: String,
+    pub kind: PropertyKind,
+}
+
+#[derive(Debug, Clone)]
+pub struct PlyElement {
+    pub name: String,
+    pub count: usize,
+    pub properties: Vec<PlyProperty>,
+}
+
+impl PlyElement {
+    /// Fewest bytes one entry can take: empty lists, and in ASCII one digit
+    /// and a separator per value.
+    fn min_size(&self, format: PlyFormat) -> usize {
+        self.properties
+            .iter()
+            .map(|p| match (format, &p.kind) {
+                (PlyFormat::Ascii, _) => 2,
+                (_, PropertyKind::Scalar(ty)) => ty.size(),
+                (_, PropertyKind::List { count, .. }) => count.size(),
+            })
+            .sum()
+    }
+
+    pub fn property_index(&self, name: &str) -> Option<usize> {
+        self.properties.iter().position(|p| p.name == name)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum PlyColumn {
+    Scalar(Vec<f64>),
+    List(Vec<Vec<f64>>),
+}
+
+impl PlyColumn {
+    fn with_capacity(kind: &PropertyKind, capacity: usize) -> Self {
+        match kind {
+            PropertyKind::Scalar(_) => PlyColumn::Scalar(Vec::with_capacity(capacity)),
+            PropertyKind::List { .. } => PlyColumn::List(Vec::with_capacity(capacity)),
+        }
+    }
+
+    pub fn scalars(&self) -> Option<&[f64]> {
+        match self {
+            PlyColumn::Scalar(values) => Some(values),
+            PlyColumn::List(_) => None,
+        }
+    }
+
+    pub fn lists(&self) -> Option<&[Vec<f64>]> {
+        match self {
+            PlyColumn::List(values) => Some(values),
+            PlyColumn::Scalar(_) => None,
+        }
+    }
+}
+
+pub struct PlyData {
+    pub format: PlyFormat,
+    pub elements: Vec<PlyElement>,
+    pub columns: Vec<Vec<PlyColumn>>,
+}
+
+impl PlyData {
+    pub fn element(&self, name: &str) -> Option<(&PlyElement, &[PlyColumn])> {
+        self.elements
+            .iter()
+            .position(|e| e.name == name)
+            .map(|i| (&self.elements[i], self.columns[i].as_slice()))
+    }
+}
+
+pub fn parse(data: &[u8]) -> Result<PlyData, Box<dyn std::error::Error>> {
+    let mut cursor = Cursor::new(data);
+    let (format, elements) = parse_header(&mut cursor)?;
+
+    // Counts come straight from the header, so check them against the data
+    // before allocating for them.
+    let body_size = data.len() - cursor.position() as usize;
+    let mut min_body_size = 0usize;
+    for element in &elements {
+        min_body_size = element
+            .count
+            .checked_mul(element.min_size(format))
+            .and_then(|size| min_body_size.checked_add(size))
+            .ok_or_else(|| format!("PLY element '{}' is too large", element.name))?;
+    }
+    // The last ASCII value needs no separator.
+    if min_body_size.saturating_sub((format == PlyFormat::Ascii) as usize) > body_size {
+        return Err("PLY data is shorter than the element counts in its header".into());
+    }
+
+    let mut columns: Vec<Vec<PlyColumn>> = elements
+        .iter()
+        .map(|e| {
+            e.properties
+                .iter()
+                .map(|p| PlyColumn::with_capacity(&p.kind, e.count.min(MAX_PREALLOCATED)))
+                .collect()
+        })
+        .collect();
+
+    let mut body = String::new();
+    let mut tokens = if format == PlyFormat::Ascii {
+        cursor.read_to_string(&mut body)?;
+        Some(body.split_whitespace())
+    } else {
+        None
+    };
+    let big_endian = format == PlyFormat::BinaryBigEndian;
+
+    for (element, element_columns) in elements.iter().zip(columns.iter_mut()) {
+        if element.properties.is_empty() {
+            continue;
+        }
+        for _ in 0..element.count {
+            for (property, column) in element.properties.iter().zip(element_columns.iter_mut()) {
+                match (&property.kind, column) {
+                    (PropertyKind::Scalar(ty), PlyColumn::Scalar(values)) => {
+                        values.push(match tokens.as_mut() {
+                            Some(tokens) => next_ascii_value(tokens)?,
+                            None => read_binary_scalar(&mut cursor, *ty, big_endian)?,
+                        });
+                    }
+                    (PropertyKind::List { count, item }, PlyColumn::List(values)) => {
+                        // Lists grow as items are read, so a bad count fails at the end of the data.
+                        let mut list = Vec::new();
+                        match tokens.as_mut() {
+                            Some(tokens) => {
+                                let n = next_ascii_value(tokens)? as usize;
+                                for _ in 0..n {
+                                    list.push(next_ascii_value(tokens)?);
+                                }
+                            }
+                            None => {
+                                let n = read_binary_scalar(&mut cursor, *count, big_endian)? as usize;
+                                for _ in 0..n {
+                                    list.push(read_binary_scalar(&mut cursor, *item, big_endian)?);
+                                }
+                            }
+                        }
+                        values.push(list);
+                    }
+                    _ => unreachable!(),
+                }
+            }
+        }
+    }
+
+    Ok(PlyData { format, elements, columns })
+}
+
+fn parse_header(cursor: &mut Cursor<&[u8]>) -> Result<(PlyFormat, Vec<PlyElement>), Box<dyn std::error::Error>> {
+    let mut line = String::new();
+    cursor.read_line(&mut line)?;
+    if line.trim() != "ply" {
+        return Err("Not a PLY file: missing 'ply' magic".into());
+    }
+
+    let mut format = None;
+    let mut elements: Vec<PlyElement> = Vec::new();
+
+    loop {
+        line.clear();
+        if cursor.read_line(&mut line)? == 0 {
+            return Err("Unexpected end of PLY header".into());
+        }
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.as_slice() {
+            ["format", kind, ..] => {
+                format = Some(match *kind {
+                    "ascii" => PlyFormat::Ascii,
+                    "binary_little_endian" => PlyFormat::BinaryLittleEndian,
+                    "binary_big_endian" => PlyFormat::BinaryBigEndian,
+                    _ => return Err(format!("Unknown PLY format: {}", kind).into()),
+                });
+            }
+            ["element", name, count] => {
+                elements.push(PlyElement {
+                    name: name.to_string(),
+                    count: count.parse()?,
+                    properties: Vec::new(),
+                });
+            }
+            ["property", "list", count, item, name] => {
+                let element = elements.last_mut().ok_or("PLY property declared before any element")?;
+                element.properties.push(PlyProperty {
+                    name: name.to_string(),
+                    kind: PropertyKind::List {
+                        count: ScalarType::parse(count)?,
+                        item: ScalarType::parse(item)?,
+                    },
+                });
+            }
+            ["property", ty, name] => {
+                let element = elements.last_mut().ok_or("PLY property declared before any element")?;
+                element.properties.push(PlyProperty {
+                    name: name.to_string(),
+                    kind: PropertyKind::Scalar(ScalarType::parse(ty)?),
+                });
+            }
+            ["end_header"] => break,
+            _ => {}
+        }
+    }
+
+    let format = format.ok_or("PLY header is missing a format line")?;
+    Ok((format, elements))
+}
+
+fn next_ascii_value<'a, I>(tokens: &mut I) -> Result<f64, Box<dyn std::error::Error>>
+where
+    I: Iterator<Item = &'a str>,
+{
+    Ok(tokens.next().ok_or("Unexpected end of PLY data")?.parse()?)
+}
+
+fn read_binary_scalar<R: Read>(reader: &mut R, ty: ScalarType, big_endian: bool) -> Result<f64, Box<dyn std::error::Error>> {
+    let mut buf = [0u8; 8];
+    let bytes = &mut buf[..ty.size()];
+    reader.read_exact(bytes)?;
+    if big_endian {
+        bytes.reverse();
+    }
+
+    Ok(match ty {
+        ScalarType::Int8 => bytes[0] as i8 as f64,
+        ScalarType::UInt8 => bytes[0] as f64,
+        ScalarType::Int16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+        ScalarType::UInt16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+        ScalarType::Int32 => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+        ScalarType::UInt32 => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+        ScalarType::Float32 => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+        ScalarType::Float64 => f64::from_le_bytes(buf),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binary_triangle() -> Vec<u8> {
+        let mut data = b"ply\nformat binary_little_endian 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\nelement face 1\nproperty list uchar int vertex_indices\nend_header\n".to_vec();
+        for value in [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.push(3);
+        for index in [0i32, 1, 2] {
+            data.extend_from_slice(&index.to_le_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn parses_ascii() {
+        let data = b"ply\nformat ascii 1.0\nelement vertex 2\nproperty float x\nproperty uchar red\nelement face 1\nproperty list uchar int vertex_indices\nend_header\n1.5 255\n-2 0\n3 0 1 1";
+        let ply = parse(data).unwrap();
+        assert_eq!(ply.format, PlyFormat::Ascii);
+        let (vertex, columns) = ply.element("vertex").unwrap();
+        assert_eq!(vertex.count, 2);
+        assert_eq!(columns[0].scalars().unwrap(), &[1.5, -2.0]);
+        assert_eq!(columns[1].scalars().unwrap(), &[255.0, 0.0]);
+        let (_, faces) = ply.element("face").unwrap();
+        assert_eq!(faces[0].lists().unwrap(), &[vec![0.0, 1.0, 1.0]]);
+    }
+
+    #[test]
+    fn parses_binary() {
+        let ply = parse(&binary_triangle()).unwrap();
+        let (_, columns) = ply.element("vertex").unwrap();
+        assert_eq!(columns[0].scalars().unwrap(), &[0.0, 1.0, 0.0]);
+        assert_eq!(columns[1].scalars().unwrap(), &[0.0, 0.0, 1.0]);
+        let (_, faces) = ply.element("face").unwrap();
+        assert_eq!(faces[0].lists().unwrap(), &[vec![0.0, 1.0, 2.0]]);
+    }
+
+    #[test]
+    fn parses_big_endian_doubles() {
+        let mut data = b"ply\nformat binary_big_endian 1.0\nelement vertex 1\nproperty double x\nend_header\n".to_vec();
+        data.extend_from_slice(&2.25f64.to_be_bytes());
+        let ply = parse(&data).unwrap();
+        assert_eq!(ply.element("vertex").unwrap().1[0].scalars().unwrap(), &[2.25]);
+    }
+
+    #[test]
+    fn rejects_counts_larger_than_the_data() {
+        let data = b"ply\nformat binary_little_endian 1.0\nelement vertex 4000000000\nproperty float x\nproperty float y\nproperty float z\nend_header\n\0\0\0\0";
+        assert!(parse(data).is_err());
+        let data = b"ply\nformat ascii 1.0\nelement vertex 18446744073709551615\nproperty float x\nend_header\n1\n";
+        assert!(parse(data).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_lists() {
+        let mut data = binary_triangle();
+        // A face claiming 255 indices with only three present.
+        let count = data.len() - 13;
+        data[count] = 255;
+        assert!(parse(&data).is_err());
+    }
+
+    #[test]
+    fn rejects_bad_headers() {
+        assert!(parse(b"plx\nformat ascii 1.0\nend_header\n").is_err());
+        assert!(parse(b"ply\nelement vertex 1\nproperty float x\nend_header\n1\n").is_err());
+        assert!(parse(b"ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\n").is_err());
+        assert!(parse(b"ply\nformat ascii 1.0\nelement vertex 1\nproperty quad x\nend_header\n1\n").is_err());
+    }
+}
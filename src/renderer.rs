@@ -1,9 +1,18 @@
 use wgpu::util::DeviceExt;
 use wgpu::*;
-use glam::Mat4;
-use crate::model::Model;
+use glam::{Mat3, Mat4, Vec3};
+use std::ops::Range;
+use std::time::{Duration, Instant};
+use crate::model::{ColorSource, Model, ModelPart, FIT_SIZE};
+use crate::background::BackgroundStyle;
 use crate::camera::Camera;
+use crate::config::Config;
+use crate::display::{DisplayMode, ShadingMode};
+use crate::edges::{self, EdgeFilter, EdgeInstance};
+use crate::lighting::{LightingRig, LightingUniforms};
 use crate::utils;
+use crate::selection::DepthImage;
+use crate::validation::MeshHighlight;
 
 pub struct Renderer<'window> {
     device: Device,
@@ -11,14 +20,127 @@ pub struct Renderer<'window> {
     surface: Surface<'window>,
     surface_config: SurfaceConfiguration,
     wireframe_pipeline: RenderPipeline,
+    hidden_edge_pipeline: RenderPipeline,
+    gizmo_pipeline: RenderPipeline,
+    depth_pipeline: RenderPipeline,
     flat_pipeline: RenderPipeline,
+    flat_offset_pipeline: RenderPipeline,
+    point_pipeline: RenderPipeline,
     image_pipeline: RenderPipeline,
+    overlay_line_pipeline: RenderPipeline,
+    overlay_triangle_pipeline: RenderPipeline,
+    overlay_point_pipeline: RenderPipeline,
+    gradient_pipeline: RenderPipeline,
+    blueprint_pipeline: RenderPipeline,
+    ground_pipeline: RenderPipeline,
+    uniform_bind_group_layout: BindGroupLayout,
     uniform_bind_group: BindGroup,
     uniform_buffer: Buffer,
+    /// Uniforms of parts shown away from their vertices, by part.
+    part_uniforms: Vec<(Buffer, BindGroup)>,
+    overlay_bind_group: BindGroup,
+    overlay_uniform_buffer: Buffer,
+    edge_bind_group: BindGroup,
+    edge_uniform_buffer: Buffer,
+    background_bind_group: BindGroup,
+    background_uniform_buffer: Buffer,
+    lighting_bind_group: BindGroup,
+    lighting_uniform_buffer: Buffer,
+    gizmo_bind_group: BindGroup,
+    gizmo_uniform_buffer: Buffer,
+    image_bind_group: BindGroup,
+    image_uniform_buffer: Buffer,
+    gizmo_edge_bind_group: BindGroup,
+    gizmo_edge_uniform_buffer: Buffer,
+    overlay_buffer: Option<Buffer>,
+    overlay_line_count: u32,
+    material_bind_group_layout: BindGroupLayout,
+    texture_bind_group_layout: BindGroupLayout,
+    default_texture_bind_group: BindGroup,
+    material_texture_bind_groups: Vec<Option<BindGroup>>,
+    mesh_cache: Option<GpuMesh>,
+    highlight_caches: Vec<GpuHighlight>,
+    frame_timer: FrameTimer,
     depth_texture: Texture,
     depth_texture_view: TextureView,
-    wireframe_mode: bool,
-    flat_shading: bool,
+    depth_readback: bool,
+    display_mode: DisplayMode,
+    shading_mode: ShadingMode,
+    model_transform: Mat4,
+}
+
+/// How a set of edges is depth-tested against the scene.
+#[derive(Clone, Copy, PartialEq)]
+enum EdgePass {
+    Visible,
+    Hidden,
+    Overlay,
+}
+
+const SURFACE_DEPTH_BIAS: DepthBiasState = DepthBiasState {
+    constant: 2,
+    slope_scale: 1.5,
+    clamp: 0.0,
+};
+
+#[derive(Debug, Clone, Copy)]
+pub struct FrameStats {
+    pub fps: f32,
+    pub frame_ms: f32,
+    pub render_ms: f32,
+}
+
+struct FrameTimer {
+    window_start: Instant,
+    frames: u32,
+    render_time: Duration,
+    stats: Option<FrameStats>,
+}
+
+impl FrameTimer {
+    fn new() -> Self {
+        Self {
+            window_start: Instant::now(),
+            frames: 0,
+            render_time: Duration::ZERO,
+            stats: None,
+        }
+    }
+
+    fn record(&mut self, render_time: Duration) {
+        self.frames += 1;
+        self.render_time += render_time;
+
+        let elapsed = self.window_start.elapsed();
+        if elapsed >= Duration::from_secs(1) {
+            let frames = self.frames as f32;
+            self.stats = Some(FrameStats {
+                fps: frames / elapsed.as_secs_f32(),
+                frame_ms: elapsed.as_secs_f32() * 1000.0 / frames,
+                render_ms: self.render_time.as_secs_f32() * 1000.0 / frames,
+            });
+            self.window_start = Instant::now();
+            self.frames = 0;
+            self.render_time = Duration::ZERO;
+        }
+    }
+}
+
+struct GpuMesh {
+    revision: u64,
+    vertex_buffer: Buffer,
+    index_buffer: Option<Buffer>,
+    parts: Vec<ModelPart>,
+    material_key: Option<(bool, [f32; 4])>,
+    material_bind_groups: Vec<BindGroup>,
+    edge_key: Option<(EdgeFilter, f32)>,
+    edge_buffer: Option<Buffer>,
+    edge_ranges: Vec<Range<u32>>,
+}
+
+struct GpuHighlight {
+    revision: u64,
+    buffers: Vec<(PrimitiveTopology, Buffer, u32)>,
 }
 
 #[repr(C)]
@@ -27,6 +149,35 @@ struct Uniforms {
     view_proj: [[f32; 4]; 4],
     model: [[f32; 4]; 4],
     color: [f32; 4],
+    normal_matrix: [[f32; 4]; 4],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct EdgeUniforms {
+    viewport: [f32; 2],
+    thickness: f32,
+    dash_length: f32,
+    color: [f32; 4],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct BackgroundUniforms {
+    view_proj: [[f32; 4]; 4],
+    inv_view_proj: [[f32; 4]; 4],
+    bottom_color: [f32; 4],
+    top_color: [f32; 4],
+    grid_color: [f32; 4],
+    viewport: [f32; 2],
+    grid_spacing: f32,
+    ground_height: f32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct MaterialUniforms {
+    diffuse: [f32; 4],
 }
 
 impl Uniforms {
@@ -35,6 +186,7 @@ impl Uniforms {
             view_proj: Mat4::IDENTITY.to_cols_array_2d(),
             model: Mat4::IDENTITY.to_cols_array_2d(),
             color: [0.0, 1.0, 0.0, 1.0],
+            normal_matrix: Mat4::IDENTITY.to_cols_array_2d(),
         }
     }
 }
@@ -67,14 +219,13 @@ impl<'window> Renderer<'window> {
                     })
                     .await
                     .ok_or_else(|| {
-                        format!(
-                            "Failed to find a suitable graphics adapter.\n\
-                            Please ensure you have:\n\
-                            - Vulkan drivers installed (Linux)\n\
-                            - DirectX 12 compatible GPU (Windows)\n\
-                            - Metal compatible GPU (macOS)\n\
-                            - Updated graphics drivers"
-                        )
+                        "Failed to find a suitable graphics adapter.\n\
+                        Please ensure you have:\n\
+                        - Vulkan drivers installed (Linux)\n\
+                        - DirectX 12 compatible GPU (Windows)\n\
+                        - Metal compatible GPU (macOS)\n\
+                        - Updated graphics drivers"
+                            .to_string()
                     })?
             }
         };
@@ -90,6 +241,10 @@ impl<'window> Renderer<'window> {
             )
             .await?;
 
+        let depth_readback = adapter
+            .get_downlevel_capabilities()
+            .flags
+            .contains(DownlevelFlags::DEPTH_TEXTURE_AND_BUFFER_COPIES);
         let surface_caps = surface.get_capabilities(&adapter);
         let surface_format = surface_caps
             .formats
@@ -146,16 +301,205 @@ impl<'window> Renderer<'window> {
             label: Some("uniform_bind_group"),
         });
 
+        let overlay_uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Overlay Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[Uniforms::new()]),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let overlay_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            layout: &uniform_bind_group_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: overlay_uniform_buffer.as_entire_binding(),
+            }],
+            label: Some("overlay_bind_group"),
+        });
+
+        let overlay_line_pipeline = Self::create_overlay_pipeline(
+            &device,
+            &surface_config,
+            &uniform_bind_group_layout,
+            PrimitiveTopology::LineList,
+        );
+        let overlay_triangle_pipeline = Self::create_overlay_pipeline(
+            &device,
+            &surface_config,
+            &uniform_bind_group_layout,
+            PrimitiveTopology::TriangleList,
+        );
+        let overlay_point_pipeline = Self::create_overlay_pipeline(
+            &device,
+            &surface_config,
+            &uniform_bind_group_layout,
+            PrimitiveTopology::PointList,
+        );
+
+        let edge_uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Edge Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[EdgeUniforms {
+                viewport: [size.width as f32, size.height as f32],
+                thickness: 1.0,
+                dash_length: 6.0,
+                color: [0.0, 1.0, 0.0, 1.0],
+            }]),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let edge_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+            label: Some("edge_bind_group_layout"),
+        });
+
+        let edge_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            layout: &edge_bind_group_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: edge_uniform_buffer.as_entire_binding(),
+            }],
+            label: Some("edge_bind_group"),
+        });
+
+        let background_uniform_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Background Uniform Buffer"),
+            size: std::mem::size_of::<BackgroundUniforms>() as u64,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let background_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            layout: &uniform_bind_group_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: background_uniform_buffer.as_entire_binding(),
+            }],
+            label: Some("background_bind_group"),
+        });
+
+        let gradient_pipeline = Self::create_background_pipeline(
+            &device,
+            &surface_config,
+            &uniform_bind_group_layout,
+            "fs_gradient",
+        );
+        let blueprint_pipeline = Self::create_background_pipeline(
+            &device,
+            &surface_config,
+            &uniform_bind_group_layout,
+            "fs_blueprint",
+        );
+        let ground_pipeline = Self::create_background_pipeline(
+            &device,
+            &surface_config,
+            &uniform_bind_group_layout,
+            "fs_ground",
+        );
+
         let wireframe_pipeline = Self::create_wireframe_pipeline(
             &device,
             &surface_config,
             &uniform_bind_group_layout,
+            &edge_bind_group_layout,
+            EdgePass::Visible,
         );
-        let flat_pipeline = Self::create_flat_pipeline(
+        let hidden_edge_pipeline = Self::create_wireframe_pipeline(
+            &device,
+            &surface_config,
+            &uniform_bind_group_layout,
+            &edge_bind_group_layout,
+            EdgePass::Hidden,
+        );
+        let gizmo_pipeline = Self::create_wireframe_pipeline(
+            &device,
+            &surface_config,
+            &uniform_bind_group_layout,
+            &edge_bind_group_layout,
+            EdgePass::Overlay,
+        );
+
+        let gizmo_uniform_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Gizmo Uniform Buffer"),
+            size: std::mem::size_of::<Uniforms>() as u64,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let gizmo_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            layout: &uniform_bind_group_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: gizmo_uniform_buffer.as_entire_binding(),
+            }],
+            label: Some("gizmo_bind_group"),
+        });
+
+        let image_uniform_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Image Uniform Buffer"),
+            size: std::mem::size_of::<Uniforms>() as u64,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let image_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            layout: &uniform_bind_group_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: image_uniform_buffer.as_entire_binding(),
+            }],
+            label: Some("image_bind_group"),
+        });
+
+        let gizmo_edge_uniform_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Gizmo Edge Uniform Buffer"),
+            size: std::mem::size_of::<EdgeUniforms>() as u64,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let gizmo_edge_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            layout: &edge_bind_group_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: gizmo_edge_uniform_buffer.as_entire_binding(),
+            }],
+            label: Some("gizmo_edge_bind_group"),
+        });
+        let depth_pipeline = Self::create_depth_pipeline(
+            &device,
+            &surface_config,
+            &uniform_bind_group_layout,
+        );
+        let point_pipeline = Self::create_point_pipeline(
             &device,
             &surface_config,
             &uniform_bind_group_layout,
         );
+        let material_bind_group_layout =
+            device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                entries: &[
+                    BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+                label: Some("material_bind_group_layout"),
+            });
+
         let texture_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
             entries: &[
                 BindGroupLayoutEntry {
@@ -178,6 +522,40 @@ impl<'window> Renderer<'window> {
             label: Some("texture_bind_group_layout"),
         });
 
+        let lighting_uniform_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Lighting Uniform Buffer"),
+            size: std::mem::size_of::<LightingUniforms>() as u64,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let lighting_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            layout: &uniform_bind_group_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: lighting_uniform_buffer.as_entire_binding(),
+            }],
+            label: Some("lighting_bind_group"),
+        });
+
+        let flat_pipeline = Self::create_flat_pipeline(
+            &device,
+            &surface_config,
+            &uniform_bind_group_layout,
+            &material_bind_group_layout,
+            &texture_bind_group_layout,
+            &uniform_bind_group_layout,
+            DepthBiasState::default(),
+        );
+        let flat_offset_pipeline = Self::create_flat_pipeline(
+            &device,
+            &surface_config,
+            &uniform_bind_group_layout,
+            &material_bind_group_layout,
+            &texture_bind_group_layout,
+            &uniform_bind_group_layout,
+            SURFACE_DEPTH_BIAS,
+        );
         let image_pipeline = Self::create_image_pipeline(
             &device,
             &surface_config,
@@ -185,23 +563,158 @@ impl<'window> Renderer<'window> {
             &texture_bind_group_layout,
         );
 
+        let default_texture_bind_group = Self::create_texture_bind_group(
+            &device,
+            &queue,
+            &texture_bind_group_layout,
+            1,
+            1,
+            &[255, 255, 255, 255],
+        );
+
         Ok(Self {
             device,
             queue,
             surface,
             surface_config,
             wireframe_pipeline,
+            hidden_edge_pipeline,
+            gizmo_pipeline,
+            depth_pipeline,
             flat_pipeline,
+            flat_offset_pipeline,
+            point_pipeline,
             image_pipeline,
+            overlay_line_pipeline,
+            overlay_triangle_pipeline,
+            overlay_point_pipeline,
+            gradient_pipeline,
+            blueprint_pipeline,
+            ground_pipeline,
+            uniform_bind_group_layout,
             uniform_bind_group,
             uniform_buffer,
+            part_uniforms: Vec::new(),
+            overlay_bind_group,
+            overlay_uniform_buffer,
+            edge_bind_group,
+            edge_uniform_buffer,
+            background_bind_group,
+            background_uniform_buffer,
+            lighting_bind_group,
+            lighting_uniform_buffer,
+            gizmo_bind_group,
+            gizmo_uniform_buffer,
+            image_bind_group,
+            image_uniform_buffer,
+            gizmo_edge_bind_group,
+            gizmo_edge_uniform_buffer,
+            overlay_buffer: None,
+            overlay_line_count: 0,
+            material_bind_group_layout,
+            texture_bind_group_layout,
+            default_texture_bind_group,
+            material_texture_bind_groups: Vec::new(),
+            mesh_cache: None,
+            highlight_caches: Vec::new(),
+            frame_timer: FrameTimer::new(),
             depth_texture,
             depth_texture_view,
-            wireframe_mode: false,
-            flat_shading: true,
+            depth_readback,
+            display_mode: DisplayMode::default(),
+            shading_mode: ShadingMode::default(),
+            model_transform: Mat4::IDENTITY,
+        })
+    }
+
+    fn create_texture_bind_group(
+        device: &Device,
+        queue: &Queue,
+        layout: &BindGroupLayout,
+        width: u32,
+        height: u32,
+        rgba: &[u8],
+    ) -> BindGroup {
+        let size = Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some("Material Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Rgba8UnormSrgb,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        queue.write_texture(
+            ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            rgba,
+            ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * width),
+                rows_per_image: Some(height),
+            },
+            size,
+        );
+
+        let texture_view = texture.create_view(&TextureViewDescriptor::default());
+
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            address_mode_u: AddressMode::Repeat,
+            address_mode_v: AddressMode::Repeat,
+            address_mode_w: AddressMode::Repeat,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            mipmap_filter: FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        device.create_bind_group(&BindGroupDescriptor {
+            layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&texture_view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&sampler),
+                },
+            ],
+            label: Some("material_texture_bind_group"),
         })
     }
 
+    pub fn upload_material_textures(&mut self, model: &Model) {
+        self.material_texture_bind_groups = model
+            .materials
+            .iter()
+            .map(|material| {
+                material.diffuse_texture.as_ref().map(|texture| {
+                    Self::create_texture_bind_group(
+                        &self.device,
+                        &self.queue,
+                        &self.texture_bind_group_layout,
+                        texture.width,
+                        texture.height,
+                        &texture.rgba,
+                    )
+                })
+            })
+            .collect();
+    }
+
     fn create_depth_texture(device: &Device, width: u32, height: u32) -> Texture {
         device.create_texture(&TextureDescriptor {
             size: Extent3d {
@@ -213,7 +726,7 @@ impl<'window> Renderer<'window> {
             sample_count: 1,
             dimension: TextureDimension::D2,
             format: TextureFormat::Depth32Float,
-            usage: TextureUsages::RENDER_ATTACHMENT,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
             label: Some("depth_texture"),
             view_formats: &[],
         })
@@ -223,15 +736,17 @@ impl<'window> Renderer<'window> {
         device: &Device,
         config: &SurfaceConfiguration,
         bind_group_layout: &BindGroupLayout,
+        edge_bind_group_layout: &BindGroupLayout,
+        pass: EdgePass,
     ) -> RenderPipeline {
         let shader = device.create_shader_module(ShaderModuleDescriptor {
             label: Some("Wireframe Shader"),
-            source: ShaderSource::Wgsl(include_str!("shaders/wireframe.wgsl").into()),
+            source: ShaderSource::Wgsl(include_str!("shaders/edges.wgsl").into()),
         });
 
         let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
             label: Some("Wireframe Pipeline Layout"),
-            bind_group_layouts: &[bind_group_layout],
+            bind_group_layouts: &[bind_group_layout, edge_bind_group_layout],
             push_constant_ranges: &[],
         });
 
@@ -242,8 +757,8 @@ impl<'window> Renderer<'window> {
                 module: &shader,
                 entry_point: "vs_main",
                 buffers: &[VertexBufferLayout {
-                    array_stride: std::mem::size_of::<crate::model::Vertex>() as u64,
-                    step_mode: VertexStepMode::Vertex,
+                    array_stride: std::mem::size_of::<EdgeInstance>() as u64,
+                    step_mode: VertexStepMode::Instance,
                     attributes: &[
                         VertexAttribute {
                             offset: 0,
@@ -255,12 +770,17 @@ impl<'window> Renderer<'window> {
                             shader_location: 1,
                             format: VertexFormat::Float32x3,
                         },
+                        VertexAttribute {
+                            offset: std::mem::size_of::<[f32; 6]>() as u64,
+                            shader_location: 2,
+                            format: VertexFormat::Float32x4,
+                        },
                     ],
                 }],
             },
             fragment: Some(FragmentState {
                 module: &shader,
-                entry_point: "fs_main",
+                entry_point: if pass == EdgePass::Hidden { "fs_dashed" } else { "fs_main" },
                 targets: &[Some(ColorTargetState {
                     format: config.format,
                     blend: Some(BlendState::REPLACE),
@@ -268,18 +788,22 @@ impl<'window> Renderer<'window> {
                 })],
             }),
             primitive: PrimitiveState {
-                topology: PrimitiveTopology::LineList,
+                topology: PrimitiveTopology::TriangleList,
                 strip_index_format: None,
                 front_face: FrontFace::Ccw,
-                cull_mode: Some(Face::Back),
-                polygon_mode: PolygonMode::Line,
+                cull_mode: None,
+                polygon_mode: PolygonMode::Fill,
                 unclipped_depth: false,
                 conservative: false,
             },
             depth_stencil: Some(DepthStencilState {
                 format: TextureFormat::Depth32Float,
-                depth_write_enabled: true,
-                depth_compare: CompareFunction::Less,
+                depth_write_enabled: pass == EdgePass::Visible,
+                depth_compare: match pass {
+                    EdgePass::Visible => CompareFunction::Less,
+                    EdgePass::Hidden => CompareFunction::Greater,
+                    EdgePass::Overlay => CompareFunction::Always,
+                },
                 stencil: StencilState::default(),
                 bias: DepthBiasState::default(),
             }),
@@ -292,24 +816,24 @@ impl<'window> Renderer<'window> {
         })
     }
 
-    fn create_flat_pipeline(
+    fn create_point_pipeline(
         device: &Device,
         config: &SurfaceConfiguration,
         bind_group_layout: &BindGroupLayout,
     ) -> RenderPipeline {
         let shader = device.create_shader_module(ShaderModuleDescriptor {
-            label: Some("Flat Shader"),
-            source: ShaderSource::Wgsl(include_str!("shaders/flat.wgsl").into()),
+            label: Some("Point Shader"),
+            source: ShaderSource::Wgsl(include_str!("shaders/wireframe.wgsl").into()),
         });
 
         let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-            label: Some("Flat Pipeline Layout"),
+            label: Some("Point Pipeline Layout"),
             bind_group_layouts: &[bind_group_layout],
             push_constant_ranges: &[],
         });
 
         device.create_render_pipeline(&RenderPipelineDescriptor {
-            label: Some("Flat Pipeline"),
+            label: Some("Point Pipeline"),
             layout: Some(&render_pipeline_layout),
             vertex: VertexState {
                 module: &shader,
@@ -328,6 +852,16 @@ impl<'window> Renderer<'window> {
                             shader_location: 1,
                             format: VertexFormat::Float32x3,
                         },
+                        VertexAttribute {
+                            offset: std::mem::size_of::<[f32; 6]>() as u64,
+                            shader_location: 2,
+                            format: VertexFormat::Float32x2,
+                        },
+                        VertexAttribute {
+                            offset: std::mem::size_of::<[f32; 8]>() as u64,
+                            shader_location: 3,
+                            format: VertexFormat::Float32x4,
+                        },
                     ],
                 }],
             },
@@ -341,10 +875,10 @@ impl<'window> Renderer<'window> {
                 })],
             }),
             primitive: PrimitiveState {
-                topology: PrimitiveTopology::TriangleList,
+                topology: PrimitiveTopology::PointList,
                 strip_index_format: None,
                 front_face: FrontFace::Ccw,
-                cull_mode: Some(Face::Back),
+                cull_mode: None,
                 polygon_mode: PolygonMode::Fill,
                 unclipped_depth: false,
                 conservative: false,
@@ -365,25 +899,38 @@ impl<'window> Renderer<'window> {
         })
     }
 
-    fn create_image_pipeline(
+    fn create_overlay_pipeline(
         device: &Device,
         config: &SurfaceConfiguration,
-        uniform_bind_group_layout: &BindGroupLayout,
-        texture_bind_group_layout: &BindGroupLayout,
+        bind_group_layout: &BindGroupLayout,
+        topology: PrimitiveTopology,
     ) -> RenderPipeline {
         let shader = device.create_shader_module(ShaderModuleDescriptor {
-            label: Some("Image Shader"),
-            source: ShaderSource::Wgsl(include_str!("shaders/image.wgsl").into()),
+            label: Some("Overlay Shader"),
+            source: ShaderSource::Wgsl(include_str!("shaders/wireframe.wgsl").into()),
         });
 
         let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-            label: Some("Image Pipeline Layout"),
-            bind_group_layouts: &[uniform_bind_group_layout, texture_bind_group_layout],
+            label: Some("Overlay Pipeline Layout"),
+            bind_group_layouts: &[bind_group_layout],
             push_constant_ranges: &[],
         });
 
+        let (depth_compare, bias) = if topology == PrimitiveTopology::TriangleList {
+            (
+                CompareFunction::LessEqual,
+                DepthBiasState {
+                    constant: -2,
+                    slope_scale: -1.0,
+                    clamp: 0.0,
+                },
+            )
+        } else {
+            (CompareFunction::Always, DepthBiasState::default())
+        };
+
         device.create_render_pipeline(&RenderPipelineDescriptor {
-            label: Some("Image Pipeline"),
+            label: Some("Overlay Pipeline"),
             layout: Some(&render_pipeline_layout),
             vertex: VertexState {
                 module: &shader,
@@ -402,6 +949,16 @@ impl<'window> Renderer<'window> {
                             shader_location: 1,
                             format: VertexFormat::Float32x3,
                         },
+                        VertexAttribute {
+                            offset: std::mem::size_of::<[f32; 6]>() as u64,
+                            shader_location: 2,
+                            format: VertexFormat::Float32x2,
+                        },
+                        VertexAttribute {
+                            offset: std::mem::size_of::<[f32; 8]>() as u64,
+                            shader_location: 3,
+                            format: VertexFormat::Float32x4,
+                        },
                     ],
                 }],
             },
@@ -415,7 +972,7 @@ impl<'window> Renderer<'window> {
                 })],
             }),
             primitive: PrimitiveState {
-                topology: PrimitiveTopology::TriangleList,
+                topology,
                 strip_index_format: None,
                 front_face: FrontFace::Ccw,
                 cull_mode: None,
@@ -425,10 +982,10 @@ impl<'window> Renderer<'window> {
             },
             depth_stencil: Some(DepthStencilState {
                 format: TextureFormat::Depth32Float,
-                depth_write_enabled: true,
-                depth_compare: CompareFunction::Less,
+                depth_write_enabled: false,
+                depth_compare,
                 stencil: StencilState::default(),
-                bias: DepthBiasState::default(),
+                bias,
             }),
             multisample: MultisampleState {
                 count: 1,
@@ -439,7 +996,328 @@ impl<'window> Renderer<'window> {
         })
     }
 
-    pub fn resize(&mut self, width: u32, height: u32) {
+    fn create_background_pipeline(
+        device: &Device,
+        config: &SurfaceConfiguration,
+        bind_group_layout: &BindGroupLayout,
+        entry_point: &str,
+    ) -> RenderPipeline {
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Background Shader"),
+            source: ShaderSource::Wgsl(include_str!("shaders/background.wgsl").into()),
+        });
+
+        let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Background Pipeline Layout"),
+            bind_group_layouts: &[bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        // The ground plane writes its own depth so the model occludes it;
+        // the screen backgrounds are drawn behind everything.
+        let ground = entry_point == "fs_ground";
+        device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Background Pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point,
+                targets: &[Some(ColorTargetState {
+                    format: config.format,
+                    blend: Some(if ground { BlendState::ALPHA_BLENDING } else { BlendState::REPLACE }),
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(DepthStencilState {
+                format: TextureFormat::Depth32Float,
+                depth_write_enabled: ground,
+                depth_compare: if ground { CompareFunction::Less } else { CompareFunction::Always },
+                stencil: StencilState::default(),
+                bias: DepthBiasState::default(),
+            }),
+            multisample: MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        })
+    }
+
+    fn create_depth_pipeline(
+        device: &Device,
+        config: &SurfaceConfiguration,
+        bind_group_layout: &BindGroupLayout,
+    ) -> RenderPipeline {
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Depth Shader"),
+            source: ShaderSource::Wgsl(include_str!("shaders/wireframe.wgsl").into()),
+        });
+
+        let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Depth Pipeline Layout"),
+            bind_group_layouts: &[bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Depth Pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[VertexBufferLayout {
+                    array_stride: std::mem::size_of::<crate::model::Vertex>() as u64,
+                    step_mode: VertexStepMode::Vertex,
+                    attributes: &[
+                        VertexAttribute {
+                            offset: 0,
+                            shader_location: 0,
+                            format: VertexFormat::Float32x3,
+                        },
+                        VertexAttribute {
+                            offset: std::mem::size_of::<[f32; 3]>() as u64,
+                            shader_location: 1,
+                            format: VertexFormat::Float32x3,
+                        },
+                        VertexAttribute {
+                            offset: std::mem::size_of::<[f32; 6]>() as u64,
+                            shader_location: 2,
+                            format: VertexFormat::Float32x2,
+                        },
+                        VertexAttribute {
+                            offset: std::mem::size_of::<[f32; 8]>() as u64,
+                            shader_location: 3,
+                            format: VertexFormat::Float32x4,
+                        },
+                    ],
+                }],
+            },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(ColorTargetState {
+                    format: config.format,
+                    blend: None,
+                    write_mask: ColorWrites::empty(),
+                })],
+            }),
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(DepthStencilState {
+                format: TextureFormat::Depth32Float,
+                depth_write_enabled: true,
+                depth_compare: CompareFunction::Less,
+                stencil: StencilState::default(),
+                bias: SURFACE_DEPTH_BIAS,
+            }),
+            multisample: MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        })
+    }
+
+    fn create_flat_pipeline(
+        device: &Device,
+        config: &SurfaceConfiguration,
+        bind_group_layout: &BindGroupLayout,
+        material_bind_group_layout: &BindGroupLayout,
+        texture_bind_group_layout: &BindGroupLayout,
+        lighting_bind_group_layout: &BindGroupLayout,
+        bias: DepthBiasState,
+    ) -> RenderPipeline {
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Flat Shader"),
+            source: ShaderSource::Wgsl(include_str!("shaders/flat.wgsl").into()),
+        });
+
+        let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Flat Pipeline Layout"),
+            bind_group_layouts: &[
+                bind_group_layout,
+                material_bind_group_layout,
+                texture_bind_group_layout,
+                lighting_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+
+        device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Flat Pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[VertexBufferLayout {
+                    array_stride: std::mem::size_of::<crate::model::Vertex>() as u64,
+                    step_mode: VertexStepMode::Vertex,
+                    attributes: &[
+                        VertexAttribute {
+                            offset: 0,
+                            shader_location: 0,
+                            format: VertexFormat::Float32x3,
+                        },
+                        VertexAttribute {
+                            offset: std::mem::size_of::<[f32; 3]>() as u64,
+                            shader_location: 1,
+                            format: VertexFormat::Float32x3,
+                        },
+                        VertexAttribute {
+                            offset: std::mem::size_of::<[f32; 6]>() as u64,
+                            shader_location: 2,
+                            format: VertexFormat::Float32x2,
+                        },
+                        VertexAttribute {
+                            offset: std::mem::size_of::<[f32; 8]>() as u64,
+                            shader_location: 3,
+                            format: VertexFormat::Float32x4,
+                        },
+                    ],
+                }],
+            },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(ColorTargetState {
+                    format: config.format,
+                    blend: Some(BlendState::REPLACE),
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: FrontFace::Ccw,
+                cull_mode: Some(Face::Back),
+                polygon_mode: PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(DepthStencilState {
+                format: TextureFormat::Depth32Float,
+                depth_write_enabled: true,
+                depth_compare: CompareFunction::Less,
+                stencil: StencilState::default(),
+                bias,
+            }),
+            multisample: MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        })
+    }
+
+    fn create_image_pipeline(
+        device: &Device,
+        config: &SurfaceConfiguration,
+        uniform_bind_group_layout: &BindGroupLayout,
+        texture_bind_group_layout: &BindGroupLayout,
+    ) -> RenderPipeline {
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Image Shader"),
+            source: ShaderSource::Wgsl(include_str!("shaders/image.wgsl").into()),
+        });
+
+        let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Image Pipeline Layout"),
+            bind_group_layouts: &[uniform_bind_group_layout, texture_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Image Pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[VertexBufferLayout {
+                    array_stride: std::mem::size_of::<crate::model::Vertex>() as u64,
+                    step_mode: VertexStepMode::Vertex,
+                    attributes: &[
+                        VertexAttribute {
+                            offset: 0,
+                            shader_location: 0,
+                            format: VertexFormat::Float32x3,
+                        },
+                        VertexAttribute {
+                            offset: std::mem::size_of::<[f32; 3]>() as u64,
+                            shader_location: 1,
+                            format: VertexFormat::Float32x3,
+                        },
+                        VertexAttribute {
+                            offset: std::mem::size_of::<[f32; 6]>() as u64,
+                            shader_location: 2,
+                            format: VertexFormat::Float32x2,
+                        },
+                        VertexAttribute {
+                            offset: std::mem::size_of::<[f32; 8]>() as u64,
+                            shader_location: 3,
+                            format: VertexFormat::Float32x4,
+                        },
+                    ],
+                }],
+            },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(ColorTargetState {
+                    format: config.format,
+                    blend: Some(BlendState::REPLACE),
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(DepthStencilState {
+                format: TextureFormat::Depth32Float,
+                depth_write_enabled: true,
+                depth_compare: CompareFunction::Less,
+                stencil: StencilState::default(),
+                bias: DepthBiasState::default(),
+            }),
+            multisample: MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        })
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
         if width > 0 && height > 0 {
             self.surface_config.width = width;
             self.surface_config.height = height;
@@ -449,31 +1327,56 @@ impl<'window> Renderer<'window> {
         }
     }
 
-    pub fn toggle_wireframe(&mut self) {
-        self.wireframe_mode = !self.wireframe_mode;
+    pub fn display_mode(&self) -> DisplayMode {
+        self.display_mode
+    }
+
+    pub fn set_display_mode(&mut self, mode: DisplayMode) {
+        self.display_mode = mode;
     }
 
-    pub fn toggle_flat_shading(&mut self) {
-        self.flat_shading = !self.flat_shading;
+    pub fn shading_mode(&self) -> ShadingMode {
+        self.shading_mode
     }
 
-    pub fn is_wireframe(&self) -> bool {
-        self.wireframe_mode
+    pub fn set_shading_mode(&mut self, mode: ShadingMode) {
+        self.shading_mode = mode;
     }
 
-    pub fn is_flat_shading(&self) -> bool {
-        self.flat_shading
+    pub fn set_model_transform(&mut self, transform: Mat4) {
+        self.model_transform = transform;
+    }
+
+    pub fn set_overlay_lines(&mut self, lines: &[EdgeInstance]) {
+        self.overlay_line_count = lines.len() as u32;
+        if lines.is_empty() {
+            return;
+        }
+        let size = std::mem::size_of_val(lines) as u64;
+        if self.overlay_buffer.as_ref().map_or(0, |buffer| buffer.size()) < size {
+            self.overlay_buffer = Some(self.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Gizmo Buffer"),
+                size: size.next_power_of_two(),
+                usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }));
+        }
+        if let Some(buffer) = &self.overlay_buffer {
+            self.queue.write_buffer(buffer, 0, bytemuck::cast_slice(lines));
+        }
     }
 
     pub fn render(
         &mut self,
         camera: &Camera,
         model: Option<&Model>,
+        model_texture: Option<&BindGroup>,
         image_plane: Option<(&Buffer, &Buffer, u32, &BindGroup)>,
-        wireframe_color: u32,
-        flat_color: u32,
+        highlights: &[&MeshHighlight],
+        config: &Config,
     ) -> Result<(), SurfaceError> {
         let output = self.surface.get_current_texture()?;
+        let started = Instant::now();
         let view = output.texture.create_view(&TextureViewDescriptor::default());
 
         let mut encoder = self
@@ -482,22 +1385,113 @@ impl<'window> Renderer<'window> {
                 label: Some("Render Encoder"),
             });
 
-        let model_buffers = if let Some(model) = model {
-            let view_proj = camera.get_projection_matrix() * camera.get_view_matrix();
-            let model_matrix = Mat4::IDENTITY;
+        let background = BackgroundStyle::from_name(&config.background_style).unwrap_or_default();
+        // Background colors are given as they should appear on screen, so undo
+        // the encoding an sRGB surface applies on write.
+        let background_rgba = |color: u32| {
+            let rgba = utils::color_to_rgba(color);
+            if self.surface_config.format.is_srgb() {
+                utils::srgb_to_linear(rgba)
+            } else {
+                rgba
+            }
+        };
+        let clear_color = background_rgba(config.background_color);
+        let view_matrix = camera.get_view_matrix();
+        let view_proj = camera.get_projection_matrix() * view_matrix;
+        let model_matrix = model
+            .map(|model| model.fit_transform(FIT_SIZE) * self.model_transform)
+            .unwrap_or(Mat4::IDENTITY);
+        let ground_height = match model {
+            Some(model) if background == BackgroundStyle::Ground => {
+                let (min, max) = model.bounds();
+                (0..8)
+                    .map(|corner| {
+                        let pick = |bit: usize| (corner >> bit) & 1 == 1;
+                        let point = Vec3::select(glam::BVec3::new(pick(0), pick(1), pick(2)), max, min);
+                        model_matrix.transform_point3(point).y
+                    })
+                    .fold(f32::MAX, f32::min)
+            }
+            _ => 0.0,
+        };
+        self.queue.write_buffer(
+            &self.background_uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[BackgroundUniforms {
+                view_proj: view_proj.to_cols_array_2d(),
+                inv_view_proj: view_proj.inverse().to_cols_array_2d(),
+                bottom_color: clear_color,
+                top_color: background_rgba(config.background_top_color),
+                grid_color: background_rgba(config.grid_color),
+                viewport: [self.surface_config.width as f32, self.surface_config.height as f32],
+                grid_spacing: config.grid_spacing.max(1e-3),
+                ground_height,
+            }]),
+        );
+
+        let default_rig = LightingRig::default();
+        let rig = config
+            .lighting_rigs
+            .iter()
+            .find(|rig| rig.name == config.lighting_rig)
+            .unwrap_or(&default_rig);
+        self.queue.write_buffer(
+            &self.lighting_uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[LightingUniforms::new(rig, view_matrix, config.headlight, self.shading_mode)]),
+        );
 
-            let color = if self.wireframe_mode {
-                utils::color_to_rgba(wireframe_color)
-            } else if self.flat_shading {
-                utils::color_to_rgba(flat_color)
+        if image_plane.is_some() {
+            self.queue.write_buffer(
+                &self.image_uniform_buffer,
+                0,
+                bytemuck::cast_slice(&[Uniforms {
+                    view_proj: view_proj.to_cols_array_2d(),
+                    color: [1.0, 1.0, 1.0, 1.0],
+                    ..Uniforms::new()
+                }]),
+            );
+        }
+
+        if self.overlay_line_count > 0 {
+            self.queue.write_buffer(
+                &self.gizmo_uniform_buffer,
+                0,
+                bytemuck::cast_slice(&[Uniforms {
+                    view_proj: view_proj.to_cols_array_2d(),
+                    ..Uniforms::new()
+                }]),
+            );
+            self.queue.write_buffer(
+                &self.gizmo_edge_uniform_buffer,
+                0,
+                bytemuck::cast_slice(&[EdgeUniforms {
+                    viewport: [self.surface_config.width as f32, self.surface_config.height as f32],
+                    thickness: 3.0,
+                    dash_length: 0.0,
+                    color: [1.0, 1.0, 1.0, 1.0],
+                }]),
+            );
+        }
+
+        if let Some(model) = model {
+            let normal_matrix = Mat4::from_mat3(Mat3::from_mat4(model_matrix).inverse().transpose());
+
+            let (color, edge_color) = if model.color_source != ColorSource::Uniform {
+                ([1.0, 1.0, 1.0, 1.0], [1.0, 1.0, 1.0, 1.0])
             } else {
-                utils::color_to_rgba(wireframe_color)
+                (
+                    utils::color_to_rgba(config.flat_color),
+                    utils::color_to_rgba(config.wireframe_color),
+                )
             };
 
             let uniforms = Uniforms {
                 view_proj: view_proj.to_cols_array_2d(),
                 model: model_matrix.to_cols_array_2d(),
                 color,
+                normal_matrix: normal_matrix.to_cols_array_2d(),
             };
 
             self.queue.write_buffer(
@@ -505,23 +1499,40 @@ impl<'window> Renderer<'window> {
                 0,
                 bytemuck::cast_slice(&[uniforms]),
             );
+            self.update_part_uniforms(model, uniforms, model_matrix);
+            self.queue.write_buffer(
+                &self.overlay_uniform_buffer,
+                0,
+                bytemuck::cast_slice(&[Uniforms {
+                    color: [1.0, 1.0, 1.0, 1.0],
+                    ..uniforms
+                }]),
+            );
 
-            Some((
-                self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                    label: Some("Vertex Buffer"),
-                    contents: bytemuck::cast_slice(&model.vertices),
-                    usage: BufferUsages::VERTEX,
-                }),
-                self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                    label: Some("Index Buffer"),
-                    contents: bytemuck::cast_slice(&model.indices),
-                    usage: BufferUsages::INDEX,
-                }),
-                model.indices.len() as u32,
-            ))
+            self.queue.write_buffer(
+                &self.edge_uniform_buffer,
+                0,
+                bytemuck::cast_slice(&[EdgeUniforms {
+                    viewport: [self.surface_config.width as f32, self.surface_config.height as f32],
+                    thickness: config.wireframe_thickness.max(1.0),
+                    dash_length: 6.0,
+                    color: edge_color,
+                }]),
+            );
+
+            self.upload_model(model);
+            self.update_material_bind_groups(model, model_texture.is_some(), color);
+            if self.display_mode.shows_edges() {
+                let filter = EdgeFilter::from_name(&config.wireframe_edges).unwrap_or(EdgeFilter::All);
+                self.update_edges(model, filter, config.feature_angle);
+            }
+        }
+
+        if model.is_some() {
+            self.upload_highlights(highlights);
         } else {
-            None
-        };
+            self.highlight_caches.clear();
+        }
 
         {
             let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
@@ -530,7 +1541,12 @@ impl<'window> Renderer<'window> {
                     view: &view,
                     resolve_target: None,
                     ops: Operations {
-                        load: LoadOp::Clear(Color::BLACK),
+                        load: LoadOp::Clear(Color {
+                            r: clear_color[0] as f64,
+                            g: clear_color[1] as f64,
+                            b: clear_color[2] as f64,
+                            a: clear_color[3] as f64,
+                        }),
                         store: StoreOp::Store,
                     },
                 })],
@@ -546,49 +1562,315 @@ impl<'window> Renderer<'window> {
                 timestamp_writes: None,
             });
 
-            if let Some((ref vertex_buffer, ref index_buffer, index_count)) = model_buffers {
-                render_pass.set_pipeline(if self.wireframe_mode {
-                    &self.wireframe_pipeline
-                } else {
-                    &self.flat_pipeline
-                });
-
-                render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
-                render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
-                render_pass.set_index_buffer(index_buffer.slice(..), IndexFormat::Uint32);
-                render_pass.draw_indexed(0..index_count, 0, 0..1);
+            let screen_pipeline = match background {
+                BackgroundStyle::Gradient => Some(&self.gradient_pipeline),
+                BackgroundStyle::Blueprint => Some(&self.blueprint_pipeline),
+                BackgroundStyle::Solid | BackgroundStyle::Ground => None,
+            };
+            if let Some(pipeline) = screen_pipeline {
+                render_pass.set_pipeline(pipeline);
+                render_pass.set_bind_group(0, &self.background_bind_group, &[]);
+                render_pass.draw(0..3, 0..1);
             }
 
-            if let Some((vertex_buf, index_buf, index_count, texture_bind_group)) = image_plane {
-                let view_proj = camera.get_projection_matrix() * camera.get_view_matrix();
-                let model_matrix = Mat4::IDENTITY;
-                let uniforms = Uniforms {
-                    view_proj: view_proj.to_cols_array_2d(),
-                    model: model_matrix.to_cols_array_2d(),
-                    color: [1.0, 1.0, 1.0, 1.0],
+            if let (Some(model), Some(mesh)) = (model, self.mesh_cache.as_ref()) {
+                let part_bind_group = |part: usize| match self.part_uniforms.get(part) {
+                    Some((_, bind_group)) if model.part_display_transform(part) != Mat4::IDENTITY => bind_group,
+                    _ => &self.uniform_bind_group,
                 };
+                if let Some(index_buffer) = &mesh.index_buffer {
+                    render_pass.set_index_buffer(index_buffer.slice(..), IndexFormat::Uint32);
+                }
+                render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
 
-                self.queue.write_buffer(
-                    &self.uniform_buffer,
-                    0,
-                    bytemuck::cast_slice(&[uniforms]),
-                );
+                let surface_pipeline = match self.display_mode {
+                    DisplayMode::HiddenLine => Some(&self.depth_pipeline),
+                    DisplayMode::ShadedWireframe => Some(&self.flat_offset_pipeline),
+                    DisplayMode::Shaded => Some(&self.flat_pipeline),
+                    DisplayMode::Wireframe => None,
+                };
+                let visible_parts = || {
+                    mesh.parts
+                        .iter()
+                        .zip(&mesh.material_bind_groups)
+                        .enumerate()
+                        .filter(|(_, (part, _))| part.visible)
+                };
+
+                for (index, (part, material_bind_group)) in visible_parts() {
+                    render_pass.set_bind_group(0, part_bind_group(index), &[]);
+                    if part.index_count == 0 {
+                        render_pass.set_pipeline(&self.point_pipeline);
+                        render_pass.draw(part.vertex_offset..part.vertex_offset + part.vertex_count, 0..1);
+                        continue;
+                    }
+
+                    let Some(surface_pipeline) = surface_pipeline else {
+                        continue;
+                    };
+                    render_pass.set_pipeline(surface_pipeline);
+                    if self.display_mode != DisplayMode::HiddenLine {
+                        let texture_bind_group = model_texture
+                            .or_else(|| {
+                                part.material_id
+                                    .and_then(|id| self.material_texture_bind_groups.get(id))
+                                    .and_then(Option::as_ref)
+                            })
+                            .unwrap_or(&self.default_texture_bind_group);
+                        render_pass.set_bind_group(1, material_bind_group, &[]);
+                        render_pass.set_bind_group(2, texture_bind_group, &[]);
+                        render_pass.set_bind_group(3, &self.lighting_bind_group, &[]);
+                    }
+                    render_pass.draw_indexed(part.index_range(), 0, 0..1);
+                }
 
+                if let Some(edge_buffer) = mesh.edge_buffer.as_ref().filter(|_| self.display_mode.shows_edges()) {
+                    let mut edge_pipelines = vec![&self.wireframe_pipeline];
+                    if self.display_mode == DisplayMode::HiddenLine && config.hidden_line_dashed {
+                        edge_pipelines.push(&self.hidden_edge_pipeline);
+                    }
+                    render_pass.set_bind_group(1, &self.edge_bind_group, &[]);
+                    render_pass.set_vertex_buffer(0, edge_buffer.slice(..));
+                    for pipeline in edge_pipelines {
+                        render_pass.set_pipeline(pipeline);
+                        for (index, _) in visible_parts() {
+                            if let Some(range) = mesh.edge_ranges.get(index) {
+                                render_pass.set_bind_group(0, part_bind_group(index), &[]);
+                                render_pass.draw(0..6, range.clone());
+                            }
+                        }
+                    }
+                }
+            }
+
+            if background == BackgroundStyle::Ground {
+                render_pass.set_pipeline(&self.ground_pipeline);
+                render_pass.set_bind_group(0, &self.background_bind_group, &[]);
+                render_pass.draw(0..3, 0..1);
+            }
+
+            for highlight in &self.highlight_caches {
+                render_pass.set_bind_group(0, &self.overlay_bind_group, &[]);
+                for (topology, buffer, count) in &highlight.buffers {
+                    let pipeline = match topology {
+                        PrimitiveTopology::TriangleList => &self.overlay_triangle_pipeline,
+                        PrimitiveTopology::LineList => &self.overlay_line_pipeline,
+                        _ => &self.overlay_point_pipeline,
+                    };
+                    render_pass.set_pipeline(pipeline);
+                    render_pass.set_vertex_buffer(0, buffer.slice(..));
+                    render_pass.draw(0..*count, 0..1);
+                }
+            }
+
+            if let Some((vertex_buf, index_buf, index_count, texture_bind_group)) = image_plane {
                 render_pass.set_pipeline(&self.image_pipeline);
-                render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+                render_pass.set_bind_group(0, &self.image_bind_group, &[]);
                 render_pass.set_bind_group(1, texture_bind_group, &[]);
                 render_pass.set_vertex_buffer(0, vertex_buf.slice(..));
                 render_pass.set_index_buffer(index_buf.slice(..), IndexFormat::Uint32);
                 render_pass.draw_indexed(0..index_count, 0, 0..1);
             }
+
+            if let (Some(buffer), 1..) = (&self.overlay_buffer, self.overlay_line_count) {
+                render_pass.set_pipeline(&self.gizmo_pipeline);
+                render_pass.set_bind_group(0, &self.gizmo_bind_group, &[]);
+                render_pass.set_bind_group(1, &self.gizmo_edge_bind_group, &[]);
+                render_pass.set_vertex_buffer(0, buffer.slice(..));
+                render_pass.draw(0..6, 0..self.overlay_line_count);
+            }
         }
 
         self.queue.submit(std::iter::once(encoder.finish()));
+        self.frame_timer.record(started.elapsed());
         output.present();
 
         Ok(())
     }
 
+    pub fn upload_model(&mut self, model: &Model) {
+        if self.mesh_cache.as_ref().is_some_and(|mesh| mesh.revision == model.revision) {
+            if let Some(mesh) = self.mesh_cache.as_mut() {
+                for (cached, part) in mesh.parts.iter_mut().zip(&model.parts) {
+                    cached.visible = part.visible;
+                }
+            }
+            return;
+        }
+
+        let vertex_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(&model.vertices),
+            usage: BufferUsages::VERTEX,
+        });
+        let index_buffer = (!model.indices.is_empty()).then(|| {
+            self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Index Buffer"),
+                contents: bytemuck::cast_slice(&model.indices),
+                usage: BufferUsages::INDEX,
+            })
+        });
+
+        self.mesh_cache = Some(GpuMesh {
+            revision: model.revision,
+            vertex_buffer,
+            index_buffer,
+            parts: model.parts.clone(),
+            material_key: None,
+            material_bind_groups: Vec::new(),
+            edge_key: None,
+            edge_buffer: None,
+            edge_ranges: Vec::new(),
+        });
+    }
+
+    fn update_part_uniforms(&mut self, model: &Model, uniforms: Uniforms, model_matrix: Mat4) {
+        for part in 0..model.parts.len() {
+            let display = model.part_display_transform(part);
+            if display == Mat4::IDENTITY {
+                continue;
+            }
+            while self.part_uniforms.len() <= part {
+                let buffer = self.device.create_buffer(&BufferDescriptor {
+                    label: Some("Part Uniform Buffer"),
+                    size: std::mem::size_of::<Uniforms>() as u64,
+                    usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                });
+                let bind_group = self.device.create_bind_group(&BindGroupDescriptor {
+                    layout: &self.uniform_bind_group_layout,
+                    entries: &[BindGroupEntry {
+                        binding: 0,
+                        resource: buffer.as_entire_binding(),
+                    }],
+                    label: Some("part_uniform_bind_group"),
+                });
+                self.part_uniforms.push((buffer, bind_group));
+            }
+            let matrix = model_matrix * display;
+            let part_uniforms = Uniforms {
+                model: matrix.to_cols_array_2d(),
+                normal_matrix: Mat4::from_mat3(Mat3::from_mat4(matrix).inverse().transpose()).to_cols_array_2d(),
+                ..uniforms
+            };
+            self.queue.write_buffer(&self.part_uniforms[part].0, 0, bytemuck::cast_slice(&[part_uniforms]));
+        }
+    }
+
+    fn update_material_bind_groups(&mut self, model: &Model, textured: bool, color: [f32; 4]) {
+        let Some(mesh) = self.mesh_cache.as_ref() else {
+            return;
+        };
+        if mesh.material_key == Some((textured, color)) {
+            return;
+        }
+
+        let material_bind_groups = model
+            .parts
+            .iter()
+            .map(|part| {
+                let diffuse = if textured {
+                    [1.0, 1.0, 1.0, 1.0]
+                } else {
+                    model
+                        .part_material(part)
+                        .map(|m| m.diffuse_rgba())
+                        .unwrap_or(color)
+                };
+                self.create_material_bind_group(diffuse)
+            })
+            .collect();
+
+        if let Some(mesh) = self.mesh_cache.as_mut() {
+            mesh.material_key = Some((textured, color));
+            mesh.material_bind_groups = material_bind_groups;
+        }
+    }
+
+    fn update_edges(&mut self, model: &Model, filter: EdgeFilter, feature_angle: f32) {
+        let Some(mesh) = self.mesh_cache.as_ref() else {
+            return;
+        };
+        if mesh.edge_key == Some((filter, feature_angle)) {
+            return;
+        }
+
+        let edges = edges::extract(model, filter, feature_angle);
+        let edge_buffer = (!edges.edges.is_empty()).then(|| {
+            self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Edge Buffer"),
+                contents: bytemuck::cast_slice(&edges.edges),
+                usage: BufferUsages::VERTEX,
+            })
+        });
+
+        if let Some(mesh) = self.mesh_cache.as_mut() {
+            mesh.edge_key = Some((filter, feature_angle));
+            mesh.edge_buffer = edge_buffer;
+            mesh.edge_ranges = edges.ranges;
+        }
+    }
+
+    fn upload_highlights(&mut self, highlights: &[&MeshHighlight]) {
+        self.highlight_caches.truncate(highlights.len());
+        for (index, highlight) in highlights.iter().enumerate() {
+            if self.highlight_caches.get(index).is_some_and(|cache| cache.revision == highlight.revision) {
+                continue;
+            }
+            let cache = self.upload_highlight(highlight);
+            if index < self.highlight_caches.len() {
+                self.highlight_caches[index] = cache;
+            } else {
+                self.highlight_caches.push(cache);
+            }
+        }
+    }
+
+    fn upload_highlight(&self, highlight: &MeshHighlight) -> GpuHighlight {
+        let buffers = [
+            (PrimitiveTopology::TriangleList, &highlight.triangles),
+            (PrimitiveTopology::LineList, &highlight.lines),
+            (PrimitiveTopology::PointList, &highlight.points),
+        ]
+        .into_iter()
+        .filter(|(_, vertices)| !vertices.is_empty())
+        .map(|(topology, vertices)| {
+            let buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Highlight Vertex Buffer"),
+                contents: bytemuck::cast_slice(vertices),
+                usage: BufferUsages::VERTEX,
+            });
+            (topology, buffer, vertices.len() as u32)
+        })
+        .collect();
+
+        GpuHighlight {
+            revision: highlight.revision,
+            buffers,
+        }
+    }
+
+    fn create_material_bind_group(&self, diffuse: [f32; 4]) -> BindGroup {
+        let buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Material Buffer"),
+            contents: bytemuck::cast_slice(&[MaterialUniforms { diffuse }]),
+            usage: BufferUsages::UNIFORM,
+        });
+
+        self.device.create_bind_group(&BindGroupDescriptor {
+            layout: &self.material_bind_group_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+            label: Some("material_bind_group"),
+        })
+    }
+
+    pub fn frame_stats(&self) -> Option<FrameStats> {
+        self.frame_timer.stats
+    }
+
     pub fn device(&self) -> &Device {
         &self.device
     }
@@ -600,4 +1882,64 @@ impl<'window> Renderer<'window> {
     pub fn size(&self) -> (u32, u32) {
         (self.surface_config.width, self.surface_config.height)
     }
+
+    /// Reads back the depth buffer of the last rendered frame. Blocks until
+    /// the GPU is done, so it is only meant for one-off queries. Returns
+    /// `None` on devices that cannot copy depth textures.
+    pub fn read_depth(&self) -> Option<DepthImage> {
+        if !self.depth_readback {
+            return None;
+        }
+        let (width, height) = self.size();
+        let row_bytes = width * 4;
+        let padded_row_bytes = row_bytes.div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT) * COPY_BYTES_PER_ROW_ALIGNMENT;
+        let buffer = self.device.create_buffer(&BufferDescriptor {
+            label: Some("Depth Readback Buffer"),
+            size: (padded_row_bytes * height) as u64,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = self.device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Depth Readback Encoder"),
+        });
+        encoder.copy_texture_to_buffer(
+            ImageCopyTexture {
+                texture: &self.depth_texture,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::DepthOnly,
+            },
+            ImageCopyBuffer {
+                buffer: &buffer,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row_bytes),
+                    rows_per_image: Some(height),
+                },
+            },
+            Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device.poll(Maintain::Wait);
+        receiver.recv().ok()?.ok()?;
+
+        let data = slice.get_mapped_range();
+        let mut depths = Vec::with_capacity((width * height) as usize);
+        for row in data.chunks(padded_row_bytes as usize) {
+            depths.extend_from_slice(bytemuck::cast_slice(&row[..row_bytes as usize]));
+        }
+        drop(data);
+        buffer.unmap();
+        Some(DepthImage { width, height, depths })
+    }
 }
\ No newline at end of file
@@ -1,9 +1,20 @@
-use glam::Vec3;
+use glam::{Vec2, Vec3};
+use std::collections::HashSet;
+use crate::model::{next_revision, Model};
+use crate::topology::MeshTopology;
+use crate::validation::MeshHighlight;
 
+const SELECTED_COLOR: [f32; 4] = [0.2, 0.8, 1.0, 1.0];
+
+/// Selected vertices are source vertices (see `Model::source_vertex`), so
+/// they survive changes of the normal mode; faces index `Model::indices`
+/// and objects `Model::parts`.
 #[derive(Debug, Clone)]
 pub struct Selection {
-    pub selected_vertices: Vec<usize>,
-    pub selected_faces: Vec<usize>,
+    pub selected_vertices: HashSet<usize>,
+    pub selected_faces: HashSet<usize>,
+    pub selected_objects: HashSet<usize>,
+    pub active_object: Option<usize>,
     pub mode: SelectionMode,
 }
 
@@ -15,11 +26,200 @@ pub enum SelectionMode {
     Object,
 }
 
+impl SelectionMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "none" => Some(SelectionMode::None),
+            "vertex" => Some(SelectionMode::Vertex),
+            "face" => Some(SelectionMode::Face),
+            "object" => Some(SelectionMode::Object),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SelectionMode::None => "none",
+            SelectionMode::Vertex => "vertex",
+            SelectionMode::Face => "face",
+            SelectionMode::Object => "object",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            SelectionMode::Vertex => SelectionMode::Face,
+            SelectionMode::Face => SelectionMode::Object,
+            _ => SelectionMode::Vertex,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SelectOp {
+    Replace,
+    Add,
+    Subtract,
+    Toggle,
+}
+
+impl SelectOp {
+    pub fn from_modifiers(control: bool, alt: bool) -> Self {
+        match (control, alt) {
+            (false, false) => SelectOp::Replace,
+            (true, false) => SelectOp::Add,
+            (false, true) => SelectOp::Subtract,
+            (true, true) => SelectOp::Toggle,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SelectionShape {
+    Box,
+    Lasso,
+}
+
+impl SelectionShape {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "box" => Some(SelectionShape::Box),
+            "lasso" => Some(SelectionShape::Lasso),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SelectionShape::Box => "box",
+            SelectionShape::Lasso => "lasso",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            SelectionShape::Box => SelectionShape::Lasso,
+            SelectionShape::Lasso => SelectionShape::Box,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SelectionRegion {
+    pub shape: SelectionShape,
+    pub points: Vec<Vec2>,
+}
+
+impl SelectionRegion {
+    pub fn new(shape: SelectionShape, start: Vec2) -> Self {
+        Self {
+            shape,
+            points: vec![start],
+        }
+    }
+
+    pub fn extend(&mut self, cursor: Vec2) {
+        match self.shape {
+            SelectionShape::Box => {
+                self.points.truncate(1);
+                self.points.push(cursor);
+            }
+            SelectionShape::Lasso => {
+                match self.points.last() {
+                    Some(last) if last.distance(cursor) < 2.0 => {}
+                    _ => self.points.push(cursor),
+                }
+            }
+        }
+    }
+
+    fn bounds(&self) -> (Vec2, Vec2) {
+        self.points
+            .iter()
+            .fold((Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)), |(min, max), p| (min.min(*p), max.max(*p)))
+    }
+
+    pub fn is_click(&self) -> bool {
+        let (min, max) = self.bounds();
+        (max - min).max_element() < 4.0
+    }
+
+    pub fn outline(&self) -> Vec<Vec2> {
+        let mut outline = match self.shape {
+            SelectionShape::Box => {
+                let (min, max) = self.bounds();
+                vec![min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)]
+            }
+            SelectionShape::Lasso => self.points.clone(),
+        };
+        if let Some(&first) = outline.first() {
+            outline.push(first);
+        }
+        outline
+    }
+
+    pub fn contains(&self, point: Vec2) -> bool {
+        let (min, max) = self.bounds();
+        if point.cmplt(min).any() || point.cmpgt(max).any() {
+            return false;
+        }
+        if self.shape == SelectionShape::Box {
+            return true;
+        }
+        // Even-odd rule against the closed lasso polygon.
+        let mut inside = false;
+        let mut previous = self.points[self.points.len() - 1];
+        for &current in &self.points {
+            if (current.y > point.y) != (previous.y > point.y)
+                && point.x < (previous.x - current.x) * (point.y - current.y) / (previous.y - current.y) + current.x
+            {
+                inside = !inside;
+            }
+            previous = current;
+        }
+        inside
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DepthImage {
+    pub width: u32,
+    pub height: u32,
+    pub depths: Vec<f32>,
+}
+
+impl DepthImage {
+    /// Farthest depth around a pixel, so elements on silhouettes and edges
+    /// are not hidden by their own neighbours.
+    pub fn sample(&self, pixel: Vec2) -> Option<f32> {
+        let (x, y) = (pixel.x.floor() as i64, pixel.y.floor() as i64);
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return None;
+        }
+        let mut depth = f32::MIN;
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let (sx, sy) = ((x + dx).clamp(0, self.width as i64 - 1), (y + dy).clamp(0, self.height as i64 - 1));
+                depth = depth.max(self.depths[(sy * self.width as i64 + sx) as usize]);
+            }
+        }
+        Some(depth)
+    }
+}
+
+impl Default for Selection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Selection {
     pub fn new() -> Self {
         Self {
-            selected_vertices: Vec::new(),
-            selected_faces: Vec::new(),
+            selected_vertices: HashSet::new(),
+            selected_faces: HashSet::new(),
+            selected_objects: HashSet::new(),
+            active_object: None,
             mode: SelectionMode::None,
         }
     }
@@ -27,68 +227,492 @@ impl Selection {
     pub fn clear(&mut self) {
         self.selected_vertices.clear();
         self.selected_faces.clear();
+        self.selected_objects.clear();
+        self.active_object = None;
         self.mode = SelectionMode::None;
     }
 
     pub fn select_vertex(&mut self, index: usize) {
-        if !self.selected_vertices.contains(&index) {
-            self.selected_vertices.push(index);
-        }
+        self.selected_vertices.insert(index);
         self.mode = SelectionMode::Vertex;
     }
 
     pub fn select_face(&mut self, index: usize) {
-        if !self.selected_faces.contains(&index) {
-            self.selected_faces.push(index);
-        }
+        self.selected_faces.insert(index);
         self.mode = SelectionMode::Face;
     }
 
     pub fn deselect_vertex(&mut self, index: usize) {
-        self.selected_vertices.retain(|&i| i != index);
-        if self.selected_vertices.is_empty() && self.selected_faces.is_empty() {
-            self.mode = SelectionMode::None;
-        }
+        self.selected_vertices.remove(&index);
+        self.update_mode();
     }
 
     pub fn deselect_face(&mut self, index: usize) {
-        self.selected_faces.retain(|&i| i != index);
-        if self.selected_vertices.is_empty() && self.selected_faces.is_empty() {
-            self.mode = SelectionMode::None;
+        self.selected_faces.remove(&index);
+        self.update_mode();
+    }
+
+    pub fn apply_vertices(&mut self, op: SelectOp, vertices: impl IntoIterator<Item = usize>) {
+        if op == SelectOp::Replace || self.mode == SelectionMode::Object {
+            self.clear();
+        }
+        apply(&mut self.selected_vertices, op, vertices);
+        if op != SelectOp::Subtract && !self.selected_vertices.is_empty() {
+            self.mode = SelectionMode::Vertex;
+        }
+        self.update_mode();
+    }
+
+    pub fn apply_faces(&mut self, op: SelectOp, faces: impl IntoIterator<Item = usize>) {
+        if op == SelectOp::Replace || self.mode == SelectionMode::Object {
+            self.clear();
+        }
+        apply(&mut self.selected_faces, op, faces);
+        if op != SelectOp::Subtract && !self.selected_faces.is_empty() {
+            self.mode = SelectionMode::Face;
+        }
+        self.update_mode();
+    }
+
+    pub fn apply_objects(&mut self, op: SelectOp, objects: impl IntoIterator<Item = usize>) {
+        if op == SelectOp::Replace || self.mode != SelectionMode::Object {
+            self.clear();
         }
+        let objects: Vec<usize> = objects.into_iter().collect();
+        apply(&mut self.selected_objects, op, objects.iter().copied());
+        self.active_object = objects
+            .iter()
+            .rev()
+            .chain(self.active_object.iter())
+            .find(|object| self.selected_objects.contains(object))
+            .or_else(|| self.selected_objects.iter().min())
+            .copied();
+        self.mode = if self.selected_objects.is_empty() { SelectionMode::None } else { SelectionMode::Object };
+    }
+
+    fn update_mode(&mut self) {
+        if self.mode == SelectionMode::Object && !self.selected_objects.is_empty() {
+            return;
+        }
+        self.mode = match (self.selected_vertices.is_empty(), self.selected_faces.is_empty()) {
+            (true, true) => SelectionMode::None,
+            (false, true) => SelectionMode::Vertex,
+            (true, false) => SelectionMode::Face,
+            (false, false) => self.mode,
+        };
     }
 
     pub fn is_selected(&self) -> bool {
-        !self.selected_vertices.is_empty() || !self.selected_faces.is_empty()
-    }
-
-    pub fn ray_intersect_vertex(
-        &self,
-        ray_origin: Vec3,
-        ray_dir: Vec3,
-        vertices: &[(f32, f32, f32)],
-        threshold: f32,
-    ) -> Option<usize> {
-        let mut closest_dist = f32::MAX;
-        let mut closest_idx = None;
-
-        for (i, vertex) in vertices.iter().enumerate() {
-            let v = Vec3::new(vertex.0, vertex.1, vertex.2);
-            let to_vertex = v - ray_origin;
-            let projection = to_vertex.dot(ray_dir);
-            
-            if projection > 0.0 {
-                let point_on_ray = ray_origin + ray_dir * projection;
-                let dist = (point_on_ray - v).length();
-                
-                if dist < threshold && dist < closest_dist {
-                    closest_dist = dist;
-                    closest_idx = Some(i);
+        !self.selected_vertices.is_empty() || !self.selected_faces.is_empty() || !self.selected_objects.is_empty()
+    }
+
+    fn welded_vertices(&self, topology: &MeshTopology) -> HashSet<u32> {
+        self.selected_vertices
+            .iter()
+            .filter_map(|&source| topology.welded_of_source(source))
+            .collect()
+    }
+
+    fn set_welded_vertices(&mut self, topology: &MeshTopology, vertices: impl IntoIterator<Item = u32>) {
+        let sources: Vec<usize> = vertices
+            .into_iter()
+            .flat_map(|vertex| topology.sources(vertex).iter().copied())
+            .collect();
+        self.selected_vertices.clear();
+        self.apply_vertices(SelectOp::Add, sources);
+    }
+
+    pub fn mesh_vertices(&self, topology: &MeshTopology, model: &Model) -> HashSet<usize> {
+        let welded: HashSet<u32> = match self.mode {
+            SelectionMode::Vertex => self.welded_vertices(topology),
+            SelectionMode::Face => self
+                .selected_faces
+                .iter()
+                .filter_map(|&face| topology.faces.get(face))
+                .flatten()
+                .copied()
+                .collect(),
+            SelectionMode::Object => {
+                return self
+                    .mesh_faces(topology, model, false)
+                    .into_iter()
+                    .flat_map(|face| model.indices[face * 3..face * 3 + 3].iter())
+                    .map(|&index| model.source_vertex(index as usize))
+                    .collect();
+            }
+            SelectionMode::None => HashSet::new(),
+        };
+        welded
+            .into_iter()
+            .flat_map(|vertex| topology.sources(vertex).iter().copied())
+            .collect()
+    }
+
+    pub fn mesh_faces(&self, topology: &MeshTopology, model: &Model, all_corners: bool) -> Vec<usize> {
+        let mut faces: Vec<usize> = match self.mode {
+            SelectionMode::Face => self.selected_faces.iter().copied().filter(|&face| face < topology.faces.len()).collect(),
+            SelectionMode::Object => self
+                .selected_objects
+                .iter()
+                .filter_map(|&object| model.parts.get(object))
+                .flat_map(|part| {
+                    let range = part.index_range();
+                    range.start as usize / 3..range.end as usize / 3
+                })
+                .collect(),
+            SelectionMode::Vertex => {
+                let selected = self.welded_vertices(topology);
+                (0..topology.faces.len())
+                    .filter(|&face| {
+                        let mut corners = topology.faces[face].iter();
+                        if all_corners {
+                            corners.all(|corner| selected.contains(corner))
+                        } else {
+                            corners.any(|corner| selected.contains(corner))
+                        }
+                    })
+                    .collect()
+            }
+            SelectionMode::None => Vec::new(),
+        };
+        faces.sort_unstable();
+        faces
+    }
+
+    /// Adds the ring of vertices or faces adjacent to the selection. Faces
+    /// are adjacent when they share a vertex.
+    pub fn grow(&mut self, topology: &MeshTopology) {
+        match self.mode {
+            SelectionMode::Vertex => {
+                let selected = self.welded_vertices(topology);
+                let grown: HashSet<u32> = selected
+                    .iter()
+                    .flat_map(|&vertex| topology.vertex_neighbors(vertex))
+                    .chain(selected.iter().copied())
+                    .collect();
+                self.set_welded_vertices(topology, grown);
+            }
+            SelectionMode::Face => {
+                let grown: Vec<usize> = self
+                    .selected_faces
+                    .iter()
+                    .filter_map(|&face| topology.faces.get(face))
+                    .flatten()
+                    .flat_map(|&vertex| topology.vertex_faces(vertex).iter().map(|&face| face as usize))
+                    .collect();
+                self.apply_faces(SelectOp::Add, grown);
+            }
+            _ => {}
+        }
+    }
+
+    pub fn shrink(&mut self, topology: &MeshTopology) {
+        match self.mode {
+            SelectionMode::Vertex => {
+                let selected = self.welded_vertices(topology);
+                let kept: Vec<u32> = selected
+                    .iter()
+                    .copied()
+                    .filter(|&vertex| topology.vertex_neighbors(vertex).all(|other| selected.contains(&other)))
+                    .collect();
+                self.set_welded_vertices(topology, kept);
+            }
+            SelectionMode::Face => {
+                let edge: Vec<usize> = self
+                    .selected_faces
+                    .iter()
+                    .copied()
+                    .filter(|&face| {
+                        topology.faces.get(face).is_some_and(|corners| {
+                            corners.iter().any(|&vertex| {
+                                topology
+                                    .vertex_faces(vertex)
+                                    .iter()
+                                    .any(|&other| !self.selected_faces.contains(&(other as usize)))
+                            })
+                        })
+                    })
+                    .collect();
+                self.apply_faces(SelectOp::Subtract, edge);
+            }
+            _ => {}
+        }
+    }
+
+    pub fn select_linked(&mut self, topology: &MeshTopology) {
+        match self.mode {
+            SelectionMode::Vertex => {
+                let mut linked = self.welded_vertices(topology);
+                let mut stack: Vec<u32> = linked.iter().copied().collect();
+                while let Some(vertex) = stack.pop() {
+                    for other in topology.vertex_neighbors(vertex) {
+                        if linked.insert(other) {
+                            stack.push(other);
+                        }
+                    }
                 }
+                self.set_welded_vertices(topology, linked);
             }
+            SelectionMode::Face => {
+                let mut linked = self.selected_faces.clone();
+                let mut stack: Vec<usize> = linked.iter().copied().collect();
+                while let Some(face) = stack.pop() {
+                    let Some(corners) = topology.faces.get(face) else {
+                        continue;
+                    };
+                    for &vertex in corners {
+                        for &other in topology.vertex_faces(vertex) {
+                            if linked.insert(other as usize) {
+                                stack.push(other as usize);
+                            }
+                        }
+                    }
+                }
+                self.apply_faces(SelectOp::Add, linked);
+            }
+            _ => {}
         }
+    }
 
-        closest_idx
+    pub fn select_by_normal(&mut self, topology: &MeshTopology, candidates: impl IntoIterator<Item = usize>, max_angle: f32) {
+        let reference: Vec3 = match self.mode {
+            SelectionMode::Vertex => self
+                .welded_vertices(topology)
+                .iter()
+                .flat_map(|&vertex| topology.vertex_faces(vertex))
+                .map(|&face| topology.face_normals[face as usize])
+                .sum(),
+            SelectionMode::Face => self.selected_faces.iter().filter_map(|&face| topology.face_normals.get(face)).sum(),
+            _ => return,
+        };
+        let Some(reference) = reference.try_normalize() else {
+            return;
+        };
+        let min_cos = max_angle.clamp(0.0, 180.0).to_radians().cos();
+        let matching: Vec<usize> = candidates
+            .into_iter()
+            .filter(|&face| {
+                let normal = topology.face_normals[face];
+                normal != Vec3::ZERO && normal.dot(reference) >= min_cos
+            })
+            .collect();
+        match self.mode {
+            SelectionMode::Vertex => {
+                let mut vertices = self.welded_vertices(topology);
+                vertices.extend(matching.iter().flat_map(|&face| topology.faces[face]));
+                self.set_welded_vertices(topology, vertices);
+            }
+            _ => self.apply_faces(SelectOp::Add, matching),
+        }
+    }
+
+    pub fn select_boundary(&mut self, topology: &MeshTopology) {
+        let selected = &self.selected_faces;
+        let region = self.mode == SelectionMode::Face && !selected.is_empty();
+        let mut vertices = HashSet::new();
+        for (face, ids) in topology.faces.iter().enumerate() {
+            if region && !selected.contains(&face) {
+                continue;
+            }
+            for k in 0..3 {
+                let half_edge = face * 3 + k;
+                let border = if region {
+                    !topology.twins[half_edge].is_some_and(|twin| selected.contains(&(twin as usize / 3)))
+                } else {
+                    topology.is_boundary(half_edge)
+                };
+                if border {
+                    vertices.insert(ids[k]);
+                    vertices.insert(ids[(k + 1) % 3]);
+                }
+            }
+        }
+        self.clear();
+        self.set_welded_vertices(topology, vertices);
+    }
+
+    pub fn invert(
+        &mut self,
+        mode: SelectionMode,
+        vertices: impl IntoIterator<Item = usize>,
+        faces: impl IntoIterator<Item = usize>,
+        objects: impl IntoIterator<Item = usize>,
+    ) {
+        match mode {
+            SelectionMode::Vertex => {
+                let inverted: Vec<usize> = vertices
+                    .into_iter()
+                    .filter(|vertex| !self.selected_vertices.contains(vertex))
+                    .collect();
+                self.apply_vertices(SelectOp::Replace, inverted);
+            }
+            SelectionMode::Face => {
+                let inverted: Vec<usize> = faces
+                    .into_iter()
+                    .filter(|face| !self.selected_faces.contains(face))
+                    .collect();
+                self.apply_faces(SelectOp::Replace, inverted);
+            }
+            SelectionMode::Object => {
+                let inverted: Vec<usize> = objects
+                    .into_iter()
+                    .filter(|object| !self.selected_objects.contains(object))
+                    .collect();
+                self.apply_objects(SelectOp::Replace, inverted);
+            }
+            _ => {}
+        }
     }
 }
 
+fn apply(set: &mut HashSet<usize>, op: SelectOp, items: impl IntoIterator<Item = usize>) {
+    for item in items {
+        match op {
+            SelectOp::Replace | SelectOp::Add => {
+                set.insert(item);
+            }
+            SelectOp::Subtract => {
+                set.remove(&item);
+            }
+            SelectOp::Toggle => {
+                if !set.remove(&item) {
+                    set.insert(item);
+                }
+            }
+        }
+    }
+}
+
+pub fn highlight(model: &Model, selection: &Selection) -> MeshHighlight {
+    let mut result = MeshHighlight {
+        revision: next_revision(),
+        ..Default::default()
+    };
+    let colored = |index: u32| {
+        let mut vertex = model.vertices[index as usize];
+        vertex.color = SELECTED_COLOR;
+        vertex
+    };
+
+    for &face in &selection.selected_faces {
+        if let Some(tri) = model.indices.get(face * 3..face * 3 + 3) {
+            result.triangles.extend(tri.iter().map(|&index| colored(index)));
+        }
+    }
+
+    if !selection.selected_vertices.is_empty() {
+        let selected = |index: u32| selection.selected_vertices.contains(&model.source_vertex(index as usize));
+        let mut edges = HashSet::new();
+        for tri in model.indices.chunks_exact(3) {
+            for k in 0..3 {
+                let (a, b) = (tri[k], tri[(k + 1) % 3]);
+                let (source_a, source_b) = (model.source_vertex(a as usize), model.source_vertex(b as usize));
+                if selected(a) && selected(b) && edges.insert((source_a.min(source_b), source_a.max(source_b))) {
+                    result.lines.push(colored(a));
+                    result.lines.push(colored(b));
+                }
+            }
+        }
+
+        let mut shown = HashSet::new();
+        for index in 0..model.vertices.len() {
+            let source = model.source_vertex(index);
+            if selection.selected_vertices.contains(&source) && shown.insert(source) {
+                result.points.push(colored(index as u32));
+            }
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_meshes::{cube, cube_vertices, CUBE_INDICES};
+
+    fn faces(faces: &[usize]) -> Selection {
+        let mut selection = Selection::new();
+        selection.apply_faces(SelectOp::Replace, faces.iter().copied());
+        selection
+    }
+
+    fn sorted(set: &HashSet<usize>) -> Vec<usize> {
+        let mut items: Vec<usize> = set.iter().copied().collect();
+        items.sort_unstable();
+        items
+    }
+
+    #[test]
+    fn grows_and_shrinks_faces() {
+        let topology = MeshTopology::new(&cube());
+        let mut selection = faces(&[0]);
+        selection.grow(&topology);
+        // Every face touching corner 0, 2 or 3.
+        assert_eq!(sorted(&selection.selected_faces), vec![0, 1, 4, 5, 6, 7, 8, 9, 10]);
+        selection.shrink(&topology);
+        assert_eq!(sorted(&selection.selected_faces), vec![0]);
+    }
+
+    #[test]
+    fn grows_vertices_along_edges() {
+        let model = cube();
+        let topology = MeshTopology::new(&model);
+        let mut selection = Selection::new();
+        selection.apply_vertices(SelectOp::Replace, [0]);
+        selection.grow(&topology);
+        assert_eq!(sorted(&selection.mesh_vertices(&topology, &model)), vec![0, 1, 2, 3, 4, 5, 6]);
+        selection.shrink(&topology);
+        assert_eq!(sorted(&selection.mesh_vertices(&topology, &model)), vec![0]);
+    }
+
+    #[test]
+    fn selects_linked_faces() {
+        let mut vertices = cube_vertices();
+        vertices.extend(cube_vertices().into_iter().map(|mut v| {
+            v.position[0] += 3.0;
+            v
+        }));
+        let indices: Vec<u32> = CUBE_INDICES.iter().chain(CUBE_INDICES.iter()).enumerate()
+            .map(|(i, &index)| if i < 36 { index } else { index + 8 })
+            .collect();
+        let model = Model::from_mesh(vertices, indices, Vec::new());
+        let topology = MeshTopology::new(&model);
+
+        let mut selection = faces(&[14]);
+        selection.select_linked(&topology);
+        assert_eq!(sorted(&selection.selected_faces), (12..24).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn ignores_faces_missing_from_the_topology() {
+        let model = cube();
+        let topology = MeshTopology::new(&model);
+        let mut selection = faces(&[0, 99]);
+        selection.grow(&topology);
+        selection.shrink(&topology);
+        selection.select_linked(&topology);
+        selection.select_by_normal(&topology, 0..12, 10.0);
+        assert!(selection.selected_faces.contains(&99));
+        assert_eq!(selection.mesh_faces(&topology, &model, false).len(), 12);
+    }
+
+    #[test]
+    fn selects_the_border_of_selected_faces() {
+        let model = cube();
+        let topology = MeshTopology::new(&model);
+        let mut selection = faces(&[0, 1]);
+        selection.select_boundary(&topology);
+        assert_eq!(selection.mode, SelectionMode::Vertex);
+        assert_eq!(sorted(&selection.mesh_vertices(&topology, &model)), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn box_regions_contain_their_inside() {
+        let mut region = SelectionRegion::new(SelectionShape::Box, Vec2::new(10.0, 10.0));
+        region.extend(Vec2::new(50.0, 30.0));
+        assert!(!region.is_click());
+        assert!(region.contains(Vec2::new(20.0, 20.0)));
+        assert!(!region.contains(Vec2::new(60.0, 20.0)));
+    }
+}
@@ -0,0 +1,100 @@
+struct BackgroundUniforms {
+    view_proj: mat4x4<f32>,
+    inv_view_proj: mat4x4<f32>,
+    bottom_color: vec4<f32>,
+    top_color: vec4<f32>,
+    grid_color: vec4<f32>,
+    viewport: vec2<f32>,
+    grid_spacing: f32,
+    ground_height: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> background: BackgroundUniforms;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) ndc: vec2<f32>,
+}
+
+// A single triangle covering the whole viewport.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let ndc = vec2<f32>(f32((index << 1u) & 2u) * 2.0 - 1.0, f32(index & 2u) * 2.0 - 1.0);
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(ndc, 1.0, 1.0);
+    out.ndc = ndc;
+    return out;
+}
+
+@fragment
+fn fs_gradient(in: VertexOutput) -> @location(0) vec4<f32> {
+    return mix(background.bottom_color, background.top_color, in.ndc.y * 0.5 + 0.5);
+}
+
+fn grid_line(pixel: vec2<f32>, spacing: f32) -> bool {
+    let offset = pixel - floor(pixel / spacing) * spacing;
+    return offset.x < 1.0 || offset.y < 1.0;
+}
+
+@fragment
+fn fs_blueprint(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Minor lines every 16 px, major lines every 80 px, anchored at the viewport center.
+    let pixel = in.clip_position.xy - background.viewport * 0.5;
+    if grid_line(pixel, 80.0) {
+        return background.grid_color;
+    }
+    if grid_line(pixel, 16.0) {
+        return mix(background.bottom_color, background.grid_color, 0.4);
+    }
+    return background.bottom_color;
+}
+
+struct GroundOutput {
+    @location(0) color: vec4<f32>,
+    @builtin(frag_depth) depth: f32,
+}
+
+fn unproject(ndc: vec3<f32>) -> vec3<f32> {
+    let world = background.inv_view_proj * vec4<f32>(ndc, 1.0);
+    return world.xyz / world.w;
+}
+
+@fragment
+fn fs_ground(in: VertexOutput) -> GroundOutput {
+    let near = unproject(vec3<f32>(in.ndc, 0.0));
+    let far = unproject(vec3<f32>(in.ndc, 1.0));
+    let ray = far - near;
+    let t = (background.ground_height - near.y) / ray.y;
+    let hit = near + ray * t;
+
+    // Derivatives have to be taken before any fragment is discarded.
+    let coord = hit.xz / background.grid_spacing;
+    let width = max(fwidth(coord), vec2<f32>(1e-6));
+    let grid = abs(fract(coord - 0.5) - 0.5) / width;
+    let line = 1.0 - min(min(grid.x, grid.y), 1.0);
+    let axis = abs(hit.xz) / (width * background.grid_spacing);
+
+    if t <= 0.0 || t > 1.0 {
+        discard;
+    }
+
+    var color = vec4<f32>(background.grid_color.rgb, line * 0.6);
+    if axis.y < 1.5 {
+        color = vec4<f32>(1.0, 0.2, 0.2, 1.0);
+    } else if axis.x < 1.5 {
+        color = vec4<f32>(0.2, 0.4, 1.0, 1.0);
+    }
+
+    let distance = length(hit.xz - near.xz);
+    color.a = color.a * (1.0 - smoothstep(background.grid_spacing * 20.0, background.grid_spacing * 80.0, distance));
+    if color.a <= 0.0 {
+        discard;
+    }
+
+    let clip = background.view_proj * vec4<f32>(hit, 1.0);
+    var out: GroundOutput;
+    out.color = color;
+    out.depth = clip.z / clip.w;
+    return out;
+}
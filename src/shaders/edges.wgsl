@@ -0,0 +1,89 @@
+struct Uniforms {
+    view_proj: mat4x4<f32>,
+    model: mat4x4<f32>,
+    color: vec4<f32>,
+}
+
+struct EdgeUniforms {
+    viewport: vec2<f32>,
+    thickness: f32,
+    dash_length: f32,
+    color: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> uniforms: Uniforms;
+
+@group(1) @binding(0)
+var<uniform> edge: EdgeUniforms;
+
+struct EdgeInput {
+    @location(0) start: vec3<f32>,
+    @location(1) end: vec3<f32>,
+    @location(2) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) distance: vec2<f32>,
+}
+
+const NEAR_W: f32 = 0.0001;
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+    instance: EdgeInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+    let transform = uniforms.view_proj * uniforms.model;
+    var a = transform * vec4<f32>(instance.start, 1.0);
+    var b = transform * vec4<f32>(instance.end, 1.0);
+
+    if (a.w < NEAR_W && b.w < NEAR_W) {
+        out.clip_position = vec4<f32>(0.0, 0.0, 2.0, 1.0);
+        out.color = vec4<f32>(0.0);
+        out.distance = vec2<f32>(0.0, 1.0);
+        return out;
+    }
+    if (a.w < NEAR_W) {
+        a = mix(a, b, (NEAR_W - a.w) / (b.w - a.w));
+    }
+    if (b.w < NEAR_W) {
+        b = mix(b, a, (NEAR_W - b.w) / (a.w - b.w));
+    }
+
+    let screen_a = a.xy / a.w * edge.viewport * 0.5;
+    let screen_b = b.xy / b.w * edge.viewport * 0.5;
+    var direction = screen_b - screen_a;
+    if (length(direction) < 0.0001) {
+        direction = vec2<f32>(1.0, 0.0);
+    }
+    let normal = normalize(vec2<f32>(-direction.y, direction.x));
+
+    let corner = vertex_index % 6u;
+    let use_end = corner == 1u || corner == 2u || corner == 4u;
+    let side = select(-1.0, 1.0, corner == 2u || corner == 3u || corner == 4u);
+
+    let base = select(a, b, use_end);
+    let offset = normal * side * edge.thickness * 0.5 / edge.viewport * 2.0 * base.w;
+    out.clip_position = vec4<f32>(base.xy + offset, base.zw);
+    out.color = instance.color;
+    out.distance = vec2<f32>(select(0.0, length(screen_b - screen_a), use_end) * base.w, base.w);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return edge.color * in.color;
+}
+
+@fragment
+fn fs_dashed(in: VertexOutput) -> @location(0) vec4<f32> {
+    let distance = in.distance.x / in.distance.y;
+    if (fract(distance / (2.0 * edge.dash_length)) > 0.5) {
+        discard;
+    }
+    return edge.color * in.color * vec4<f32>(0.5, 0.5, 0.5, 1.0);
+}
@@ -2,19 +2,59 @@ struct Uniforms {
     view_proj: mat4x4<f32>,
     model: mat4x4<f32>,
     color: vec4<f32>,
+    normal_matrix: mat4x4<f32>,
+}
+
+struct Material {
+    diffuse: vec4<f32>,
 }
 
 @group(0) @binding(0)
 var<uniform> uniforms: Uniforms;
 
+@group(1) @binding(0)
+var<uniform> material: Material;
+
+@group(2) @binding(0)
+var diffuse_texture: texture_2d<f32>;
+
+@group(2) @binding(1)
+var diffuse_sampler: sampler;
+
+struct Light {
+    // xyz: direction towards the light, or position for point lights; w: point light range
+    position: vec4<f32>,
+    // rgb: color * intensity; a: 1 for point lights
+    color: vec4<f32>,
+}
+
+struct Lighting {
+    eye: vec4<f32>,
+    ambient: f32,
+    specular: f32,
+    shininess: f32,
+    light_count: u32,
+    // 0: faceted, 1: smooth, 2: unlit
+    shading: u32,
+    lights: array<Light, 8>,
+}
+
+@group(3) @binding(0)
+var<uniform> lighting: Lighting;
+
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+    @location(3) color: vec4<f32>,
 }
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) normal: vec3<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) color: vec4<f32>,
+    @location(3) world_position: vec3<f32>,
 }
 
 @vertex
@@ -24,15 +64,57 @@ fn vs_main(
     var out: VertexOutput;
     let model_matrix = uniforms.model;
     let view_proj = uniforms.view_proj;
-    out.clip_position = view_proj * model_matrix * vec4<f32>(model.position, 1.0);
-    out.normal = normalize((model_matrix * vec4<f32>(model.normal, 0.0)).xyz);
+    let world_position = model_matrix * vec4<f32>(model.position, 1.0);
+    out.clip_position = view_proj * world_position;
+    out.world_position = world_position.xyz;
+    out.normal = normalize((uniforms.normal_matrix * vec4<f32>(model.normal, 0.0)).xyz);
+    out.uv = model.uv;
+    out.color = model.color;
     return out;
 }
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    let light_dir = normalize(vec3<f32>(0.5, 1.0, 0.7));
-    let ndotl = max(dot(in.normal, light_dir), 0.3);
-    return uniforms.color * vec4<f32>(ndotl, ndotl, ndotl, 1.0);
-}
+    let base_color = material.diffuse * in.color * textureSample(diffuse_texture, diffuse_sampler, in.uv);
+    // Face normals come from screen-space derivatives, which must be taken
+    // before any non-uniform branching.
+    let face_normal = cross(dpdx(in.world_position), dpdy(in.world_position));
+    if lighting.shading == 2u {
+        return base_color;
+    }
 
+    let view_dir = normalize(lighting.eye.xyz - in.world_position);
+    var normal = normalize(in.normal);
+    if lighting.shading == 0u {
+        normal = normalize(face_normal);
+    }
+    // Faces are drawn from both sides; light the side facing the viewer.
+    if dot(normal, view_dir) < 0.0 {
+        normal = -normal;
+    }
+
+    var diffuse = vec3<f32>(lighting.ambient);
+    var specular = vec3<f32>(0.0);
+    for (var i = 0u; i < min(lighting.light_count, 8u); i = i + 1u) {
+        let light = lighting.lights[i];
+        var light_dir = light.position.xyz;
+        var attenuation = 1.0;
+        if light.color.a > 0.5 {
+            let to_light = light.position.xyz - in.world_position;
+            light_dir = normalize(to_light);
+            if light.position.w > 0.0 {
+                let falloff = clamp(1.0 - dot(to_light, to_light) / (light.position.w * light.position.w), 0.0, 1.0);
+                attenuation = falloff * falloff;
+            }
+        }
+        let ndotl = max(dot(normal, light_dir), 0.0);
+        diffuse = diffuse + light.color.rgb * ndotl * attenuation;
+        if ndotl > 0.0 {
+            let halfway = normalize(light_dir + view_dir);
+            let highlight = pow(max(dot(normal, halfway), 0.0), lighting.shininess);
+            specular = specular + light.color.rgb * highlight * lighting.specular * attenuation;
+        }
+    }
+
+    return vec4<f32>(base_color.rgb * diffuse + specular, base_color.a);
+}
@@ -16,6 +16,8 @@ var texture_sampler: sampler;
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+    @location(3) color: vec4<f32>,
 }
 
 struct VertexOutput {
@@ -31,7 +33,7 @@ fn vs_main(
     let model_matrix = uniforms.model;
     let view_proj = uniforms.view_proj;
     out.clip_position = view_proj * model_matrix * vec4<f32>(model.position, 1.0);
-    out.uv = vec2<f32>((model.position.x + 1.0) * 0.5, 1.0 - (model.position.y + 1.0) * 0.5);
+    out.uv = model.uv;
     return out;
 }
 
@@ -10,10 +10,13 @@ var<uniform> uniforms: Uniforms;
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+    @location(3) color: vec4<f32>,
 }
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
 }
 
 @vertex
@@ -24,11 +27,12 @@ fn vs_main(
     let model_matrix = uniforms.model;
     let view_proj = uniforms.view_proj;
     out.clip_position = view_proj * model_matrix * vec4<f32>(model.position, 1.0);
+    out.color = model.color;
     return out;
 }
 
 @fragment
-fn fs_main() -> @location(0) vec4<f32> {
-    return uniforms.color;
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return uniforms.color * in.color;
 }
 
@@ -0,0 +1,271 @@
+use glam::Vec3;
+use std::collections::HashMap;
+use crate::model::Vertex;
+
+const HEADER_SIZE: usize = 80;
+const TRIANGLE_SIZE: usize = 50;
+
+pub struct StlTriangle {
+    pub normal: Vec3,
+    pub vertices: [Vec3; 3],
+}
+
+pub struct StlMesh {
+    pub name: String,
+    pub triangles: Vec<StlTriangle>,
+}
+
+pub fn is_binary(data: &[u8]) -> bool {
+    if data.len() < HEADER_SIZE + 4 {
+        return false;
+    }
+
+    let count = u32::from_le_bytes([data[80], data[81], data[82], data[83]]) as usize;
+    let expected = HEADER_SIZE + 4 + count.saturating_mul(TRIANGLE_SIZE);
+
+    if expected == data.len() {
+        return true;
+    }
+
+    let starts_with_solid = data
+        .iter()
+        .skip_while(|b| b.is_ascii_whitespace())
+        .take(5)
+        .copied()
+        .collect::<Vec<u8>>()
+        .eq_ignore_ascii_case(b"solid");
+
+    !starts_with_solid
+}
+
+pub fn parse(data: &[u8]) -> Result<StlMesh, Box<dyn std::error::Error>> {
+    if is_binary(data) {
+        parse_binary(data)
+    } else {
+        parse_ascii(data)
+    }
+}
+
+pub fn parse_binary(data: &[u8]) -> Result<StlMesh, Box<dyn std::error::Error>> {
+    if data.len() < HEADER_SIZE + 4 {
+        return Err("STL file is too short".into());
+    }
+
+    let header = String::from_utf8_lossy(&data[..HEADER_SIZE]);
+    let name = header
+        .trim_start_matches("solid")
+        .trim_matches(|c: char| c == '\0' || c.is_whitespace())
+        .to_string();
+
+    let count = u32::from_le_bytes([data[80], data[81], data[82], data[83]]) as usize;
+    let body = &data[HEADER_SIZE + 4..];
+
+    if body.len() < count.saturating_mul(TRIANGLE_SIZE) {
+        return Err(format!(
+            "STL file declares {} triangles but only contains data for {}",
+            count,
+            body.len() / TRIANGLE_SIZE
+        )
+        .into());
+    }
+
+    let read_vec3 = |chunk: &[u8]| {
+        let f = |i: usize| f32::from_le_bytes([chunk[i], chunk[i + 1], chunk[i + 2], chunk[i + 3]]);
+        Vec3::new(f(0), f(4), f(8))
+    };
+
+    let triangles = body
+        .chunks_exact(TRIANGLE_SIZE)
+        .take(count)
+        .map(|chunk| StlTriangle {
+            normal: read_vec3(&chunk[0..12]),
+            vertices: [
+                read_vec3(&chunk[12..24]),
+                read_vec3(&chunk[24..36]),
+                read_vec3(&chunk[36..48]),
+            ],
+        })
+        .collect();
+
+    Ok(StlMesh { name, triangles })
+}
+
+pub fn parse_ascii(data: &[u8]) -> Result<StlMesh, Box<dyn std::error::Error>> {
+    let text = std::str::from_utf8(data)?;
+    let mut name = String::new();
+    let mut triangles = Vec::new();
+    let mut normal = Vec3::ZERO;
+    let mut corners: Vec<Vec3> = Vec::with_capacity(3);
+
+    let parse_vec3 = |tokens: &[&str], line_no: usize| -> Result<Vec3, Box<dyn std::error::Error>> {
+        if tokens.len() < 3 {
+            return Err(format!("Expected three coordinates on line {}", line_no).into());
+        }
+        Ok(Vec3::new(tokens[0].parse()?, tokens[1].parse()?, tokens[2].parse()?))
+    };
+
+    for (i, line) in text.lines().enumerate() {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.first().map(|t| t.to_ascii_lowercase()).as_deref() {
+            Some("solid") => {
+                name = tokens[1..].join(" ");
+            }
+            Some("facet") => {
+                normal = if tokens.get(1).map(|t| t.eq_ignore_ascii_case("normal")) == Some(true) {
+                    parse_vec3(&tokens[2..], i + 1)?
+                } else {
+                    Vec3::ZERO
+                };
+                corners.clear();
+            }
+            Some("vertex") => {
+                corners.push(parse_vec3(&tokens[1..], i + 1)?);
+            }
+            Some("endloop") => {
+                if corners.len() < 3 {
+                    return Err(format!("Facet ending on line {} has fewer than 3 vertices", i + 1).into());
+                }
+                for k in 1..corners.len() - 1 {
+                    triangles.push(StlTriangle {
+                        normal,
+                        vertices: [corners[0], corners[k], corners[k + 1]],
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(StlMesh { name, triangles })
+}
+
+pub fn weld(triangles: &[StlTriangle]) -> (Vec<Vertex>, Vec<u32>) {
+    let mut lookup: HashMap<[u32; 3], u32> = HashMap::new();
+    let mut vertices = Vec::new();
+    let mut indices = Vec::with_capacity(triangles.len() * 3);
+
+    for triangle in triangles {
+        let [a, b, c] = triangle.vertices;
+        let winding = (b - a).cross(c - a);
+        let corners = if triangle.normal.length_squared() > 0.0 && winding.dot(triangle.normal) < 0.0 {
+            [a, c, b]
+        } else {
+            [a, b, c]
+        };
+
+        for corner in corners {
+            let key = [
+                (corner.x + 0.0).to_bits(),
+                (corner.y + 0.0).to_bits(),
+                (corner.z + 0.0).to_bits(),
+            ];
+            let index = *lookup.entry(key).or_insert_with(|| {
+                vertices.push(Vertex::new(corner, Vec3::ZERO));
+                (vertices.len() - 1) as u32
+            });
+            indices.push(index);
+        }
+    }
+
+    (vertices, indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binary(triangles: &[[f32; 12]], declared: u32) -> Vec<u8> {
+        let mut data = vec![0u8; HEADER_SIZE];
+        data[..10].copy_from_slice(b"binary box");
+        data.extend_from_slice(&declared.to_le_bytes());
+        for triangle in triangles {
+            for value in triangle {
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+            data.extend_from_slice(&[0, 0]);
+        }
+        data
+    }
+
+    fn error(result: Result<StlMesh, Box<dyn std::error::Error>>) -> String {
+        match result {
+            Ok(_) => panic!("expected an error"),
+            Err(error) => error.to_string(),
+        }
+    }
+
+    #[test]
+    fn parses_ascii_facets_and_fans_polygons() {
+        let data = b"solid my part\n facet normal 0 0 1\n  outer loop\n   vertex 0 0 0\n   vertex 1 0 0\n   vertex 1 1 0\n   vertex 0 1 0\n  endloop\n endfacet\nendsolid my part\n";
+        let mesh = parse(data).unwrap();
+        assert_eq!(mesh.name, "my part");
+        assert_eq!(mesh.triangles.len(), 2);
+        assert_eq!(mesh.triangles[1].vertices, [Vec3::ZERO, Vec3::new(1.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 0.0)]);
+        assert_eq!(mesh.triangles[0].normal, Vec3::Z);
+    }
+
+    #[test]
+    fn parses_binary_triangles() {
+        let data = binary(&[[0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]], 1);
+        assert!(is_binary(&data));
+        let mesh = parse(&data).unwrap();
+        assert_eq!(mesh.name, "binary box");
+        assert_eq!(mesh.triangles.len(), 1);
+        assert_eq!(mesh.triangles[0].vertices[1], Vec3::X);
+    }
+
+    #[test]
+    fn binary_files_whose_header_starts_with_solid_are_detected_by_size() {
+        let mut data = binary(&[[0.0; 12]], 1);
+        data[..5].copy_from_slice(b"solid");
+        assert!(is_binary(&data));
+        assert!(!is_binary(b"solid empty\nendsolid empty\n"));
+    }
+
+    #[test]
+    fn rejects_truncated_binary_data() {
+        let data = binary(&[[0.0; 12]], 3);
+        let error = error(parse_binary(&data));
+        assert!(error.contains("declares 3 triangles"), "{}", error);
+        assert!(parse_binary(&[0u8; 40]).is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_ascii() {
+        let missing = b"solid\nfacet normal 0 0 1\nouter loop\nvertex 0 0\nendloop\nendfacet\n";
+        assert!(error(parse_ascii(missing)).contains("line 4"));
+        let short = b"solid\nfacet normal 0 0 1\nouter loop\nvertex 0 0 0\nvertex 1 0 0\nendloop\nendfacet\n";
+        assert!(parse_ascii(short).is_err());
+        assert!(parse_ascii(b"solid\nfacet normal 0 0 1\nouter loop\nvertex a b c\n").is_err());
+        assert!(parse_ascii(&[b's', b'o', 0xff, 0xfe]).is_err());
+    }
+
+    #[test]
+    fn welds_shared_corners_and_follows_the_facet_normal() {
+        let quad = |normal: Vec3| {
+            [
+                StlTriangle { normal, vertices: [Vec3::ZERO, Vec3::X, Vec3::new(1.0, 1.0, 0.0)] },
+                StlTriangle { normal, vertices: [Vec3::ZERO, Vec3::new(1.0, 1.0, 0.0), Vec3::Y] },
+            ]
+        };
+        let (vertices, indices) = weld(&quad(Vec3::Z));
+        assert_eq!(vertices.len(), 4);
+        assert_eq!(indices, [0, 1, 2, 0, 2, 3]);
+
+        let (vertices, indices) = weld(&quad(-Vec3::Z));
+        for tri in indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|k| vertices[tri[k] as usize].position_vec3());
+            assert!((b - a).cross(c - a).z < 0.0);
+        }
+    }
+
+    #[test]
+    fn welds_negative_zero_with_zero() {
+        let triangles = [
+            StlTriangle { normal: Vec3::ZERO, vertices: [Vec3::ZERO, Vec3::X, Vec3::Y] },
+            StlTriangle { normal: Vec3::ZERO, vertices: [Vec3::new(-0.0, 0.0, -0.0), Vec3::Y, Vec3::Z] },
+        ];
+        let (vertices, _) = weld(&triangles);
+        assert_eq!(vertices.len(), 4);
+    }
+}
@@ -0,0 +1,24 @@
+use glam::Vec3;
+use crate::model::{Model, Vertex};
+
+/// Corner `i` of the unit cube sits at the bits of `i` as x, y and z.
+pub const CUBE_INDICES: [u32; 36] = [
+    0, 2, 3, 0, 3, 1, // -z
+    4, 5, 7, 4, 7, 6, // +z
+    0, 1, 5, 0, 5, 4, // -y
+    2, 6, 7, 2, 7, 3, // +y
+    0, 4, 6, 0, 6, 2, // -x
+    1, 3, 7, 1, 7, 5, // +x
+];
+
+pub fn cube_vertices() -> Vec<Vertex> {
+    (0..8)
+        .map(|i| Vertex::new(Vec3::new((i & 1) as f32, (i >> 1 & 1) as f32, (i >> 2 & 1) as f32), Vec3::ZERO))
+        .collect()
+}
+
+pub fn cube() -> Model {
+    let mut model = Model::from_mesh(cube_vertices(), CUBE_INDICES.to_vec(), Vec::new());
+    model.apply_normals(model.normal_settings);
+    model
+}
@@ -0,0 +1,181 @@
+use glam::Vec3;
+use std::collections::HashMap;
+use crate::model::Model;
+use crate::normals::face_normal;
+use crate::utils::position_key;
+
+/// Adjacency of a model's triangles. Model vertices at the same position
+/// share one welded vertex, so seams split for normals or UVs stay connected.
+///
+/// Half-edge `3 * face + k` runs from corner `k` of the face to corner `k + 1`.
+#[derive(Debug, Clone, Default)]
+pub struct MeshTopology {
+    pub revision: u64,
+    pub welded: Vec<u32>,
+    pub faces: Vec<[u32; 3]>,
+    pub face_normals: Vec<Vec3>,
+    /// Opposite half-edge when exactly two faces share the edge.
+    pub twins: Vec<Option<u32>>,
+    pub edge_faces: Vec<u32>,
+    vertex_faces: Vec<Vec<u32>>,
+    welded_sources: Vec<Vec<usize>>,
+    source_welded: HashMap<usize, u32>,
+}
+
+impl MeshTopology {
+    pub fn new(model: &Model) -> Self {
+        let mut ids: HashMap<[u32; 3], u32> = HashMap::new();
+        let welded: Vec<u32> = model
+            .vertices
+            .iter()
+            .map(|v| {
+                let next = ids.len() as u32;
+                *ids.entry(position_key(v.position_vec3())).or_insert(next)
+            })
+            .collect();
+        let welded_count = ids.len();
+
+        let mut welded_sources = vec![Vec::new(); welded_count];
+        let mut source_welded = HashMap::new();
+        for (index, &id) in welded.iter().enumerate() {
+            let source = model.source_vertex(index);
+            if source_welded.insert(source, id).is_none() {
+                welded_sources[id as usize].push(source);
+            }
+        }
+
+        let faces: Vec<[u32; 3]> = model
+            .indices
+            .chunks_exact(3)
+            .map(|tri| [welded[tri[0] as usize], welded[tri[1] as usize], welded[tri[2] as usize]])
+            .collect();
+        let face_normals = model
+            .indices
+            .chunks_exact(3)
+            .map(|tri| {
+                let p = |i: usize| model.vertices[tri[i] as usize].position_vec3();
+                face_normal(p(0), p(1), p(2)).unwrap_or(Vec3::ZERO)
+            })
+            .collect();
+
+        let mut vertex_faces = vec![Vec::new(); welded_count];
+        let mut edges: HashMap<(u32, u32), Vec<u32>> = HashMap::new();
+        for (face, ids) in faces.iter().enumerate() {
+            for k in 0..3 {
+                let (a, b) = (ids[k], ids[(k + 1) % 3]);
+                if !vertex_faces[a as usize].contains(&(face as u32)) {
+                    vertex_faces[a as usize].push(face as u32);
+                }
+                if a != b {
+                    edges.entry((a.min(b), a.max(b))).or_default().push((face * 3 + k) as u32);
+                }
+            }
+        }
+
+        let mut twins = vec![None; faces.len() * 3];
+        let mut edge_faces = vec![0; faces.len() * 3];
+        for uses in edges.values() {
+            for &half_edge in uses {
+                edge_faces[half_edge as usize] = uses.len() as u32;
+            }
+            if let [a, b] = uses[..] {
+                twins[a as usize] = Some(b);
+                twins[b as usize] = Some(a);
+            }
+        }
+
+        Self {
+            revision: model.revision,
+            welded,
+            faces,
+            face_normals,
+            twins,
+            edge_faces,
+            vertex_faces,
+            welded_sources,
+            source_welded,
+        }
+    }
+
+    pub fn welded_count(&self) -> usize {
+        self.vertex_faces.len()
+    }
+
+    pub fn vertex_faces(&self, vertex: u32) -> &[u32] {
+        &self.vertex_faces[vertex as usize]
+    }
+
+    pub fn vertex_neighbors(&self, vertex: u32) -> impl Iterator<Item = u32> + '_ {
+        self.vertex_faces(vertex)
+            .iter()
+            .flat_map(move |&face| self.faces[face as usize])
+            .filter(move |&other| other != vertex)
+    }
+
+    pub fn face_neighbors(&self, face: usize) -> impl Iterator<Item = usize> + '_ {
+        let ids = self.faces[face];
+        (0..3).flat_map(move |k| {
+            let (a, b) = (ids[k], ids[(k + 1) % 3]);
+            self.vertex_faces(a)
+                .iter()
+                .map(|&other| other as usize)
+                .filter(move |&other| other != face && self.faces[other].contains(&b))
+        })
+    }
+
+    pub fn is_boundary(&self, half_edge: usize) -> bool {
+        self.edge_faces[half_edge] == 1
+    }
+
+    pub fn sources(&self, vertex: u32) -> &[usize] {
+        &self.welded_sources[vertex as usize]
+    }
+
+    pub fn welded_of_source(&self, source: usize) -> Option<u32> {
+        self.source_welded.get(&source).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_meshes::{cube, cube_vertices, CUBE_INDICES};
+
+    #[test]
+    fn welds_split_vertices_of_a_closed_cube() {
+        let model = cube();
+        let topology = MeshTopology::new(&model);
+        assert_eq!(topology.welded_count(), 8);
+        assert_eq!(topology.faces.len(), 12);
+        assert!(topology.twins.iter().all(Option::is_some));
+        assert!((0..36).all(|half_edge| !topology.is_boundary(half_edge)));
+        assert_eq!(topology.face_normals[0], Vec3::NEG_Z);
+
+        let corner = topology.welded_of_source(0).unwrap();
+        let mut neighbors: Vec<u32> = topology.vertex_neighbors(corner).collect();
+        neighbors.sort_unstable();
+        neighbors.dedup();
+        assert_eq!(neighbors.len(), 6);
+        assert_eq!(topology.face_neighbors(0).count(), 3);
+    }
+
+    #[test]
+    fn finds_the_boundary_of_an_open_box() {
+        let model = Model::from_mesh(cube_vertices(), CUBE_INDICES[..30].to_vec(), Vec::new());
+        let topology = MeshTopology::new(&model);
+        let boundary: Vec<usize> = (0..30).filter(|&half_edge| topology.is_boundary(half_edge)).collect();
+        assert_eq!(boundary.len(), 4);
+        assert!(boundary.iter().all(|&half_edge| topology.twins[half_edge].is_none()));
+    }
+
+    #[test]
+    fn counts_faces_on_non_manifold_edges() {
+        let mut indices = CUBE_INDICES.to_vec();
+        indices.extend_from_slice(&[0, 3, 7]);
+        let model = Model::from_mesh(cube_vertices(), indices, Vec::new());
+        let topology = MeshTopology::new(&model);
+        // The diagonal 0-3 of the bottom face now has three faces.
+        assert_eq!(topology.edge_faces[36], 3);
+        assert_eq!(topology.twins[36], None);
+    }
+}
@@ -1,10 +1,11 @@
-use glam::{Mat4, Vec3};
+use glam::{EulerRot, Mat4, Quat, Vec3};
 
 #[derive(Debug, Clone)]
 pub struct Transform {
     pub translation: Vec3,
     pub rotation: Vec3,
     pub scale: Vec3,
+    pub pivot: Vec3,
 }
 
 impl Default for Transform {
@@ -13,6 +14,7 @@ impl Default for Transform {
             translation: Vec3::ZERO,
             rotation: Vec3::ZERO,
             scale: Vec3::ONE,
+            pivot: Vec3::ZERO,
         }
     }
 }
@@ -35,13 +37,49 @@ impl Transform {
     }
 
     pub fn to_matrix(&self) -> Mat4 {
-        let translation_mat = Mat4::from_translation(self.translation);
+        let translation_mat = Mat4::from_translation(self.translation + self.pivot);
         let rotation_x = Mat4::from_rotation_x(self.rotation.x);
         let rotation_y = Mat4::from_rotation_y(self.rotation.y);
         let rotation_z = Mat4::from_rotation_z(self.rotation.z);
         let scale_mat = Mat4::from_scale(self.scale);
-        
-        translation_mat * rotation_z * rotation_y * rotation_x * scale_mat
+        let pivot_mat = Mat4::from_translation(-self.pivot);
+
+        translation_mat * rotation_z * rotation_y * rotation_x * scale_mat * pivot_mat
+    }
+
+    pub fn rotation_quat(&self) -> Quat {
+        Quat::from_euler(EulerRot::ZYX, self.rotation.z, self.rotation.y, self.rotation.x)
+    }
+
+    pub fn set_rotation_quat(&mut self, rotation: Quat) {
+        let (z, y, x) = rotation.to_euler(EulerRot::ZYX);
+        self.rotation = Vec3::new(x, y, z);
+    }
+
+    /// One keyboard step of `tool` along `axis`: `step` units, 15° or 10%.
+    pub fn nudge(&mut self, tool: TransformTool, axis: Vec3, step: f32) {
+        match tool {
+            TransformTool::Translate => self.translate(axis * step),
+            TransformTool::Rotate => self.rotate(axis * 15f32.to_radians()),
+            TransformTool::Scale => {
+                let factor = if axis.max_element() > 0.0 { 1.1 } else { 1.0 / 1.1 };
+                self.scale_by(Vec3::ONE + axis.abs() * (factor - 1.0));
+            }
+            TransformTool::None => {}
+        }
+    }
+
+    /// Applies the change from `before` to `after` on top of this transform,
+    /// so several objects can follow one that is being edited.
+    pub fn apply_change(&mut self, before: &Transform, after: &Transform) {
+        self.translation += after.translation - before.translation;
+        self.rotation += after.rotation - before.rotation;
+        let ratio = Vec3::select(before.scale.cmpeq(Vec3::ZERO), Vec3::ONE, after.scale / before.scale);
+        self.scale *= ratio;
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.translation == Vec3::ZERO && self.rotation == Vec3::ZERO && self.scale == Vec3::ONE
     }
 
     pub fn reset(&mut self) {
@@ -51,3 +89,32 @@ impl Transform {
     }
 }
 
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TransformTool {
+    #[default]
+    None,
+    Translate,
+    Rotate,
+    Scale,
+}
+
+impl TransformTool {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TransformTool::None => "none",
+            TransformTool::Translate => "translate",
+            TransformTool::Rotate => "rotate",
+            TransformTool::Scale => "scale",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            TransformTool::None => TransformTool::Translate,
+            TransformTool::Translate => TransformTool::Rotate,
+            TransformTool::Rotate => TransformTool::Scale,
+            TransformTool::Scale => TransformTool::None,
+        }
+    }
+}
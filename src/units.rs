@@ -0,0 +1,51 @@
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Unit {
+    #[default]
+    Millimeter,
+    Centimeter,
+    Meter,
+    Inch,
+}
+
+impl Unit {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "mm" | "millimeter" | "millimeters" => Some(Unit::Millimeter),
+            "cm" | "centimeter" | "centimeters" => Some(Unit::Centimeter),
+            "m" | "meter" | "meters" => Some(Unit::Meter),
+            "in" | "inch" | "inches" => Some(Unit::Inch),
+            _ => None,
+        }
+    }
+
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            Unit::Millimeter => "mm",
+            Unit::Centimeter => "cm",
+            Unit::Meter => "m",
+            Unit::Inch => "in",
+        }
+    }
+
+    pub fn millimeters(&self) -> f32 {
+        match self {
+            Unit::Millimeter => 1.0,
+            Unit::Centimeter => 10.0,
+            Unit::Meter => 1000.0,
+            Unit::Inch => 25.4,
+        }
+    }
+
+    pub fn convert(&self, value: f32, to: Unit) -> f32 {
+        value * self.millimeters() / to.millimeters()
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            Unit::Millimeter => Unit::Centimeter,
+            Unit::Centimeter => Unit::Meter,
+            Unit::Meter => Unit::Inch,
+            Unit::Inch => Unit::Millimeter,
+        }
+    }
+}
@@ -7,6 +7,38 @@ pub fn color_to_rgba(color: u32) -> [f32; 4] {
     [r, g, b, 1.0]
 }
 
+pub fn srgb_to_linear(color: [f32; 4]) -> [f32; 4] {
+    let channel = |c: f32| {
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    [channel(color[0]), channel(color[1]), channel(color[2]), color[3]]
+}
+
+pub fn color_map(t: f32) -> [f32; 4] {
+    const STOPS: [[f32; 3]; 5] = [
+        [0.0, 0.0, 1.0],
+        [0.0, 1.0, 1.0],
+        [0.0, 1.0, 0.0],
+        [1.0, 1.0, 0.0],
+        [1.0, 0.0, 0.0],
+    ];
+    let scaled = t.clamp(0.0, 1.0) * (STOPS.len() - 1) as f32;
+    let i = (scaled.floor() as usize).min(STOPS.len() - 2);
+    let f = scaled - i as f32;
+    let a = STOPS[i];
+    let b = STOPS[i + 1];
+    [
+        a[0] + (b[0] - a[0]) * f,
+        a[1] + (b[1] - a[1]) * f,
+        a[2] + (b[2] - a[2]) * f,
+        1.0,
+    ]
+}
+
 pub fn normalize_vec3(v: Vec3) -> Vec3 {
     let len = v.length();
     if len > 0.0 {
@@ -16,6 +48,10 @@ pub fn normalize_vec3(v: Vec3) -> Vec3 {
     }
 }
 
+pub fn position_key(p: Vec3) -> [u32; 3] {
+    [(p.x + 0.0).to_bits(), (p.y + 0.0).to_bits(), (p.z + 0.0).to_bits()]
+}
+
 pub fn calculate_center(vertices: &[Vec3]) -> Vec3 {
     if vertices.is_empty() {
         return Vec3::ZERO;
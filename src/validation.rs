@@ -0,0 +1,301 @@
+use std::collections::{HashMap, HashSet};
+use crate::model::{next_revision, Vertex};
+use crate::normals::face_normal;
+use crate::utils::position_key;
+
+const BOUNDARY_COLOR: [f32; 4] = [1.0, 1.0, 0.0, 1.0];
+const NON_MANIFOLD_COLOR: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+const WINDING_COLOR: [f32; 4] = [1.0, 0.0, 1.0, 1.0];
+const DEGENERATE_COLOR: [f32; 4] = [1.0, 0.5, 0.0, 1.0];
+const DUPLICATE_COLOR: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+
+#[derive(Debug, Clone, Default)]
+pub struct MeshReport {
+    pub boundary_edges: Vec<[u32; 2]>,
+    pub boundary_loops: usize,
+    pub non_manifold_edges: Vec<[u32; 2]>,
+    pub non_manifold_vertices: Vec<u32>,
+    pub degenerate_faces: Vec<usize>,
+    pub duplicate_faces: Vec<usize>,
+    pub inconsistent_edges: Vec<[u32; 2]>,
+    pub components: usize,
+}
+
+impl MeshReport {
+    pub fn is_watertight(&self) -> bool {
+        self.boundary_edges.is_empty() && self.non_manifold_edges.is_empty()
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.is_watertight()
+            && self.non_manifold_vertices.is_empty()
+            && self.degenerate_faces.is_empty()
+            && self.duplicate_faces.is_empty()
+            && self.inconsistent_edges.is_empty()
+    }
+
+    pub fn format_report(&self) -> String {
+        format!(
+            "Watertight: {}\nBoundary edges: {}\nBoundary loops: {}\nNon-manifold edges: {}\nNon-manifold vertices: {}\nDegenerate faces: {}\nDuplicate faces: {}\nInconsistent winding edges: {}\nComponents: {}",
+            if self.is_watertight() { "yes" } else { "no" },
+            self.boundary_edges.len(),
+            self.boundary_loops,
+            self.non_manifold_edges.len(),
+            self.non_manifold_vertices.len(),
+            self.degenerate_faces.len(),
+            self.duplicate_faces.len(),
+            self.inconsistent_edges.len(),
+            self.components,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MeshHighlight {
+    pub revision: u64,
+    pub lines: Vec<Vertex>,
+    pub triangles: Vec<Vertex>,
+    pub points: Vec<Vertex>,
+}
+
+impl MeshHighlight {
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty() && self.triangles.is_empty() && self.points.is_empty()
+    }
+}
+
+fn find(parent: &mut [u32], mut x: u32) -> u32 {
+    while parent[x as usize] != x {
+        parent[x as usize] = parent[parent[x as usize] as usize];
+        x = parent[x as usize];
+    }
+    x
+}
+
+fn union(parent: &mut [u32], a: u32, b: u32) {
+    let (a, b) = (find(parent, a), find(parent, b));
+    if a != b {
+        parent[a as usize] = b;
+    }
+}
+
+fn count_roots(parent: &mut [u32], members: impl Iterator<Item = u32>) -> usize {
+    members.map(|m| find(parent, m)).collect::<HashSet<_>>().len()
+}
+
+pub fn validate(vertices: &[Vertex], indices: &[u32]) -> MeshReport {
+    let mut report = MeshReport::default();
+
+    let mut ids: HashMap<[u32; 3], u32> = HashMap::new();
+    let mut representative = Vec::new();
+    let position_ids: Vec<u32> = vertices
+        .iter()
+        .enumerate()
+        .map(|(i, v)| {
+            *ids.entry(position_key(v.position_vec3())).or_insert_with(|| {
+                representative.push(i as u32);
+                (representative.len() - 1) as u32
+            })
+        })
+        .collect();
+    let edge = |a: u32, b: u32| [representative[a as usize], representative[b as usize]];
+
+    let faces: Vec<[u32; 3]> = indices
+        .chunks_exact(3)
+        .map(|tri| [position_ids[tri[0] as usize], position_ids[tri[1] as usize], position_ids[tri[2] as usize]])
+        .collect();
+
+    let mut seen = HashSet::new();
+    let mut valid = Vec::with_capacity(faces.len());
+    for (face, (ids, tri)) in faces.iter().zip(indices.chunks_exact(3)).enumerate() {
+        let positions = [
+            vertices[tri[0] as usize].position_vec3(),
+            vertices[tri[1] as usize].position_vec3(),
+            vertices[tri[2] as usize].position_vec3(),
+        ];
+        let repeated = ids[0] == ids[1] || ids[1] == ids[2] || ids[0] == ids[2];
+        if repeated || face_normal(positions[0], positions[1], positions[2]).is_none() {
+            report.degenerate_faces.push(face);
+            continue;
+        }
+        let mut sorted = *ids;
+        sorted.sort_unstable();
+        if !seen.insert(sorted) {
+            report.duplicate_faces.push(face);
+            continue;
+        }
+        valid.push(face);
+    }
+
+    let mut edges: HashMap<(u32, u32), Vec<(usize, bool)>> = HashMap::new();
+    for &face in &valid {
+        let ids = faces[face];
+        for k in 0..3 {
+            let (a, b) = (ids[k], ids[(k + 1) % 3]);
+            edges.entry((a.min(b), a.max(b))).or_default().push((face, a < b));
+        }
+    }
+
+    let mut boundary_parent: Vec<u32> = (0..representative.len() as u32).collect();
+    let mut boundary_vertices = HashSet::new();
+    for (&(a, b), uses) in &edges {
+        match uses.len() {
+            1 => {
+                report.boundary_edges.push(edge(a, b));
+                union(&mut boundary_parent, a, b);
+                boundary_vertices.insert(a);
+            }
+            2 if uses[0].1 == uses[1].1 => report.inconsistent_edges.push(edge(a, b)),
+            2 => {}
+            _ => report.non_manifold_edges.push(edge(a, b)),
+        }
+    }
+    report.boundary_loops = count_roots(&mut boundary_parent, boundary_vertices.into_iter());
+
+    let mut vertex_faces: HashMap<u32, Vec<usize>> = HashMap::new();
+    for &face in &valid {
+        for id in faces[face] {
+            vertex_faces.entry(id).or_default().push(face);
+        }
+    }
+    for (&vertex, incident) in &vertex_faces {
+        let mut parent: Vec<u32> = (0..incident.len() as u32).collect();
+        let mut by_neighbor: HashMap<u32, u32> = HashMap::new();
+        for (local, &face) in incident.iter().enumerate() {
+            for other in faces[face].into_iter().filter(|&id| id != vertex) {
+                match by_neighbor.get(&other) {
+                    Some(&first) => union(&mut parent, first, local as u32),
+                    None => {
+                        by_neighbor.insert(other, local as u32);
+                    }
+                }
+            }
+        }
+        if count_roots(&mut parent, 0..incident.len() as u32) > 1 {
+            report.non_manifold_vertices.push(representative[vertex as usize]);
+        }
+    }
+
+    let mut component_parent: Vec<u32> = (0..representative.len() as u32).collect();
+    for ids in &faces {
+        union(&mut component_parent, ids[0], ids[1]);
+        union(&mut component_parent, ids[1], ids[2]);
+    }
+    report.components = count_roots(&mut component_parent, faces.iter().flatten().copied());
+
+    report.boundary_edges.sort_unstable();
+    report.non_manifold_edges.sort_unstable();
+    report.inconsistent_edges.sort_unstable();
+    report.non_manifold_vertices.sort_unstable();
+    report
+}
+
+pub fn highlight(vertices: &[Vertex], indices: &[u32], report: &MeshReport) -> MeshHighlight {
+    let mut result = MeshHighlight {
+        revision: next_revision(),
+        ..Default::default()
+    };
+    let colored = |index: u32, color: [f32; 4]| {
+        let mut vertex = vertices[index as usize];
+        vertex.color = color;
+        vertex
+    };
+
+    for (edges, color) in [
+        (&report.boundary_edges, BOUNDARY_COLOR),
+        (&report.non_manifold_edges, NON_MANIFOLD_COLOR),
+        (&report.inconsistent_edges, WINDING_COLOR),
+    ] {
+        for &[a, b] in edges {
+            result.lines.push(colored(a, color));
+            result.lines.push(colored(b, color));
+        }
+    }
+
+    for &face in &report.degenerate_faces {
+        let tri = &indices[face * 3..face * 3 + 3];
+        for k in 0..3 {
+            result.lines.push(colored(tri[k], DEGENERATE_COLOR));
+            result.lines.push(colored(tri[(k + 1) % 3], DEGENERATE_COLOR));
+        }
+    }
+
+    for &face in &report.duplicate_faces {
+        for &index in &indices[face * 3..face * 3 + 3] {
+            result.triangles.push(colored(index, DUPLICATE_COLOR));
+        }
+    }
+
+    for &vertex in &report.non_manifold_vertices {
+        result.points.push(colored(vertex, NON_MANIFOLD_COLOR));
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Vec3;
+    use crate::test_meshes::{cube_vertices, CUBE_INDICES};
+
+    #[test]
+    fn a_closed_cube_is_clean() {
+        let report = validate(&cube_vertices(), &CUBE_INDICES);
+        assert!(report.is_clean(), "{}", report.format_report());
+        assert_eq!(report.components, 1);
+    }
+
+    #[test]
+    fn split_corners_are_compared_by_position() {
+        let vertices: Vec<Vertex> = CUBE_INDICES.iter().map(|&i| cube_vertices()[i as usize]).collect();
+        let indices: Vec<u32> = (0..vertices.len() as u32).collect();
+        assert!(validate(&vertices, &indices).is_clean());
+    }
+
+    #[test]
+    fn an_open_box_has_one_boundary_loop() {
+        let indices: Vec<u32> = CUBE_INDICES[..6].iter().chain(&CUBE_INDICES[12..]).copied().collect();
+        let report = validate(&cube_vertices(), &indices);
+        assert!(!report.is_watertight());
+        assert_eq!(report.boundary_edges.len(), 4);
+        assert_eq!(report.boundary_loops, 1);
+        assert!(report.inconsistent_edges.is_empty());
+    }
+
+    #[test]
+    fn reports_degenerate_and_duplicate_faces() {
+        let mut indices = CUBE_INDICES.to_vec();
+        indices.extend_from_slice(&[0, 0, 1, 2, 3, 0]);
+        let report = validate(&cube_vertices(), &indices);
+        assert_eq!(report.degenerate_faces, [12]);
+        assert_eq!(report.duplicate_faces, [13]);
+        assert!(report.is_watertight());
+    }
+
+    #[test]
+    fn reports_inconsistent_winding() {
+        let mut indices = CUBE_INDICES.to_vec();
+        indices.swap(1, 2);
+        let report = validate(&cube_vertices(), &indices);
+        assert_eq!(report.inconsistent_edges, [[0, 2], [0, 3], [2, 3]]);
+        assert!(report.is_watertight());
+        assert!(!report.is_clean());
+    }
+
+    #[test]
+    fn reports_non_manifold_edges_and_vertices() {
+        let mut vertices = cube_vertices();
+        vertices.push(Vertex::new(Vec3::new(0.5, -1.0, -1.0), Vec3::ZERO));
+        let mut indices = CUBE_INDICES.to_vec();
+        indices.extend_from_slice(&[0, 1, 8]);
+        let report = validate(&vertices, &indices);
+        assert_eq!(report.non_manifold_edges, [[0, 1]]);
+        assert_eq!(report.boundary_edges.len(), 2);
+
+        let bowtie = [Vec3::ZERO, Vec3::X, Vec3::Y, -Vec3::X, -Vec3::Y].map(|p| Vertex::new(p, Vec3::ZERO));
+        let report = validate(&bowtie, &[0, 1, 2, 0, 3, 4]);
+        assert_eq!(report.non_manifold_vertices, [0]);
+        assert_eq!(report.components, 1);
+    }
+}
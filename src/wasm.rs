@@ -3,12 +3,15 @@ use wasm_bindgen::prelude::*;
 #[cfg(target_arch = "wasm32")]
 use web_sys::{HtmlCanvasElement, Window, Document};
 #[cfg(target_arch = "wasm32")]
-use crate::{App, model::Model};
+use crate::{App, model::Model, export::ExportFormat, normals::NormalMode, units::Unit};
+#[cfg(target_arch = "wasm32")]
+use std::collections::HashMap;
 
 #[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
 pub struct WasmViewer {
     app: App,
+    resources: HashMap<String, Vec<u8>>,
 }
 
 #[cfg(target_arch = "wasm32")]
@@ -21,15 +24,56 @@ impl WasmViewer {
         
         Ok(WasmViewer {
             app: App::new(),
+            resources: HashMap::new(),
         })
     }
 
     #[wasm_bindgen]
     pub fn load_model_from_bytes(&mut self, data: &[u8]) -> Result<(), JsValue> {
-        self.app.load_file_from_bytes(data, "obj")
+        self.app.load_file_from_bytes_with_resources(data, "obj", &self.resources)
             .map_err(|e| JsValue::from_str(&format!("Failed to load model: {}", e)))
     }
 
+    #[wasm_bindgen]
+    pub fn load_model_from_bytes_as(&mut self, data: &[u8], extension: &str) -> Result<(), JsValue> {
+        self.app.load_file_from_bytes_with_resources(data, extension, &self.resources)
+            .map_err(|e| JsValue::from_str(&format!("Failed to load model: {}", e)))
+    }
+
+    #[wasm_bindgen]
+    pub fn export_model(&self, format: &str) -> Result<Vec<u8>, JsValue> {
+        let format = ExportFormat::from_name(format)
+            .ok_or_else(|| JsValue::from_str(&format!("Unknown export format: {}", format)))?;
+        self.app.export_model_to_bytes(format)
+            .map_err(|e| JsValue::from_str(&format!("Failed to export model: {}", e)))
+    }
+
+    #[wasm_bindgen]
+    pub fn set_unit(&mut self, unit: &str) -> Result<(), JsValue> {
+        let unit = Unit::from_name(unit)
+            .ok_or_else(|| JsValue::from_str(&format!("Unknown unit: {}", unit)))?;
+        self.app.set_unit(unit);
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn set_normal_mode(&mut self, mode: &str) -> Result<(), JsValue> {
+        let mode = NormalMode::from_name(mode)
+            .ok_or_else(|| JsValue::from_str(&format!("Unknown normal mode: {}", mode)))?;
+        self.app.set_normal_mode(mode);
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn add_resource(&mut self, name: &str, data: &[u8]) {
+        self.resources.insert(name.to_string(), data.to_vec());
+    }
+
+    #[wasm_bindgen]
+    pub fn clear_resources(&mut self) {
+        self.resources.clear();
+    }
+
     #[wasm_bindgen]
     pub fn handle_keyboard(&mut self, key: &str) {
         self.app.handle_keyboard(key);
@@ -61,14 +105,24 @@ impl WasmViewer {
     }
 
     #[wasm_bindgen]
-    pub fn get_model_info(&self) -> Option<String> {
-        self.app.model_info.as_ref().map(|info| info.format_info())
+    pub fn get_model_info(&mut self) -> Option<String> {
+        self.app.info_text()
     }
 
     #[wasm_bindgen]
     pub fn has_model(&self) -> bool {
         self.app.model.is_some()
     }
+
+    #[wasm_bindgen]
+    pub fn get_part_names(&self) -> Vec<String> {
+        self.app.part_names()
+    }
+
+    #[wasm_bindgen]
+    pub fn toggle_part(&mut self, index: usize) -> bool {
+        self.app.toggle_part(index)
+    }
 }
 
 #[cfg(target_arch = "wasm32")]